@@ -1,6 +1,13 @@
 use anyhow::Result;
 use async_trait::async_trait;
 use reqsign_core::FileRead;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::SystemTime;
+use tokio::io::AsyncWriteExt;
+
+/// Counter to make the temporary file of every write unique in this process.
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
 
 #[derive(Debug, Clone, Copy, Default)]
 pub struct TokioFileRead;
@@ -10,4 +17,105 @@ impl FileRead for TokioFileRead {
     async fn file_read(&self, path: &str) -> Result<Vec<u8>> {
         tokio::fs::read(path).await.map_err(Into::into)
     }
+
+    async fn file_modified(&self, path: &str) -> Result<Option<SystemTime>> {
+        Ok(Some(tokio::fs::metadata(path).await?.modified()?))
+    }
+
+    /// Write into a temporary file first, then rename it to the target path
+    /// so that readers never see a partially written file.
+    ///
+    /// On unix, new directories are created with mode `0700` and the file
+    /// with mode `0600`.
+    async fn file_write(&self, path: &str, content: &[u8]) -> Result<()> {
+        if let Some(dir) = Path::new(path).parent() {
+            let mut builder = tokio::fs::DirBuilder::new();
+            builder.recursive(true);
+            #[cfg(unix)]
+            builder.mode(0o700);
+            builder.create(dir).await?;
+        }
+
+        // Concurrent writers to the same path must not share the temporary
+        // file, the last rename wins.
+        let tmp = format!(
+            "{path}.{}.{}.tmp",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        );
+        let mut options = tokio::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+
+        let result = async {
+            let mut file = options.open(&tmp).await?;
+            file.write_all(content).await?;
+            file.sync_all().await?;
+            tokio::fs::rename(&tmp, path).await
+        }
+        .await;
+        if result.is_err() {
+            let _ = tokio::fs::remove_file(&tmp).await;
+        }
+        result.map_err(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_file_write() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("cache/credential.json");
+        let path = path.to_string_lossy();
+
+        TokioFileRead.file_write(&path, b"secret").await?;
+        TokioFileRead.file_write(&path, b"new secret").await?;
+        assert_eq!(TokioFileRead.file_read(&path).await?, b"new secret");
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(path.as_ref())?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+            let mode = std::fs::metadata(dir.path().join("cache"))?
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_file_write_concurrently() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("credential.json");
+        let path = path.to_string_lossy().to_string();
+
+        let contents: Vec<_> = (0..32).map(|i| format!("secret {i}")).collect();
+        let tasks: Vec<_> = contents
+            .iter()
+            .cloned()
+            .map(|content| {
+                let path = path.clone();
+                tokio::spawn(
+                    async move { TokioFileRead.file_write(&path, content.as_bytes()).await },
+                )
+            })
+            .collect();
+        for task in tasks {
+            task.await??;
+        }
+
+        // The file is written by one of the writers as a whole.
+        let content = String::from_utf8(TokioFileRead.file_read(&path).await?)?;
+        assert!(contents.contains(&content), "{content}");
+        // No temporary files are left behind.
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+        Ok(())
+    }
 }
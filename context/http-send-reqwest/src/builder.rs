@@ -0,0 +1,134 @@
+use crate::ReqwestHttpSend;
+use reqsign_core::Error;
+use reqwest::{Certificate, Client};
+
+/// ReqwestHttpSendBuilder builds a [`ReqwestHttpSend`] with the TLS backend
+/// enabled by features.
+///
+/// - `rustls-tls-webpki-roots`: rustls with the Mozilla root certificates
+///   bundled by webpki-roots.
+/// - `rustls-tls-native-roots`: rustls with the root certificates of the
+///   platform, like `/etc/ssl/certs` on Linux.
+/// - `native-tls`: the platform TLS library, like OpenSSL on Linux, which
+///   trusts the root certificates of the platform. It's only used if none
+///   of the rustls features is enabled.
+///
+/// This is useful behind TLS-intercepting proxies in enterprise networks,
+/// add the proxy's CA certificate or the system bundle like
+/// `/etc/ssl/certs/ca-certificates.crt` via
+/// [`ReqwestHttpSendBuilder::with_root_certificates`], and pass the result
+/// to [`reqsign_core::Context::new`].
+#[derive(Debug)]
+pub struct ReqwestHttpSendBuilder {
+    #[cfg(feature = "rustls-tls-webpki-roots")]
+    webpki_roots: bool,
+    #[cfg(feature = "rustls-tls-native-roots")]
+    native_roots: bool,
+    root_certificates: Vec<Certificate>,
+}
+
+impl Default for ReqwestHttpSendBuilder {
+    fn default() -> Self {
+        Self {
+            #[cfg(feature = "rustls-tls-webpki-roots")]
+            webpki_roots: true,
+            #[cfg(feature = "rustls-tls-native-roots")]
+            native_roots: true,
+            root_certificates: Vec::new(),
+        }
+    }
+}
+
+impl ReqwestHttpSendBuilder {
+    /// Set whether to trust the Mozilla root certificates from webpki-roots,
+    /// enabled by default.
+    ///
+    /// Disable it to only trust the certificates added via
+    /// [`ReqwestHttpSendBuilder::with_root_certificates`].
+    #[cfg(feature = "rustls-tls-webpki-roots")]
+    pub fn with_webpki_roots(mut self, enabled: bool) -> Self {
+        self.webpki_roots = enabled;
+        self
+    }
+
+    /// Set whether to trust the root certificates of the platform, enabled
+    /// by default.
+    ///
+    /// Disable it to only trust the certificates added via
+    /// [`ReqwestHttpSendBuilder::with_root_certificates`].
+    #[cfg(feature = "rustls-tls-native-roots")]
+    pub fn with_native_roots(mut self, enabled: bool) -> Self {
+        self.native_roots = enabled;
+        self
+    }
+
+    /// Trust the PEM encoded certificates, the bundle could contain more
+    /// than one certificate.
+    ///
+    /// They're trusted in addition to the built-in root certificates.
+    pub fn with_root_certificates(mut self, pem: &[u8]) -> anyhow::Result<Self> {
+        let certs = Certificate::from_pem_bundle(pem)
+            .map_err(|e| Error::Config(format!("invalid root certificates: {e}")))?;
+        if certs.is_empty() {
+            return Err(Error::Config("no root certificate found in pem".to_string()).into());
+        }
+        self.root_certificates.extend(certs);
+        Ok(self)
+    }
+
+    /// Build the [`ReqwestHttpSend`].
+    pub fn build(self) -> anyhow::Result<ReqwestHttpSend> {
+        let mut builder = Client::builder();
+        #[cfg(any(
+            feature = "rustls-tls-webpki-roots",
+            feature = "rustls-tls-native-roots"
+        ))]
+        {
+            builder = builder.use_rustls_tls();
+        }
+        #[cfg(not(any(
+            feature = "rustls-tls-webpki-roots",
+            feature = "rustls-tls-native-roots"
+        )))]
+        {
+            builder = builder.use_native_tls();
+        }
+        #[cfg(feature = "rustls-tls-webpki-roots")]
+        {
+            builder = builder.tls_built_in_webpki_certs(self.webpki_roots);
+        }
+        #[cfg(feature = "rustls-tls-native-roots")]
+        {
+            builder = builder.tls_built_in_native_certs(self.native_roots);
+        }
+        for cert in self.root_certificates {
+            builder = builder.add_root_certificate(cert);
+        }
+        Ok(ReqwestHttpSend::new(builder.build()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_with_root_certificates() -> anyhow::Result<()> {
+        let pem = include_bytes!("../testdata/ca.pem");
+        let builder = ReqwestHttpSend::builder();
+        #[cfg(feature = "rustls-tls-webpki-roots")]
+        let builder = builder.with_webpki_roots(false);
+        #[cfg(feature = "rustls-tls-native-roots")]
+        let builder = builder.with_native_roots(false);
+        builder.with_root_certificates(pem)?.build()?;
+
+        let err = ReqwestHttpSend::builder()
+            .with_root_certificates(b"not a certificate")
+            .expect_err("invalid pem must fail");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Config(_))
+        ));
+        Ok(())
+    }
+}
@@ -1,9 +1,22 @@
 use async_trait::async_trait;
 use bytes::Bytes;
 use http_body_util::BodyExt;
-use reqsign_core::HttpSend;
+use reqsign_core::{Error, HttpSend, HttpTimeout};
 use reqwest::{Client, Request};
 
+#[cfg(any(
+    feature = "rustls-tls-webpki-roots",
+    feature = "rustls-tls-native-roots",
+    feature = "native-tls"
+))]
+mod builder;
+#[cfg(any(
+    feature = "rustls-tls-webpki-roots",
+    feature = "rustls-tls-native-roots",
+    feature = "native-tls"
+))]
+pub use builder::ReqwestHttpSendBuilder;
+
 #[derive(Debug, Default)]
 pub struct ReqwestHttpSend {
     client: Client,
@@ -14,16 +27,73 @@ impl ReqwestHttpSend {
     pub fn new(client: Client) -> Self {
         Self { client }
     }
+
+    /// Create a builder to configure the TLS root certificates used for
+    /// credential requests.
+    #[cfg(any(
+        feature = "rustls-tls-webpki-roots",
+        feature = "rustls-tls-native-roots",
+        feature = "native-tls"
+    ))]
+    pub fn builder() -> ReqwestHttpSendBuilder {
+        ReqwestHttpSendBuilder::default()
+    }
 }
 
 #[async_trait]
 impl HttpSend for ReqwestHttpSend {
     async fn http_send(&self, req: http::Request<Bytes>) -> anyhow::Result<http::Response<Bytes>> {
-        let req = Request::try_from(req)?;
-        let resp: http::Response<_> = self.client.execute(req).await?.into();
+        let timeout = req.extensions().get::<HttpTimeout>().copied();
+        let mut req = Request::try_from(req)?;
+        // reqwest only supports connect timeout per client, so both timeouts
+        // are applied as the total timeout of this request.
+        if let Some(total) = timeout.and_then(|v| v.total()) {
+            *req.timeout_mut() = Some(total);
+        }
+
+        let resp: http::Response<_> = match self.client.execute(req).await {
+            Ok(resp) => resp.into(),
+            Err(err) if err.is_timeout() => return Err(Error::Timeout(err.to_string()).into()),
+            Err(err) => return Err(err.into()),
+        };
 
         let (parts, body) = resp.into_parts();
         let bs = BodyExt::collect(body).await.map(|buf| buf.to_bytes())?;
         Ok(http::Response::from_parts(parts, bs))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_http_send_timeout() -> anyhow::Result<()> {
+        // Accept connections but never respond.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        tokio::spawn(async move {
+            let mut conns = Vec::new();
+            while let Ok((conn, _)) = listener.accept().await {
+                conns.push(conn);
+            }
+        });
+
+        let req = http::Request::get(format!("http://{addr}/"))
+            .extension(HttpTimeout::new(
+                Duration::from_millis(100),
+                Duration::from_millis(100),
+            ))
+            .body(Bytes::new())?;
+        let err = ReqwestHttpSend::default()
+            .http_send(req)
+            .await
+            .expect_err("request must time out");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Timeout(_))
+        ));
+        Ok(())
+    }
+}
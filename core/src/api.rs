@@ -1,3 +1,4 @@
+use crate::time::DateTime;
 use crate::Context;
 use std::fmt::Debug;
 use std::time::Duration;
@@ -6,6 +7,25 @@ use std::time::Duration;
 pub trait Key: Clone + Debug + Send + Sync + Unpin + 'static {
     /// Check if the key is valid.
     fn is_valid(&self) -> bool;
+
+    /// Check if the key is valid at `now`.
+    ///
+    /// [`Signer`](crate::Signer) calls this with [`Context::now`], so keys
+    /// that expire should override it to make expiry follow the context
+    /// clock, like [`FixedClock`](crate::FixedClock) in tests. Falls back to
+    /// [`Key::is_valid`] by default.
+    fn is_valid_at(&self, now: DateTime) -> bool {
+        let _ = now;
+        self.is_valid()
+    }
+
+    /// Get the expiration time of the key.
+    ///
+    /// Returns `None` by default for keys that never expire, it's used to
+    /// schedule background refreshes.
+    fn expires_in(&self) -> Option<DateTime> {
+        None
+    }
 }
 
 impl<T: Key> Key for Option<T> {
@@ -16,6 +36,14 @@ impl<T: Key> Key for Option<T> {
 
         ctx.is_valid()
     }
+
+    fn is_valid_at(&self, now: DateTime) -> bool {
+        self.as_ref().is_some_and(|v| v.is_valid_at(now))
+    }
+
+    fn expires_in(&self) -> Option<DateTime> {
+        self.as_ref().and_then(|v| v.expires_in())
+    }
 }
 
 /// Load is the trait used by signer to load the key from the environment.
@@ -0,0 +1,81 @@
+//! Blocking API over the async [`Load`] trait.
+
+use anyhow::anyhow;
+use anyhow::Result;
+
+use crate::Context;
+use crate::Load;
+
+/// LoadBlocking drives [`Load::load`] to completion on a small internal
+/// runtime, so that synchronous codebases can use the async loaders without
+/// adopting tokio.
+///
+/// It's implemented for every [`Load`].
+///
+/// # Caveat
+///
+/// Blocking inside an async runtime could stall or deadlock it, so
+/// [`LoadBlocking::load_blocking`] returns an error instead if it's called
+/// within a tokio runtime. Use [`Load::load`] directly there.
+///
+/// # Example
+///
+/// ```no_run
+/// # use reqsign_core::{Context, Load, LoadBlocking};
+/// # fn example(ctx: &Context, loader: &impl Load) -> anyhow::Result<()> {
+/// let key = loader.load_blocking(ctx)?;
+/// # Ok(())
+/// # }
+/// ```
+pub trait LoadBlocking: Load {
+    /// Load signing key from current env in a blocking way.
+    fn load_blocking(&self, ctx: &Context) -> Result<Option<Self::Key>>;
+}
+
+impl<T: Load + ?Sized> LoadBlocking for T {
+    fn load_blocking(&self, ctx: &Context) -> Result<Option<Self::Key>> {
+        if tokio::runtime::Handle::try_current().is_ok() {
+            return Err(anyhow!(
+                "load_blocking can't be called within an async runtime, use load instead"
+            ));
+        }
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        rt.block_on(self.load(ctx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockContext;
+    use std::time::Duration;
+
+    #[derive(Debug)]
+    struct SleepLoader;
+
+    #[async_trait::async_trait]
+    impl Load for SleepLoader {
+        type Key = String;
+
+        async fn load(&self, _: &Context) -> Result<Option<String>> {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            Ok(Some("key".to_string()))
+        }
+    }
+
+    #[test]
+    fn test_load_blocking() -> Result<()> {
+        let ctx = MockContext::new().context();
+        assert_eq!(SleepLoader.load_blocking(&ctx)?.as_deref(), Some("key"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_blocking_within_runtime() {
+        let ctx = MockContext::new().context();
+        assert!(SleepLoader.load_blocking(&ctx).is_err());
+    }
+}
@@ -0,0 +1,186 @@
+//! Canonicalization shared by HMAC based signers.
+//!
+//! Services like Aliyun OSS and Huawei Cloud OBS share the same S3 v2 style
+//! layout for string to sign:
+//!
+//! ```text
+//!   VERB + "\n"
+//! + Content-MD5 + "\n"
+//! + Content-Type + "\n"
+//! + Date + "\n"
+//! + CanonicalizedHeaders
+//! + CanonicalizedResource
+//! ```
+//!
+//! Provider signers only need to supply their own [`CanonicalRules`] and
+//! [`HmacAlgorithm`].
+
+use std::fmt::Write;
+
+use anyhow::Result;
+use http::header::CONTENT_TYPE;
+use http::HeaderName;
+
+use crate::hash::base64_hmac_sha1;
+use crate::hash::base64_hmac_sha256;
+use crate::SigningRequest;
+
+const CONTENT_MD5: HeaderName = HeaderName::from_static("content-md5");
+
+/// HMAC algorithm used to calculate the signature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HmacAlgorithm {
+    /// HMAC-SHA1
+    #[default]
+    HmacSha1,
+    /// HMAC-SHA256
+    HmacSha256,
+}
+
+impl HmacAlgorithm {
+    /// Calculate the base64 encoded HMAC of content.
+    pub fn sign(&self, key: &[u8], content: &[u8]) -> String {
+        match self {
+            HmacAlgorithm::HmacSha1 => base64_hmac_sha1(key, content),
+            HmacAlgorithm::HmacSha256 => base64_hmac_sha256(key, content),
+        }
+    }
+}
+
+/// Service specific rules used while building string to sign.
+pub trait CanonicalRules {
+    /// Prefix of headers that will be included in `CanonicalizedHeaders`,
+    /// like `x-oss-`.
+    fn header_prefix(&self) -> &str;
+
+    /// Check whether the query key is a sub-resource that should be included
+    /// in `CanonicalizedResource`.
+    fn is_sub_resource(&self, key: &str) -> bool;
+
+    /// Whether path and sub-resources should be percent decoded in
+    /// `CanonicalizedResource`.
+    ///
+    /// Default to `false`.
+    fn percent_decode(&self) -> bool {
+        false
+    }
+}
+
+/// Construct string to sign.
+///
+/// `date` is the http date for header signing, or the expires timestamp for
+/// query signing.
+pub fn string_to_sign(
+    ctx: &SigningRequest,
+    rules: &impl CanonicalRules,
+    date: &str,
+    bucket: &str,
+) -> Result<String> {
+    let mut s = String::new();
+    writeln!(&mut s, "{}", ctx.method.as_str())?;
+    writeln!(&mut s, "{}", ctx.header_get_or_default(&CONTENT_MD5)?)?;
+    writeln!(&mut s, "{}", ctx.header_get_or_default(&CONTENT_TYPE)?)?;
+    writeln!(&mut s, "{date}")?;
+
+    let headers = canonicalize_headers(ctx, rules);
+    if !headers.is_empty() {
+        writeln!(&mut s, "{headers}")?;
+    }
+    s.write_str(&canonicalize_resource(ctx, rules, bucket))?;
+
+    Ok(s)
+}
+
+/// Build `CanonicalizedHeaders` from headers with the given prefix.
+pub fn canonicalize_headers(ctx: &SigningRequest, rules: &impl CanonicalRules) -> String {
+    SigningRequest::header_to_string(
+        ctx.header_to_vec_with_prefix(rules.header_prefix()),
+        ":",
+        "\n",
+    )
+}
+
+/// Build `CanonicalizedResource` from bucket, path and sub-resources.
+pub fn canonicalize_resource(
+    ctx: &SigningRequest,
+    rules: &impl CanonicalRules,
+    bucket: &str,
+) -> String {
+    let params = ctx.query_to_vec_with_filter(|k| rules.is_sub_resource(k));
+
+    let (path, params_str) = if rules.percent_decode() {
+        (
+            ctx.path_percent_decoded(),
+            SigningRequest::query_to_percent_decoded_string(params, "=", "&"),
+        )
+    } else {
+        (
+            ctx.path.as_str().into(),
+            SigningRequest::query_to_string(params, "=", "&"),
+        )
+    };
+
+    if params_str.is_empty() {
+        format!("/{bucket}{path}")
+    } else {
+        format!("/{bucket}{path}?{params_str}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TestRules;
+
+    impl CanonicalRules for TestRules {
+        fn header_prefix(&self) -> &str {
+            "x-oss-"
+        }
+
+        fn is_sub_resource(&self, key: &str) -> bool {
+            key == "acl"
+        }
+    }
+
+    #[test]
+    fn test_string_to_sign() -> Result<()> {
+        let req = http::Request::put(
+            "https://oss-example.oss-cn-hangzhou.aliyuncs.com/nelson?acl&foo=bar",
+        )
+        .header(
+            "Content-MD5",
+            "ODBGOERFMDMzQTczRUY3NUE3NzA5QzdFNUYzMDQxNEM=",
+        )
+        .header(CONTENT_TYPE, "text/html")
+        .header("X-OSS-Meta-Author", "foo@bar.com")
+        .header("X-OSS-Magic", "abracadabra")
+        .body(())?;
+        let (mut parts, _) = req.into_parts();
+        let ctx = SigningRequest::build(&mut parts)?;
+
+        let s = string_to_sign(
+            &ctx,
+            &TestRules,
+            "Thu, 17 Nov 2005 18:49:58 GMT",
+            "oss-example",
+        )?;
+        assert_eq!(
+            s,
+            "PUT\nODBGOERFMDMzQTczRUY3NUE3NzA5QzdFNUYzMDQxNEM=\ntext/html\nThu, 17 Nov 2005 18:49:58 GMT\nx-oss-magic:abracadabra\nx-oss-meta-author:foo@bar.com\n/oss-example/nelson?acl"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_hmac_algorithm() {
+        assert_eq!(
+            HmacAlgorithm::HmacSha1.sign(b"key", b"content"),
+            base64_hmac_sha1(b"key", b"content")
+        );
+        assert_eq!(
+            HmacAlgorithm::HmacSha256.sign(b"key", b"content"),
+            base64_hmac_sha256(b"key", b"content")
+        );
+    }
+}
@@ -0,0 +1,32 @@
+use crate::time::{now, DateTime};
+use std::fmt::Debug;
+
+/// Clock provides the current time for signing and expiry checks.
+///
+/// Replace it with [`FixedClock`] to make time-dependent behavior reproducible.
+pub trait Clock: Debug + Send + Sync + 'static {
+    /// Get the current time.
+    fn now(&self) -> DateTime;
+}
+
+/// Implements Clock with the system time.
+#[derive(Debug, Copy, Clone)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime {
+        now()
+    }
+}
+
+/// FixedClock always returns the given time.
+///
+/// This is useful for testing or for signing with a caller-supplied timestamp.
+#[derive(Debug, Copy, Clone)]
+pub struct FixedClock(pub DateTime);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime {
+        self.0
+    }
+}
@@ -1,19 +1,86 @@
+use crate::clock::{Clock, SystemClock};
 use crate::env::{Env, OsEnv};
+use crate::time::DateTime;
+use crate::EndpointResolver;
 use crate::{FileRead, HttpSend};
 use anyhow::Result;
 use bytes::Bytes;
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 /// Context provides the context for the request signing.
+///
+/// # WASM
+///
+/// In browsers, there is no filesystem and requests must go through `fetch`.
+/// A minimal context could be built with [gloo-net](https://docs.rs/gloo-net)
+/// and [send_wrapper](https://docs.rs/send_wrapper), the latter makes the
+/// `fetch` futures `Send` which is fine since wasm32 is single threaded:
+///
+/// ```rust,ignore
+/// use anyhow::Result;
+/// use bytes::Bytes;
+/// use reqsign_core::{Context, FileRead, HttpSend, StaticEnv};
+/// use send_wrapper::SendWrapper;
+///
+/// #[derive(Debug)]
+/// struct NoFileRead;
+///
+/// #[async_trait::async_trait]
+/// impl FileRead for NoFileRead {
+///     async fn file_read(&self, path: &str) -> Result<Vec<u8>> {
+///         anyhow::bail!("file {path} is not available in browsers")
+///     }
+/// }
+///
+/// #[derive(Debug)]
+/// struct FetchHttpSend;
+///
+/// #[async_trait::async_trait]
+/// impl HttpSend for FetchHttpSend {
+///     async fn http_send(&self, req: http::Request<Bytes>) -> Result<http::Response<Bytes>> {
+///         SendWrapper::new(async move {
+///             let (parts, body) = req.into_parts();
+///             let mut builder = gloo_net::http::RequestBuilder::new(&parts.uri.to_string())
+///                 .method(parts.method);
+///             for (k, v) in parts.headers.iter() {
+///                 builder = builder.header(k.as_str(), v.to_str()?);
+///             }
+///             let resp = builder
+///                 .body(js_sys::Uint8Array::from(body.as_ref()))?
+///                 .send()
+///                 .await?;
+///
+///             let mut out = http::Response::builder().status(resp.status());
+///             for (k, v) in resp.headers().entries() {
+///                 out = out.header(k, v);
+///             }
+///             Ok(out.body(Bytes::from(resp.binary().await?))?)
+///         })
+///         .await
+///     }
+/// }
+///
+/// let ctx = Context::new(NoFileRead, FetchHttpSend).with_env(StaticEnv {
+///     home_dir: None,
+///     envs: Default::default(),
+/// });
+/// ```
 #[derive(Debug, Clone)]
 pub struct Context {
     fs: Arc<dyn FileRead>,
     http: Arc<dyn HttpSend>,
     env: Arc<dyn Env>,
+    clock: Arc<dyn Clock>,
+    user_agent: Arc<str>,
+    endpoint_resolver: Option<Arc<dyn EndpointResolver>>,
 }
 
+/// Default `User-Agent` of requests sent via [`Context`].
+const DEFAULT_USER_AGENT: &str = concat!("reqsign/", env!("CARGO_PKG_VERSION"));
+
 impl Context {
     /// Create a new context.
     #[inline]
@@ -22,6 +89,9 @@ impl Context {
             fs: Arc::new(fs),
             http: Arc::new(http),
             env: Arc::new(OsEnv),
+            clock: Arc::new(SystemClock),
+            user_agent: Arc::from(DEFAULT_USER_AGENT),
+            endpoint_resolver: None,
         }
     }
 
@@ -32,12 +102,56 @@ impl Context {
         self
     }
 
+    /// Set the clock for the context. Use this if you want to mock the time.
+    #[inline]
+    pub fn with_clock(mut self, clock: impl Clock) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Set the `User-Agent` of requests sent via this context, like the ones
+    /// to fetch credentials from STS or IMDS.
+    ///
+    /// Default to `reqsign/{version}`. Requests that set `User-Agent`
+    /// themselves are left untouched.
+    #[inline]
+    pub fn with_user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Arc::from(user_agent.into());
+        self
+    }
+
+    /// Set the resolver to rewrite the uri of requests sent via this context
+    /// before sending, see [`EndpointResolver`].
+    #[inline]
+    pub fn with_endpoint_resolver(mut self, resolver: impl EndpointResolver) -> Self {
+        self.endpoint_resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    /// Get the current time from the clock.
+    #[inline]
+    pub fn now(&self) -> DateTime {
+        self.clock.now()
+    }
+
     /// Read the file content entirely in `Vec<u8>`.
     #[inline]
     pub async fn file_read(&self, path: &str) -> Result<Vec<u8>> {
         self.fs.file_read(path).await
     }
 
+    /// Get the last modification time of the file, see [`FileRead::file_modified`].
+    #[inline]
+    pub async fn file_modified(&self, path: &str) -> Result<Option<SystemTime>> {
+        self.fs.file_modified(path).await
+    }
+
+    /// Write the content into the file, see [`FileRead::file_write`].
+    #[inline]
+    pub async fn file_write(&self, path: &str, content: &[u8]) -> Result<()> {
+        self.fs.file_write(path, content).await
+    }
+
     /// Read the file content entirely in `String`.
     pub async fn file_read_as_string(&self, path: &str) -> Result<String> {
         let bytes = self.file_read(path).await?;
@@ -47,7 +161,7 @@ impl Context {
     /// Send http request and return the response.
     #[inline]
     pub async fn http_send(&self, req: http::Request<Bytes>) -> Result<http::Response<Bytes>> {
-        self.http.http_send(req).await
+        self.http.http_send(self.prepare_request(req)?).await
     }
 
     /// Send http request and return the response as string.
@@ -55,11 +169,26 @@ impl Context {
         &self,
         req: http::Request<Bytes>,
     ) -> Result<http::Response<String>> {
-        let (parts, body) = self.http.http_send(req).await?.into_parts();
+        let (parts, body) = self.http_send(req).await?.into_parts();
         let body = String::from_utf8_lossy(&body).to_string();
         Ok(http::Response::from_parts(parts, body))
     }
 
+    /// Rewrite the uri with the endpoint resolver and set default headers.
+    fn prepare_request(&self, mut req: http::Request<Bytes>) -> Result<http::Request<Bytes>> {
+        if let Some(resolver) = &self.endpoint_resolver {
+            let uri = std::mem::take(req.uri_mut());
+            *req.uri_mut() = resolver.resolve(uri)?;
+        }
+        if !req.headers().contains_key(http::header::USER_AGENT) {
+            req.headers_mut().insert(
+                http::header::USER_AGENT,
+                http::HeaderValue::from_str(&self.user_agent)?,
+            );
+        }
+        Ok(req)
+    }
+
     /// Get the home directory of the current user.
     #[inline]
     pub fn home_dir(&self) -> Option<PathBuf> {
@@ -0,0 +1,16 @@
+use anyhow::Result;
+use http::Uri;
+use std::fmt::Debug;
+
+/// EndpointResolver rewrites the uri of requests sent via
+/// [`Context`](crate::Context), like the ones to fetch credentials from STS
+/// or IMDS.
+///
+/// This is useful to remap many endpoints to internal addresses at once,
+/// for example, with split-horizon DNS or VPC endpoints. Requests are
+/// rewritten after signing, the signed `Host` header is kept.
+pub trait EndpointResolver: Debug + Send + Sync + 'static {
+    /// Resolve the uri of the request, return it as is to leave the request
+    /// untouched.
+    fn resolve(&self, uri: Uri) -> Result<Uri>;
+}
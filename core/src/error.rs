@@ -0,0 +1,108 @@
+use std::fmt::{Display, Formatter};
+
+/// Error is the typed error returned by reqsign loaders and builders.
+///
+/// The public APIs still return `anyhow::Result` for ergonomics. Callers can
+/// recover the variant via `downcast_ref` to decide whether to retry or fall
+/// back:
+///
+/// ```
+/// use reqsign_core::Error;
+///
+/// let err = anyhow::Error::from(Error::Http("service unavailable".to_string()));
+/// assert!(matches!(err.downcast_ref::<Error>(), Some(Error::Http(_))));
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// No credential could be found from the configured sources.
+    CredentialNotFound(String),
+    /// The request to the credential service failed.
+    Http(String),
+    /// The credential service responded with an error.
+    ///
+    /// `code` and `request_id` are set if the service returns a structured
+    /// error body, `message` is the raw body otherwise.
+    Service {
+        /// The status of the response.
+        status: http::StatusCode,
+        /// The error code, like `AccessDenied`.
+        code: Option<String>,
+        /// The error message.
+        message: String,
+        /// The id of the request, useful when contacting the vendor.
+        request_id: Option<String>,
+    },
+    /// The response or file content could not be parsed.
+    Parse(String),
+    /// The credential has expired.
+    Expired(String),
+    /// The configuration is invalid or incomplete.
+    Config(String),
+    /// The request to the credential service timed out.
+    ///
+    /// It's not retried so that credential chains can move on to the next
+    /// source quickly.
+    Timeout(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::CredentialNotFound(msg) => write!(f, "credential not found: {msg}"),
+            Error::Http(msg) => write!(f, "http request failed: {msg}"),
+            Error::Service {
+                status,
+                code,
+                message,
+                request_id,
+            } => {
+                write!(f, "service responded with {status}: ")?;
+                if let Some(code) = code {
+                    write!(f, "[{code}] ")?;
+                }
+                write!(f, "{message}")?;
+                if let Some(request_id) = request_id {
+                    write!(f, " (request id: {request_id})")?;
+                }
+                Ok(())
+            }
+            Error::Parse(msg) => write!(f, "parse failed: {msg}"),
+            Error::Expired(msg) => write!(f, "credential expired: {msg}"),
+            Error::Config(msg) => write!(f, "invalid config: {msg}"),
+            Error::Timeout(msg) => write!(f, "request timed out: {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_error_display() {
+        let err = Error::Service {
+            status: http::StatusCode::FORBIDDEN,
+            code: Some("AccessDenied".to_string()),
+            message: "User [demo] is not authorized".to_string(),
+            request_id: Some("c6104cbe".to_string()),
+        };
+        assert_eq!(
+            err.to_string(),
+            "service responded with 403 Forbidden: [AccessDenied] User [demo] is not authorized (request id: c6104cbe)"
+        );
+
+        let err = Error::Service {
+            status: http::StatusCode::BAD_GATEWAY,
+            code: None,
+            message: "Bad Gateway".to_string(),
+            request_id: None,
+        };
+        assert_eq!(
+            err.to_string(),
+            "service responded with 502 Bad Gateway: Bad Gateway"
+        );
+    }
+}
@@ -1,11 +1,33 @@
 use anyhow::Result;
 use std::fmt::Debug;
+use std::time::SystemTime;
 
 /// FileRead is used to read the file content entirely in `Vec<u8>`.
 ///
-/// This could be used by `Load` to load the credential from the file.
+/// This could be used by `Load` to load the credential from the file, and
+/// optionally to cache credentials via [`FileRead::file_write`].
 #[async_trait::async_trait]
 pub trait FileRead: Debug + Send + Sync + 'static {
     /// Read the file content entirely in `Vec<u8>`.
     async fn file_read(&self, path: &str) -> Result<Vec<u8>>;
+
+    /// Get the last modification time of the file.
+    ///
+    /// Returns `None` if it's not supported, so callers can't tell whether
+    /// the file has changed and should read it again.
+    async fn file_modified(&self, path: &str) -> Result<Option<SystemTime>> {
+        let _ = path;
+        Ok(None)
+    }
+
+    /// Write the content into the file atomically, creating its parent
+    /// directories if needed.
+    ///
+    /// The content may hold secrets like cached credentials, so the file
+    /// should only be accessible by the current user. Returns an error if
+    /// it's not supported.
+    async fn file_write(&self, path: &str, content: &[u8]) -> Result<()> {
+        let _ = (path, content);
+        Err(anyhow::anyhow!("file write is not supported"))
+    }
 }
@@ -6,6 +6,7 @@ use base64::prelude::BASE64_STANDARD;
 use base64::Engine;
 use hmac::Hmac;
 use hmac::Mac;
+use md5::Md5;
 use sha1::Sha1;
 use sha2::Digest;
 use sha2::Sha256;
@@ -22,6 +23,20 @@ pub fn base64_decode(content: &str) -> Result<Vec<u8>> {
         .map_err(|e| anyhow!("base64 decode failed for {e:?}"))
 }
 
+/// Base64 encoded MD5 hash.
+///
+/// This is the value required by the `Content-MD5` header.
+pub fn base64_md5(content: &[u8]) -> String {
+    base64_encode(Md5::digest(content).as_slice())
+}
+
+/// Base64 encoded SHA256 hash.
+///
+/// This is the value required by headers like `x-content-sha256`.
+pub fn base64_sha256(content: &[u8]) -> String {
+    base64_encode(Sha256::digest(content).as_slice())
+}
+
 /// Hex encoded SHA1 hash.
 ///
 /// Use this function instead of `hex::encode(sha1(content))` can reduce
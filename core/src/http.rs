@@ -1,6 +1,7 @@
 use anyhow::Result;
 use bytes::Bytes;
 use std::fmt::Debug;
+use std::time::Duration;
 
 /// HttpSend is used to send http request during the signing process.
 ///
@@ -11,3 +12,40 @@ pub trait HttpSend: Debug + Send + Sync + 'static {
     /// Send http request and return the response.
     async fn http_send(&self, req: http::Request<Bytes>) -> Result<http::Response<Bytes>>;
 }
+
+/// Timeouts for one request sent via [`HttpSend`].
+///
+/// Loaders insert it into the request extensions, for example, short timeouts
+/// for metadata endpoints that are unreachable outside the cloud.
+/// Implementations should honor it and return [`crate::Error::Timeout`] once
+/// exceeded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct HttpTimeout {
+    /// Timeout for establishing the connection.
+    pub connect: Option<Duration>,
+    /// Timeout for reading the response after connected.
+    pub read: Option<Duration>,
+}
+
+impl HttpTimeout {
+    /// Create a new timeout with both connect and read timeouts.
+    pub fn new(connect: Duration, read: Duration) -> Self {
+        Self {
+            connect: Some(connect),
+            read: Some(read),
+        }
+    }
+
+    /// The total time allowed for the request, the sum of both timeouts.
+    ///
+    /// Implementations that can't apply the connect timeout on its own use
+    /// it as the timeout of the whole request, so a request with 1s connect
+    /// and 1s read timeouts could take up to 2s. Returns `None` if neither
+    /// timeout is set.
+    pub fn total(&self) -> Option<Duration> {
+        match (self.connect, self.read) {
+            (None, None) => None,
+            (connect, read) => Some(connect.unwrap_or_default() + read.unwrap_or_default()),
+        }
+    }
+}
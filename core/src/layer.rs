@@ -0,0 +1,195 @@
+//! [`tower`] integration for [`Signer`].
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::Context as TaskContext;
+use std::task::Poll;
+use std::time::Duration;
+
+use tower::BoxError;
+use tower::Layer;
+use tower::Service;
+
+use crate::Key;
+use crate::Signer;
+
+/// Layer that signs every outgoing request with the given [`Signer`].
+///
+/// # Example
+///
+/// ```no_run
+/// # use reqsign_core::{Key, SignLayer, Signer};
+/// # fn example<K: Key, S>(signer: Signer<K>, client: S) {
+/// let client = tower::ServiceBuilder::new()
+///     .layer(SignLayer::new(signer))
+///     .service(client);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SignLayer<K: Key> {
+    signer: Signer<K>,
+    expires_in: Option<Duration>,
+}
+
+impl<K: Key> SignLayer<K> {
+    /// Create a new layer with the given signer.
+    pub fn new(signer: Signer<K>) -> Self {
+        Self {
+            signer,
+            expires_in: None,
+        }
+    }
+
+    /// Sign requests with the given expiration, which will build presigned
+    /// requests for services that support it.
+    pub fn with_expires_in(mut self, expires_in: Duration) -> Self {
+        self.expires_in = Some(expires_in);
+        self
+    }
+}
+
+impl<K: Key, S> Layer<S> for SignLayer<K> {
+    type Service = SignService<K, S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SignService {
+            signer: self.signer.clone(),
+            expires_in: self.expires_in,
+            inner,
+        }
+    }
+}
+
+/// Service that signs the request before delegating to the inner service.
+///
+/// The credential is loaded lazily on the first request and shared by all
+/// clones of this service. Every call signs the request again, so requests
+/// sent again by a retry layer above this service will carry a fresh
+/// signature.
+#[derive(Clone, Debug)]
+pub struct SignService<K: Key, S> {
+    signer: Signer<K>,
+    expires_in: Option<Duration>,
+    inner: S,
+}
+
+impl<K, S, ReqBody> Service<http::Request<ReqBody>> for SignService<K, S>
+where
+    K: Key,
+    S: Service<http::Request<ReqBody>> + Clone + Send + 'static,
+    S::Error: Into<BoxError>,
+    S::Future: Send,
+    ReqBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut TaskContext<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, req: http::Request<ReqBody>) -> Self::Future {
+        let signer = self.signer.clone();
+        let expires_in = self.expires_in;
+        // Take the service that was driven to readiness, and leave a clone
+        // in place for the next call.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let (mut parts, body) = req.into_parts();
+            signer
+                .sign(&mut parts, expires_in)
+                .await
+                .map_err(BoxError::from)?;
+
+            inner
+                .call(http::Request::from_parts(parts, body))
+                .await
+                .map_err(Into::into)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockContext;
+    use crate::{Build, Context, Load};
+    use anyhow::Result;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    #[derive(Clone, Debug)]
+    struct TestKey;
+
+    impl Key for TestKey {
+        fn is_valid(&self) -> bool {
+            true
+        }
+    }
+
+    #[derive(Debug)]
+    struct TestLoader;
+
+    #[async_trait::async_trait]
+    impl Load for TestLoader {
+        type Key = TestKey;
+
+        async fn load(&self, _: &Context) -> Result<Option<TestKey>> {
+            Ok(Some(TestKey))
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct CountingBuilder {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait::async_trait]
+    impl Build for CountingBuilder {
+        type Key = TestKey;
+
+        async fn build(
+            &self,
+            _: &Context,
+            req: &mut http::request::Parts,
+            _: Option<&TestKey>,
+            _: Option<Duration>,
+        ) -> Result<()> {
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            req.headers.insert(
+                http::header::AUTHORIZATION,
+                format!("test-{calls}").parse()?,
+            );
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sign_service() -> Result<()> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let signer = Signer::new(
+            MockContext::new().context(),
+            TestLoader,
+            CountingBuilder {
+                calls: calls.clone(),
+            },
+        );
+
+        let svc = tower::ServiceBuilder::new()
+            .layer(SignLayer::new(signer))
+            .service_fn(|req: http::Request<()>| async move {
+                Ok::<_, BoxError>(req.headers()[http::header::AUTHORIZATION].clone())
+            });
+
+        let req = || http::Request::get("https://example.com").body(()).unwrap();
+        assert_eq!(svc.clone().oneshot(req()).await.unwrap(), "test-1");
+        // Calling again, like a retry does, signs the request again.
+        assert_eq!(svc.clone().oneshot(req()).await.unwrap(), "test-2");
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+}
@@ -3,6 +3,7 @@
 // Make sure all our public APIs have docs.
 #![warn(missing_docs)]
 
+pub mod canonical;
 pub mod hash;
 pub mod time;
 pub mod utils;
@@ -13,13 +14,44 @@ mod fs;
 pub use fs::FileRead;
 mod http;
 pub use http::HttpSend;
+pub use http::HttpTimeout;
 mod env;
 pub use env::Env;
 pub use env::StaticEnv;
+mod endpoint;
+pub use endpoint::EndpointResolver;
+mod clock;
+pub use clock::Clock;
+pub use clock::FixedClock;
+
+mod error;
+pub use error::Error;
 
 mod api;
 pub use api::{Build, Key, Load};
+#[cfg(feature = "blocking")]
+mod blocking;
+#[cfg(feature = "blocking")]
+pub use blocking::LoadBlocking;
+#[cfg(feature = "refresh")]
+mod refresh;
+#[cfg(feature = "refresh")]
+pub use refresh::RefreshHandle;
 mod request;
 pub use request::{SigningMethod, SigningRequest};
 mod signer;
 pub use signer::Signer;
+mod token;
+pub use token::{BearerToken, TokenFileLoader};
+#[cfg(feature = "tower")]
+mod layer;
+#[cfg(feature = "tower")]
+pub use layer::{SignLayer, SignService};
+#[cfg(feature = "reqwest")]
+mod middleware;
+#[cfg(feature = "reqwest")]
+pub use middleware::SignMiddleware;
+#[cfg(any(test, feature = "testing"))]
+mod testing;
+#[cfg(any(test, feature = "testing"))]
+pub use testing::MockContext;
@@ -0,0 +1,224 @@
+//! [`reqwest_middleware`] integration for [`Signer`].
+
+use std::mem;
+use std::time::Duration;
+
+use anyhow::Result;
+use http::Extensions;
+use http::HeaderName;
+use http::HeaderValue;
+use reqwest_middleware::Middleware;
+use reqwest_middleware::Next;
+
+use crate::hash::hex_sha256;
+use crate::Key;
+use crate::Signer;
+
+/// Middleware that signs every outgoing request with the given [`Signer`].
+///
+/// Extensions passed into the middleware chain are visible to the builder,
+/// so per-request options like `reqsign_aws_v4::UnsignedPayload` work as
+/// usual.
+///
+/// # Example
+///
+/// ```no_run
+/// # use reqsign_core::{Key, SignMiddleware, Signer};
+/// # fn example<K: Key>(signer: Signer<K>) {
+/// let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+///     .with(
+///         SignMiddleware::new(signer)
+///             .with_payload_hash(http::HeaderName::from_static("x-amz-content-sha256")),
+///     )
+///     .build();
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct SignMiddleware<K: Key> {
+    signer: Signer<K>,
+    expires_in: Option<Duration>,
+    payload_hash: Option<HeaderName>,
+}
+
+impl<K: Key> SignMiddleware<K> {
+    /// Create a new middleware with the given signer.
+    pub fn new(signer: Signer<K>) -> Self {
+        Self {
+            signer,
+            expires_in: None,
+            payload_hash: None,
+        }
+    }
+
+    /// Sign requests with the given expiration, which will build presigned
+    /// requests for services that support it.
+    pub fn with_expires_in(mut self, expires_in: Duration) -> Self {
+        self.expires_in = Some(expires_in);
+        self
+    }
+
+    /// Insert the hex encoded sha256 of the body into the given header
+    /// before signing, for example `x-amz-content-sha256` for AWS.
+    ///
+    /// Only buffered bodies can be hashed. Streaming bodies are left
+    /// untouched, so the builder falls back to its default like
+    /// `UNSIGNED-PAYLOAD`.
+    pub fn with_payload_hash(mut self, header: HeaderName) -> Self {
+        self.payload_hash = Some(header);
+        self
+    }
+
+    async fn sign(&self, req: &mut reqwest::Request, extensions: &mut Extensions) -> Result<()> {
+        if let Some(header) = &self.payload_hash {
+            if !req.headers().contains_key(header) {
+                if let Some(body) = req.body().and_then(|body| body.as_bytes()) {
+                    let value = HeaderValue::try_from(hex_sha256(body))?;
+                    req.headers_mut().insert(header.clone(), value);
+                }
+            }
+        }
+
+        let (mut parts, _) = http::Request::new(()).into_parts();
+        parts.method = req.method().clone();
+        parts.uri = req.url().as_str().parse()?;
+        parts.version = req.version();
+        parts.headers = mem::take(req.headers_mut());
+        parts.extensions = mem::take(extensions);
+
+        let result = self.signer.sign(&mut parts, self.expires_in).await;
+
+        // Return headers and extensions back, even if signing failed.
+        *req.headers_mut() = parts.headers;
+        *extensions = parts.extensions;
+        result?;
+
+        *req.url_mut() = reqwest::Url::parse(&parts.uri.to_string())?;
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl<K: Key> Middleware for SignMiddleware<K> {
+    async fn handle(
+        &self,
+        mut req: reqwest::Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<reqwest::Response> {
+        self.sign(&mut req, extensions)
+            .await
+            .map_err(reqwest_middleware::Error::Middleware)?;
+
+        next.run(req, extensions).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockContext;
+    use crate::{Build, Context, Load};
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    #[derive(Clone, Debug)]
+    struct TestKey;
+
+    impl Key for TestKey {
+        fn is_valid(&self) -> bool {
+            true
+        }
+    }
+
+    #[derive(Debug)]
+    struct TestLoader;
+
+    #[async_trait::async_trait]
+    impl Load for TestLoader {
+        type Key = TestKey;
+
+        async fn load(&self, _: &Context) -> Result<Option<TestKey>> {
+            Ok(Some(TestKey))
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct Scope(&'static str);
+
+    #[derive(Debug)]
+    struct TestBuilder;
+
+    #[async_trait::async_trait]
+    impl Build for TestBuilder {
+        type Key = TestKey;
+
+        async fn build(
+            &self,
+            _: &Context,
+            req: &mut http::request::Parts,
+            _: Option<&TestKey>,
+            _: Option<Duration>,
+        ) -> Result<()> {
+            let scope = req.extensions.get::<Scope>().map_or("none", |v| v.0);
+            req.headers.insert(
+                http::header::AUTHORIZATION,
+                format!("test {scope}").parse()?,
+            );
+            req.uri = format!("{}?signature=test", req.uri).parse()?;
+            Ok(())
+        }
+    }
+
+    /// Capture the signed request instead of sending it.
+    #[derive(Default)]
+    struct Capture {
+        req: Arc<Mutex<Option<reqwest::Request>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Middleware for Capture {
+        async fn handle(
+            &self,
+            req: reqwest::Request,
+            _: &mut Extensions,
+            _: Next<'_>,
+        ) -> reqwest_middleware::Result<reqwest::Response> {
+            *self.req.lock().unwrap() = Some(req);
+            Ok(http::Response::new("").into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sign_middleware() -> Result<()> {
+        let signer = Signer::new(MockContext::new().context(), TestLoader, TestBuilder);
+        let capture = Capture::default();
+        let captured = capture.req.clone();
+
+        let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+            .with(
+                SignMiddleware::new(signer)
+                    .with_payload_hash(HeaderName::from_static("x-amz-content-sha256")),
+            )
+            .with(capture)
+            .build();
+
+        client
+            .put("https://example.com/object")
+            .with_extension(Scope("s3"))
+            .body("Hello, World!")
+            .send()
+            .await?;
+
+        let req = captured.lock().unwrap().take().unwrap();
+        assert_eq!(
+            req.url().as_str(),
+            "https://example.com/object?signature=test"
+        );
+        assert_eq!(req.headers()[http::header::AUTHORIZATION], "test s3");
+        assert_eq!(
+            req.headers()["x-amz-content-sha256"],
+            "dffd6021bb2bd5b0af676290809ec3a53191dd81c7f70a4b28688a362182986f"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,258 @@
+//! Background refresh of signing keys.
+
+use std::time::Duration;
+
+use crate::{Key, Signer};
+
+/// The first delay to retry a failed refresh, doubled on every failure.
+const MIN_RETRY_DELAY: Duration = Duration::from_millis(100);
+/// The max delay to retry a failed refresh.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Handle of the task spawned by [`Signer::spawn_refresh`].
+///
+/// The task is stopped by [`RefreshHandle::stop`] or once the handle is
+/// dropped, keep it alive as long as the signer is in use.
+#[derive(Debug)]
+pub struct RefreshHandle {
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl RefreshHandle {
+    /// Stop the refresh task, the cached key is kept.
+    pub fn stop(self) {}
+}
+
+impl Drop for RefreshHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl<K: Key> Signer<K> {
+    /// Spawn a tokio task that loads the key in background and reloads it
+    /// `ahead` of its expiry, so that signing always sees a fresh key
+    /// without waiting for the loader.
+    ///
+    /// Failed refreshes are retried with exponential backoff from 100ms up
+    /// to 60s, the last loaded key is used until it actually expires. Keys
+    /// without [`Key::expires_in`] are loaded only once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called outside a tokio runtime.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// # use reqsign_core::{Key, Signer};
+    /// # use std::time::Duration;
+    /// # async fn example<K: Key>(signer: Signer<K>) {
+    /// let handle = signer.spawn_refresh(Duration::from_secs(300));
+    /// // Sign requests with `signer`...
+    /// handle.stop();
+    /// # }
+    /// ```
+    pub fn spawn_refresh(&self, ahead: Duration) -> RefreshHandle {
+        let signer = self.clone();
+        let task = tokio::spawn(async move {
+            let mut retry_delay = MIN_RETRY_DELAY;
+            loop {
+                let key = signer.refresh_key().await;
+                let expires_in = match &key {
+                    Ok(Some(key)) => match key.expires_in() {
+                        Some(expires_in) => expires_in,
+                        None => return,
+                    },
+                    _ => {
+                        tokio::time::sleep(retry_delay).await;
+                        retry_delay = (retry_delay * 2).min(MAX_RETRY_DELAY);
+                        continue;
+                    }
+                };
+                retry_delay = MIN_RETRY_DELAY;
+
+                // Wait at least half of the remaining lifetime, so that short
+                // lived keys are not reloaded in a busy loop.
+                let remaining = (expires_in - signer.context().now())
+                    .to_std()
+                    .unwrap_or(Duration::ZERO);
+                let wait = remaining.saturating_sub(ahead).max(remaining / 2);
+                tokio::time::sleep(wait.max(MIN_RETRY_DELAY)).await;
+            }
+        });
+
+        RefreshHandle { task }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::time::{now, DateTime};
+    use crate::MockContext;
+    use crate::{Build, Clock, Context, Load};
+    use anyhow::{anyhow, Result};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Clock that follows the tokio time, so that time paused by tests
+    /// applies to keys as well.
+    #[derive(Debug)]
+    struct TokioClock {
+        base: DateTime,
+        start: tokio::time::Instant,
+    }
+
+    impl Clock for TokioClock {
+        fn now(&self) -> DateTime {
+            self.base + self.start.elapsed()
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    struct ExpiringKey(DateTime);
+
+    impl Key for ExpiringKey {
+        fn is_valid(&self) -> bool {
+            self.is_valid_at(now())
+        }
+
+        fn is_valid_at(&self, now: DateTime) -> bool {
+            self.0 > now
+        }
+
+        fn expires_in(&self) -> Option<DateTime> {
+            Some(self.0)
+        }
+    }
+
+    /// Loader that returns keys expiring after `lifetime`, and fails the
+    /// calls listed in `failures`.
+    #[derive(Debug)]
+    struct ExpiringLoader {
+        calls: Arc<AtomicUsize>,
+        lifetime: Duration,
+        failures: Vec<usize>,
+    }
+
+    #[async_trait::async_trait]
+    impl Load for ExpiringLoader {
+        type Key = ExpiringKey;
+
+        async fn load(&self, ctx: &Context) -> Result<Option<ExpiringKey>> {
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.failures.contains(&calls) {
+                return Err(anyhow!("temporary failure"));
+            }
+            Ok(Some(ExpiringKey(ctx.now() + self.lifetime)))
+        }
+    }
+
+    #[derive(Debug)]
+    struct NoopBuilder;
+
+    #[async_trait::async_trait]
+    impl Build for NoopBuilder {
+        type Key = ExpiringKey;
+
+        async fn build(
+            &self,
+            _: &Context,
+            _: &mut http::request::Parts,
+            key: Option<&ExpiringKey>,
+            _: Option<Duration>,
+        ) -> Result<()> {
+            key.ok_or_else(|| anyhow!("key must be loaded"))?;
+            Ok(())
+        }
+    }
+
+    fn signer(
+        calls: Arc<AtomicUsize>,
+        lifetime: Duration,
+        failures: Vec<usize>,
+    ) -> Signer<ExpiringKey> {
+        let ctx = MockContext::new().context().with_clock(TokioClock {
+            base: now(),
+            start: tokio::time::Instant::now(),
+        });
+        Signer::new(
+            ctx,
+            ExpiringLoader {
+                calls,
+                lifetime,
+                failures,
+            },
+            NoopBuilder,
+        )
+    }
+
+    fn parts() -> http::request::Parts {
+        http::Request::get("https://example.com")
+            .body(())
+            .unwrap()
+            .into_parts()
+            .0
+    }
+
+    /// Advance the paused time and let the refresh task run.
+    async fn advance(duration: Duration) {
+        tokio::time::advance(duration).await;
+        tokio::task::yield_now().await;
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_spawn_refresh() -> Result<()> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let signer = signer(calls.clone(), Duration::from_millis(400), vec![]);
+
+        let handle = signer.spawn_refresh(Duration::from_millis(300));
+        advance(Duration::ZERO).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Signing uses the key loaded in background.
+        signer.sign(&mut parts(), None).await?;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Reloaded after half of the lifetime.
+        advance(Duration::from_millis(199)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        advance(Duration::from_millis(1)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        handle.stop();
+        advance(Duration::from_secs(10)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_spawn_refresh_retry_after_failure() -> Result<()> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        // The first refresh succeeds, the next two fail.
+        let signer = signer(calls.clone(), Duration::from_secs(1), vec![1, 2]);
+
+        let _handle = signer.spawn_refresh(Duration::from_millis(900));
+        advance(Duration::ZERO).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        // Refresh at 500ms fails and is retried after 100ms and 200ms.
+        advance(Duration::from_millis(500)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // The last good key is kept while refresh is failing.
+        signer.sign(&mut parts(), None).await?;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        advance(Duration::from_millis(100)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        advance(Duration::from_millis(199)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        advance(Duration::from_millis(1)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        signer.sign(&mut parts(), None).await?;
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+        Ok(())
+    }
+}
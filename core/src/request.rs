@@ -107,7 +107,7 @@ impl SigningRequest {
     }
 
     /// Get the path percent decoded.
-    pub fn path_percent_decoded(&self) -> Cow<str> {
+    pub fn path_percent_decoded(&self) -> Cow<'_, str> {
         percent_encoding::percent_decode_str(&self.path).decode_utf8_lossy()
     }
 
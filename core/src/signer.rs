@@ -1,7 +1,10 @@
-use crate::{Build, Context, Key, Load};
+use crate::time::DateTime;
+use crate::{Build, Context, FixedClock, Key, Load};
 use anyhow::Result;
-use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::Mutex;
 
 /// Signer is the main struct used to sign the request.
 #[derive(Clone, Debug)]
@@ -10,6 +13,11 @@ pub struct Signer<K: Key> {
     loader: Arc<dyn Load<Key = K>>,
     builder: Arc<dyn Build<Key = K>>,
     key: Arc<Mutex<Option<K>>>,
+    /// Bumped every time a key is loaded, so that concurrent reloads can
+    /// tell whether the key has been reloaded while they were waiting.
+    generation: Arc<AtomicU64>,
+
+    anonymous: bool,
 }
 
 impl<K: Key> Signer<K> {
@@ -21,26 +29,365 @@ impl<K: Key> Signer<K> {
             loader: Arc::new(loader),
             builder: Arc::new(builder),
             key: Arc::new(Mutex::new(None)),
+            generation: Arc::new(AtomicU64::new(0)),
+
+            anonymous: false,
         }
     }
 
+    /// Make this signer anonymous.
+    ///
+    /// Anonymous signer passes requests through unchanged without loading
+    /// any key, which is useful for public buckets behind a CDN.
+    pub fn with_anonymous(mut self) -> Self {
+        self.anonymous = true;
+        self
+    }
+
     /// Signing request.
     pub async fn sign(
         &self,
         req: &mut http::request::Parts,
         expires_in: Option<Duration>,
     ) -> Result<()> {
-        let key = self.key.lock().expect("lock poisoned").clone();
-        let key = if key.is_valid() {
-            key
-        } else {
-            let ctx = self.loader.load(&self.ctx).await?;
-            *self.key.lock().expect("lock poisoned") = ctx.clone();
-            ctx
+        self.sign_inner(&self.ctx, req, expires_in).await
+    }
+
+    /// Signing request at the given time instead of the time from the
+    /// context's clock.
+    ///
+    /// This is useful when the local clock is skewed, for example, users can
+    /// sync the time from the `Date` header of server responses. All the
+    /// time related fields of the signature are derived from `time`.
+    ///
+    /// Keys are still loaded with the context's clock.
+    pub async fn sign_with_time(
+        &self,
+        req: &mut http::request::Parts,
+        expires_in: Option<Duration>,
+        time: DateTime,
+    ) -> Result<()> {
+        let ctx = self.ctx.clone().with_clock(FixedClock(time));
+        self.sign_inner(&ctx, req, expires_in).await
+    }
+
+    /// Reload the key even if the cached one is still valid, and return it.
+    ///
+    /// This is useful when the key has been revoked before it expires, for
+    /// example, retry with a fresh key after the service returns `403`.
+    ///
+    /// Concurrent reloads share the same load: callers that have waited for
+    /// another reload to finish get its key instead of loading again. The
+    /// cached key is left untouched if loading failed.
+    pub async fn reload(&self) -> Result<Option<K>> {
+        let generation = self.generation.load(Ordering::SeqCst);
+        let mut key = self.key.lock().await;
+        if self.generation.load(Ordering::SeqCst) == generation {
+            *key = self.loader.load(&self.ctx).await?;
+            self.generation.fetch_add(1, Ordering::SeqCst);
+        }
+        Ok(key.clone())
+    }
+
+    /// Load a new key without holding the lock, so that signing is not
+    /// blocked, and store it on success.
+    ///
+    /// The cached key is left untouched if loading failed or no key is
+    /// loaded.
+    #[cfg(feature = "refresh")]
+    pub(crate) async fn refresh_key(&self) -> Result<Option<K>> {
+        let Some(loaded) = self.loader.load(&self.ctx).await? else {
+            return Ok(None);
+        };
+        *self.key.lock().await = Some(loaded.clone());
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        Ok(Some(loaded))
+    }
+
+    /// Get the context of this signer.
+    #[cfg(feature = "refresh")]
+    pub(crate) fn context(&self) -> &Context {
+        &self.ctx
+    }
+
+    async fn sign_inner(
+        &self,
+        ctx: &Context,
+        req: &mut http::request::Parts,
+        expires_in: Option<Duration>,
+    ) -> Result<()> {
+        if self.anonymous {
+            return Ok(());
+        }
+
+        let key = {
+            // Hold the lock while loading so that only one refresh is in flight,
+            // other tasks will wait for it and reuse the loaded key.
+            //
+            // The cached key is left untouched if loading failed, so the next
+            // call will try again.
+            let mut key = self.key.lock().await;
+            if !key.is_valid_at(self.ctx.now()) {
+                *key = self.loader.load(&self.ctx).await?;
+                self.generation.fetch_add(1, Ordering::SeqCst);
+            }
+            key.clone()
+        };
+
+        self.builder.build(ctx, req, key.as_ref(), expires_in).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockContext;
+    use anyhow::anyhow;
+    use std::sync::atomic::AtomicUsize;
+
+    #[derive(Clone, Debug)]
+    struct TestKey;
+
+    impl Key for TestKey {
+        fn is_valid(&self) -> bool {
+            true
+        }
+    }
+
+    #[derive(Debug, Default)]
+    struct CountingLoader {
+        calls: Arc<AtomicUsize>,
+        failures: usize,
+    }
+
+    #[async_trait::async_trait]
+    impl Load for CountingLoader {
+        type Key = TestKey;
+
+        async fn load(&self, _: &Context) -> Result<Option<TestKey>> {
+            let calls = self.calls.fetch_add(1, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            if calls < self.failures {
+                return Err(anyhow!("temporary failure"));
+            }
+            Ok(Some(TestKey))
+        }
+    }
+
+    /// Key that expires at the given time.
+    #[derive(Clone, Debug)]
+    struct ExpiringKey(DateTime);
+
+    impl Key for ExpiringKey {
+        fn is_valid(&self) -> bool {
+            self.is_valid_at(crate::time::now())
+        }
+
+        fn is_valid_at(&self, now: DateTime) -> bool {
+            self.0 > now
+        }
+    }
+
+    #[derive(Debug)]
+    struct ExpiringLoader {
+        calls: Arc<AtomicUsize>,
+        expires_in: DateTime,
+    }
+
+    #[async_trait::async_trait]
+    impl Load for ExpiringLoader {
+        type Key = ExpiringKey;
+
+        async fn load(&self, _: &Context) -> Result<Option<ExpiringKey>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(Some(ExpiringKey(self.expires_in)))
+        }
+    }
+
+    #[derive(Debug)]
+    struct ExpiringBuilder;
+
+    #[async_trait::async_trait]
+    impl Build for ExpiringBuilder {
+        type Key = ExpiringKey;
+
+        async fn build(
+            &self,
+            _: &Context,
+            _: &mut http::request::Parts,
+            _: Option<&ExpiringKey>,
+            _: Option<Duration>,
+        ) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[derive(Debug)]
+    struct TestBuilder;
+
+    #[async_trait::async_trait]
+    impl Build for TestBuilder {
+        type Key = TestKey;
+
+        async fn build(
+            &self,
+            ctx: &Context,
+            req: &mut http::request::Parts,
+            _: Option<&TestKey>,
+            _: Option<Duration>,
+        ) -> Result<()> {
+            req.headers.insert(
+                http::header::AUTHORIZATION,
+                http::HeaderValue::from_static("test"),
+            );
+            req.headers
+                .insert(http::header::DATE, ctx.now().timestamp().into());
+            Ok(())
+        }
+    }
+
+    fn parts() -> http::request::Parts {
+        http::Request::get("https://example.com")
+            .body(())
+            .unwrap()
+            .into_parts()
+            .0
+    }
+
+    #[tokio::test]
+    async fn test_sign_single_flight() -> Result<()> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let loader = CountingLoader {
+            calls: calls.clone(),
+            failures: 0,
+        };
+        let signer = Signer::new(MockContext::new().context(), loader, TestBuilder);
+
+        let tasks = (0..50)
+            .map(|_| {
+                let signer = signer.clone();
+                tokio::spawn(async move { signer.sign(&mut parts(), None).await })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            task.await??;
+        }
+
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sign_expiry_follows_context_clock() -> Result<()> {
+        let expires_in = crate::time::parse_rfc3339("2024-01-01T00:00:00Z")?;
+        let sign_at = |clock: DateTime| {
+            let calls = Arc::new(AtomicUsize::new(0));
+            let signer = Signer::new(
+                MockContext::new().context().with_clock(FixedClock(clock)),
+                ExpiringLoader {
+                    calls: calls.clone(),
+                    expires_in,
+                },
+                ExpiringBuilder,
+            );
+            async move {
+                signer.sign(&mut parts(), None).await?;
+                signer.sign(&mut parts(), None).await?;
+                Ok::<_, anyhow::Error>(calls.load(Ordering::SeqCst))
+            }
+        };
+
+        // The key is still valid at the fixed clock although it has expired
+        // in wall-clock time.
+        let hour = chrono::TimeDelta::try_hours(1).unwrap();
+        assert_eq!(sign_at(expires_in - hour).await?, 1);
+        // The expired key is loaded again for every request.
+        assert_eq!(sign_at(expires_in + hour).await?, 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reload() -> Result<()> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let loader = CountingLoader {
+            calls: calls.clone(),
+            failures: 0,
         };
+        let signer = Signer::new(MockContext::new().context(), loader, TestBuilder);
+
+        signer.sign(&mut parts(), None).await?;
+        assert!(signer.reload().await?.is_some());
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+
+        // Concurrent reloads only load once.
+        let tasks = (0..50)
+            .map(|_| {
+                let signer = signer.clone();
+                tokio::spawn(async move { signer.reload().await })
+            })
+            .collect::<Vec<_>>();
+        for task in tasks {
+            assert!(task.await??.is_some());
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        // The reloaded key is used for signing.
+        signer.sign(&mut parts(), None).await?;
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sign_retry_after_failure() -> Result<()> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let loader = CountingLoader {
+            calls: calls.clone(),
+            failures: 1,
+        };
+        let signer = Signer::new(MockContext::new().context(), loader, TestBuilder);
+
+        assert!(signer.sign(&mut parts(), None).await.is_err());
+        signer.sign(&mut parts(), None).await?;
+        signer.sign(&mut parts(), None).await?;
+
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sign_anonymous() -> Result<()> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let loader = CountingLoader {
+            calls: calls.clone(),
+            failures: 0,
+        };
+        let signer =
+            Signer::new(MockContext::new().context(), loader, TestBuilder).with_anonymous();
+
+        let mut req = parts();
+        signer.sign(&mut req, None).await?;
+
+        assert!(req.headers.get(http::header::AUTHORIZATION).is_none());
+        assert_eq!(calls.load(Ordering::SeqCst), 0);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sign_with_time() -> Result<()> {
+        let signer = Signer::new(
+            MockContext::new().context(),
+            CountingLoader::default(),
+            TestBuilder,
+        );
+        let time = chrono::DateTime::parse_from_rfc3339("2022-03-13T07:20:04Z")?
+            .with_timezone(&chrono::Utc);
 
-        self.builder
-            .build(&self.ctx, req, key.as_ref(), expires_in)
-            .await
+        let mut req = parts();
+        signer.sign_with_time(&mut req, None, time).await?;
+        assert_eq!(
+            req.headers[http::header::DATE],
+            time.timestamp().to_string()
+        );
+        Ok(())
     }
 }
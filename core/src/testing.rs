@@ -0,0 +1,268 @@
+use crate::{Context, Error, FileRead, HttpSend, StaticEnv};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::{Method, StatusCode};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// MockContext builds a [`Context`] that serves canned responses, useful for
+/// testing loaders without touching the network or file system.
+///
+/// Responses are keyed by method and full url. Requests without a registered
+/// response will return an error, requests registered via
+/// [`MockContext::with_timeout`] return [`Error::Timeout`]. Registering the
+/// same request more than once serves the responses in order and repeats the
+/// last one. Every sent request is recorded and can be inspected via
+/// [`MockContext::requests`].
+///
+/// ```
+/// use http::{Method, StatusCode};
+/// use reqsign_core::MockContext;
+///
+/// # async fn example() -> anyhow::Result<()> {
+/// let mock = MockContext::new().with_response(
+///     Method::GET,
+///     "http://127.0.0.1/hello",
+///     StatusCode::OK,
+///     "world",
+/// );
+/// let ctx = mock.context();
+///
+/// let req = http::Request::get("http://127.0.0.1/hello").body(bytes::Bytes::new())?;
+/// let resp = ctx.http_send_as_string(req).await?;
+/// assert_eq!(resp.body(), "world");
+/// assert_eq!(mock.requests().len(), 1);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockContext {
+    state: Arc<Mutex<MockState>>,
+}
+
+/// The response of a request, `None` means timeout.
+type MockResponse = Option<(StatusCode, Bytes)>;
+
+#[derive(Debug, Default)]
+struct MockState {
+    responses: HashMap<(Method, String), VecDeque<MockResponse>>,
+    files: HashMap<String, Vec<u8>>,
+    requests: Vec<http::Request<Bytes>>,
+}
+
+impl MockContext {
+    /// Create a new mock context without any responses.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the response for requests to `url` with `method`.
+    pub fn with_response(
+        self,
+        method: Method,
+        url: &str,
+        status: StatusCode,
+        body: impl Into<Bytes>,
+    ) -> Self {
+        self.state
+            .lock()
+            .expect("lock poisoned")
+            .responses
+            .entry((method, url.to_string()))
+            .or_default()
+            .push_back(Some((status, body.into())));
+        self
+    }
+
+    /// Let requests to `url` with `method` time out.
+    pub fn with_timeout(self, method: Method, url: &str) -> Self {
+        self.state
+            .lock()
+            .expect("lock poisoned")
+            .responses
+            .entry((method, url.to_string()))
+            .or_default()
+            .push_back(None);
+        self
+    }
+
+    /// Register the content of the file at `path`.
+    pub fn with_file(self, path: &str, content: impl Into<Vec<u8>>) -> Self {
+        self.state
+            .lock()
+            .expect("lock poisoned")
+            .files
+            .insert(path.to_string(), content.into());
+        self
+    }
+
+    /// Build a [`Context`] backed by this mock.
+    ///
+    /// The context uses an empty [`StaticEnv`], call [`Context::with_env`]
+    /// to provide env values.
+    pub fn context(&self) -> Context {
+        Context::new(self.clone(), self.clone()).with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::new(),
+        })
+    }
+
+    /// Get all requests sent so far in order.
+    pub fn requests(&self) -> Vec<http::Request<Bytes>> {
+        self.state
+            .lock()
+            .expect("lock poisoned")
+            .requests
+            .iter()
+            .map(|req| {
+                let mut cloned = http::Request::new(req.body().clone());
+                *cloned.method_mut() = req.method().clone();
+                *cloned.uri_mut() = req.uri().clone();
+                *cloned.version_mut() = req.version();
+                *cloned.headers_mut() = req.headers().clone();
+                *cloned.extensions_mut() = req.extensions().clone();
+                cloned
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl FileRead for MockContext {
+    async fn file_read(&self, path: &str) -> Result<Vec<u8>> {
+        self.state
+            .lock()
+            .expect("lock poisoned")
+            .files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| anyhow!("no mock file for {path}"))
+    }
+
+    async fn file_write(&self, path: &str, content: &[u8]) -> Result<()> {
+        self.state
+            .lock()
+            .expect("lock poisoned")
+            .files
+            .insert(path.to_string(), content.to_vec());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl HttpSend for MockContext {
+    async fn http_send(&self, req: http::Request<Bytes>) -> Result<http::Response<Bytes>> {
+        let mut state = self.state.lock().expect("lock poisoned");
+        let key = (req.method().clone(), req.uri().to_string());
+        let resp = state.responses.get_mut(&key).and_then(|queue| {
+            if queue.len() > 1 {
+                queue.pop_front()
+            } else {
+                queue.front().cloned()
+            }
+        });
+        state.requests.push(req);
+
+        let resp = resp.ok_or_else(|| anyhow!("no mock response for {} {}", key.0, key.1))?;
+        let (status, body) =
+            resp.ok_or_else(|| Error::Timeout(format!("mock timeout for {} {}", key.0, key.1)))?;
+        Ok(http::Response::builder().status(status).body(body)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_mock_context() -> Result<()> {
+        let mock = MockContext::new()
+            .with_response(
+                Method::PUT,
+                "http://127.0.0.1/token",
+                StatusCode::OK,
+                "token",
+            )
+            .with_timeout(Method::GET, "http://127.0.0.1/slow")
+            .with_response(Method::GET, "http://127.0.0.1/slow", StatusCode::OK, "done")
+            .with_file("/tmp/token", "file_token");
+        let ctx = mock.context();
+
+        let req = http::Request::put("http://127.0.0.1/token")
+            .header("x-test", "value")
+            .body(Bytes::new())?;
+        let resp = ctx.http_send_as_string(req).await?;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.body(), "token");
+
+        // Method is part of the key.
+        let req = http::Request::get("http://127.0.0.1/token").body(Bytes::new())?;
+        assert!(ctx.http_send(req).await.is_err());
+
+        let req = http::Request::get("http://127.0.0.1/slow").body(Bytes::new())?;
+        let err = ctx.http_send(req).await.expect_err("request must time out");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Timeout(_))
+        ));
+        // Later responses are served in order and the last one is repeated.
+        for _ in 0..2 {
+            let req = http::Request::get("http://127.0.0.1/slow").body(Bytes::new())?;
+            assert_eq!(ctx.http_send_as_string(req).await?.body(), "done");
+        }
+
+        assert_eq!(ctx.file_read_as_string("/tmp/token").await?, "file_token");
+        ctx.file_write("/tmp/token", b"new_token").await?;
+        assert_eq!(ctx.file_read_as_string("/tmp/token").await?, "new_token");
+        assert!(ctx.file_read("/tmp/not_exist").await.is_err());
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 5);
+        assert_eq!(requests[0].method(), Method::PUT);
+        assert_eq!(requests[0].headers()["x-test"], "value");
+        assert_eq!(requests[1].method(), Method::GET);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_context_user_agent() -> Result<()> {
+        let mock = MockContext::new().with_response(
+            Method::GET,
+            "http://127.0.0.1/token",
+            StatusCode::OK,
+            "token",
+        );
+
+        let req = http::Request::get("http://127.0.0.1/token").body(Bytes::new())?;
+        mock.context().http_send(req).await?;
+        let req = http::Request::get("http://127.0.0.1/token").body(Bytes::new())?;
+        mock.context()
+            .with_user_agent("my-app/1.0")
+            .http_send_as_string(req)
+            .await?;
+        // User-Agent set by the request is kept.
+        let req = http::Request::get("http://127.0.0.1/token")
+            .header(http::header::USER_AGENT, "aws-cli/2.0")
+            .body(Bytes::new())?;
+        mock.context()
+            .with_user_agent("my-app/1.0")
+            .http_send(req)
+            .await?;
+
+        let requests = mock.requests();
+        assert_eq!(
+            requests[0].headers()[http::header::USER_AGENT],
+            concat!("reqsign/", env!("CARGO_PKG_VERSION"))
+        );
+        assert_eq!(
+            requests[1].headers()[http::header::USER_AGENT],
+            "my-app/1.0"
+        );
+        assert_eq!(
+            requests[2].headers()[http::header::USER_AGENT],
+            "aws-cli/2.0"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt::{Debug, Formatter};
+
+use anyhow::Result;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+
+use crate::time::{now, DateTime};
+use crate::utils::Redact;
+use crate::{Context, Key, Load};
+
+/// Bearer token like a projected kubernetes service account token.
+///
+/// It's usually not used to sign requests directly, but exchanged for
+/// service credentials via OIDC flows like AWS `AssumeRoleWithWebIdentity`.
+#[derive(Clone)]
+pub struct BearerToken {
+    /// The raw token.
+    pub token: String,
+    /// Expiration time for this token, parsed from the JWT `exp` claim.
+    pub expires_in: Option<DateTime>,
+}
+
+impl Debug for BearerToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BearerToken")
+            .field("token", &Redact::from(&self.token))
+            .field("expires_in", &self.expires_in)
+            .finish()
+    }
+}
+
+impl Key for BearerToken {
+    fn is_valid(&self) -> bool {
+        self.is_valid_at(now())
+    }
+
+    fn is_valid_at(&self, now: DateTime) -> bool {
+        if self.token.is_empty() {
+            return false;
+        }
+        // Take 120s as buffer to avoid edge cases.
+        self.expires_in
+            .map(|v| v > now + chrono::TimeDelta::try_minutes(2).expect("in bounds"))
+            .unwrap_or(true)
+    }
+}
+
+/// TokenFileLoader will load [`BearerToken`] from a file, for example, a
+/// projected service account token at `/var/run/secrets/tokens/token`.
+///
+/// The token's expiration is parsed from the JWT `exp` claim without
+/// verifying the signature, so a [`crate::Signer`] will re-read the file
+/// before the token expires. Tokens that are not JWTs never expire.
+#[derive(Debug, Clone)]
+pub struct TokenFileLoader {
+    path: String,
+}
+
+impl TokenFileLoader {
+    /// Create a new loader that reads the token from `path`.
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Load for TokenFileLoader {
+    type Key = BearerToken;
+
+    async fn load(&self, ctx: &Context) -> Result<Option<Self::Key>> {
+        let token = ctx.file_read_as_string(&self.path).await?;
+        let token = token.trim().to_string();
+        if token.is_empty() {
+            return Ok(None);
+        }
+
+        let expires_in = jwt_expiration(&token);
+        Ok(Some(BearerToken { token, expires_in }))
+    }
+}
+
+/// Parse the `exp` claim of a JWT without verifying its signature.
+fn jwt_expiration(token: &str) -> Option<DateTime> {
+    #[derive(serde::Deserialize)]
+    struct Claims {
+        exp: i64,
+    }
+
+    let payload = token.split('.').nth(1)?;
+    let payload = URL_SAFE_NO_PAD.decode(payload.trim_end_matches('=')).ok()?;
+    let claims: Claims = serde_json::from_slice(&payload).ok()?;
+    DateTime::from_timestamp(claims.exp, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MockContext;
+
+    fn jwt(exp: i64) -> String {
+        let payload = URL_SAFE_NO_PAD.encode(format!(
+            r#"{{"sub":"system:serviceaccount:default:demo","exp":{exp}}}"#
+        ));
+        format!("eyJhbGciOiJSUzI1NiJ9.{payload}.signature")
+    }
+
+    #[tokio::test]
+    async fn test_token_file_loader() -> Result<()> {
+        let exp = now().timestamp() + 3600;
+        let ctx = MockContext::new()
+            .with_file("/var/run/secrets/tokens/token", format!("{}\n", jwt(exp)))
+            .context();
+
+        let token = TokenFileLoader::new("/var/run/secrets/tokens/token")
+            .load(&ctx)
+            .await?
+            .expect("token must be loaded");
+        assert_eq!(token.token, jwt(exp));
+        assert_eq!(token.expires_in, DateTime::from_timestamp(exp, 0));
+        assert!(token.is_valid());
+
+        // Tokens about to expire should be re-read.
+        let token = BearerToken {
+            expires_in: Some(now() + chrono::TimeDelta::try_seconds(60).unwrap()),
+            ..token
+        };
+        assert!(!token.is_valid());
+        assert!(token.is_valid_at(now() - chrono::TimeDelta::try_hours(1).unwrap()));
+        Ok(())
+    }
+
+    #[test]
+    fn test_jwt_expiration() {
+        assert_eq!(
+            jwt_expiration(&jwt(1700000000)),
+            DateTime::from_timestamp(1700000000, 0)
+        );
+        assert_eq!(jwt_expiration("not-a-jwt"), None);
+        assert_eq!(jwt_expiration("a.b.c"), None);
+    }
+}
@@ -2,6 +2,24 @@
 
 use std::fmt::Debug;
 
+use anyhow::Result;
+use http::HeaderValue;
+
+use crate::hash::base64_md5;
+
+/// Compute the base64 encoded MD5 of body and set it as the `Content-MD5`
+/// header.
+///
+/// Operations like S3 `CompleteMultipartUpload` and Azure Blob `Put Block`
+/// require this header. Signers that include `Content-MD5` in their string
+/// to sign, like Azure shared key, will pick it up while signing.
+pub fn set_content_md5(parts: &mut http::request::Parts, body: &[u8]) -> Result<()> {
+    parts
+        .headers
+        .insert("content-md5", HeaderValue::try_from(base64_md5(body))?);
+    Ok(())
+}
+
 /// Redacts a string by replacing all but the first and last three characters with asterisks.
 ///
 /// - If the input string has fewer than 12 characters, it should be entirely redacted.
@@ -51,6 +69,17 @@ impl<'a> Debug for Redact<'a> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_set_content_md5() -> Result<()> {
+        let (mut parts, _) = http::Request::put("https://example.com/object")
+            .body(())?
+            .into_parts();
+
+        set_content_md5(&mut parts, b"Hello, World!")?;
+        assert_eq!(parts.headers["content-md5"], "ZajifYh5KDgxtmS9i38K1A==");
+        Ok(())
+    }
+
     #[test]
     fn test_redact() {
         let cases = vec![
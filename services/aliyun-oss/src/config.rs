@@ -45,6 +45,11 @@ pub struct Config {
     /// - this field if it's `is_some`
     /// - env value: [`ALIBABA_CLOUD_STS_ENDPOINT`]
     pub sts_endpoint: Option<String>,
+    /// `ecs_ram_role` will be loaded from
+    ///
+    /// - this field if it's `is_some`
+    /// - env value: [`ALIBABA_CLOUD_ECS_METADATA`]
+    pub ecs_ram_role: Option<String>,
 }
 
 impl Default for Config {
@@ -58,6 +63,7 @@ impl Default for Config {
             oidc_provider_arn: None,
             oidc_token_file: None,
             sts_endpoint: None,
+            ecs_ram_role: None,
         }
     }
 }
@@ -88,6 +94,9 @@ impl Config {
         if let Some(v) = envs.get(ALIBABA_CLOUD_STS_ENDPOINT) {
             self.sts_endpoint.get_or_insert(v.clone());
         }
+        if let Some(v) = envs.get(ALIBABA_CLOUD_ECS_METADATA) {
+            self.ecs_ram_role.get_or_insert(v.clone());
+        }
 
         self
     }
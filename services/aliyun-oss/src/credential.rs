@@ -1,3 +1,5 @@
+use std::fmt::Debug;
+use std::fmt::Formatter;
 use std::fs;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -5,6 +7,10 @@ use std::sync::Mutex;
 use anyhow::anyhow;
 use anyhow::Result;
 use log::debug;
+use percent_encoding::utf8_percent_encode;
+use percent_encoding::AsciiSet;
+use percent_encoding::NON_ALPHANUMERIC;
+use reqsign_core::hash::base64_hmac_sha1;
 use reqwest::Client;
 use serde::Deserialize;
 
@@ -13,10 +19,10 @@ use reqsign_core::time::format_rfc3339;
 use reqsign_core::time::now;
 use reqsign_core::time::parse_rfc3339;
 use reqsign_core::time::DateTime;
+use reqsign_core::utils::Redact;
 
 /// Credential that holds the access_key and secret_key.
 #[derive(Default, Clone)]
-#[cfg_attr(test, derive(Debug))]
 pub struct Credential {
     /// Access key id for credential.
     pub access_key_id: String,
@@ -28,6 +34,17 @@ pub struct Credential {
     pub expires_in: Option<DateTime>,
 }
 
+impl Debug for Credential {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credential")
+            .field("access_key_id", &Redact::from(&self.access_key_id))
+            .field("access_key_secret", &Redact::from(&self.access_key_secret))
+            .field("security_token", &Redact::from(&self.security_token))
+            .field("expires_in", &self.expires_in)
+            .finish()
+    }
+}
+
 impl Credential {
     /// is current cred is valid?
     pub fn is_valid(&self) -> bool {
@@ -89,6 +106,14 @@ impl Loader {
     }
 
     async fn load_inner(&self) -> Result<Option<Credential>> {
+        if let Ok(Some(cred)) = self
+            .load_via_assume_role()
+            .await
+            .map_err(|err| debug!("load credential via assume_role failed: {err:?}"))
+        {
+            return Ok(Some(cred));
+        }
+
         if let Ok(Some(cred)) = self
             .load_via_static()
             .map_err(|err| debug!("load credential via static failed: {err:?}"))
@@ -104,6 +129,14 @@ impl Loader {
             return Ok(Some(cred));
         }
 
+        if let Ok(Some(cred)) = self
+            .load_via_ecs_ram_role()
+            .await
+            .map_err(|err| debug!("load credential via ecs_ram_role failed: {err:?}"))
+        {
+            return Ok(Some(cred));
+        }
+
         Ok(None)
     }
 
@@ -151,7 +184,70 @@ impl Loader {
             return Err(anyhow!("request to Aliyun STS Services failed: {content}"));
         }
 
-        let resp: AssumeRoleWithOidcResponse = serde_json::from_slice(&resp.bytes().await?)?;
+        let resp: AssumeRoleResponse = serde_json::from_slice(&resp.bytes().await?)?;
+        let resp_cred = resp.credentials;
+
+        let cred = Credential {
+            access_key_id: resp_cred.access_key_id,
+            access_key_secret: resp_cred.access_key_secret,
+            security_token: Some(resp_cred.security_token),
+            expires_in: Some(parse_rfc3339(&resp_cred.expiration)?),
+        };
+
+        Ok(Some(cred))
+    }
+
+    /// Assume role with the static access key.
+    ///
+    /// Only used if `role_arn` is set without `oidc_token_file`, so the
+    /// static access key will be used as the source credential.
+    async fn load_via_assume_role(&self) -> Result<Option<Credential>> {
+        let (Some(ak), Some(sk), Some(role_arn)) = (
+            &self.config.access_key_id,
+            &self.config.access_key_secret,
+            &self.config.role_arn,
+        ) else {
+            return Ok(None);
+        };
+        if self.config.oidc_token_file.is_some() {
+            return Ok(None);
+        }
+
+        let mut params = vec![
+            ("AccessKeyId".to_string(), ak.clone()),
+            ("Action".to_string(), "AssumeRole".to_string()),
+            ("Format".to_string(), "JSON".to_string()),
+            ("RoleArn".to_string(), role_arn.clone()),
+            (
+                "RoleSessionName".to_string(),
+                self.config.role_session_name.clone(),
+            ),
+            ("SignatureMethod".to_string(), "HMAC-SHA1".to_string()),
+            (
+                "SignatureNonce".to_string(),
+                format!("{:032x}", rand::random::<u128>()),
+            ),
+            ("SignatureVersion".to_string(), "1.0".to_string()),
+            ("Timestamp".to_string(), format_rfc3339(now())),
+            ("Version".to_string(), "2015-04-01".to_string()),
+        ];
+        if let Some(token) = &self.config.security_token {
+            params.push(("SecurityToken".to_string(), token.clone()));
+        }
+
+        let url = format!(
+            "{}/?{}",
+            self.get_sts_endpoint(),
+            rpc_signed_query("GET", params, sk)
+        );
+
+        let resp = self.client.get(&url).send().await?;
+        if resp.status() != http::StatusCode::OK {
+            let content = resp.text().await?;
+            return Err(anyhow!("request to Aliyun STS Services failed: {content}"));
+        }
+
+        let resp: AssumeRoleResponse = serde_json::from_slice(&resp.bytes().await?)?;
         let resp_cred = resp.credentials;
 
         let cred = Credential {
@@ -164,6 +260,40 @@ impl Loader {
         Ok(Some(cred))
     }
 
+    /// Load credential from the ECS RAM role metadata service.
+    ///
+    /// Reference: [Instance RAM roles](https://www.alibabacloud.com/help/en/ecs/user-guide/attach-an-instance-ram-role-to-an-ecs-instance)
+    async fn load_via_ecs_ram_role(&self) -> Result<Option<Credential>> {
+        let Some(role) = &self.config.ecs_ram_role else {
+            return Ok(None);
+        };
+
+        let url =
+            format!("{ECS_METADATA_ENDPOINT}/latest/meta-data/ram/security-credentials/{role}");
+        let resp = self.client.get(&url).send().await?;
+        if resp.status() != http::StatusCode::OK {
+            let content = resp.text().await?;
+            return Err(anyhow!("request to Aliyun ECS metadata failed: {content}"));
+        }
+
+        let resp: EcsRamRoleCredentials = serde_json::from_slice(&resp.bytes().await?)?;
+        if resp.code != "Success" {
+            return Err(anyhow!(
+                "load credential from Aliyun ECS metadata failed: {}",
+                resp.code
+            ));
+        }
+
+        let cred = Credential {
+            access_key_id: resp.access_key_id,
+            access_key_secret: resp.access_key_secret,
+            security_token: Some(resp.security_token),
+            expires_in: Some(parse_rfc3339(&resp.expiration)?),
+        };
+
+        Ok(Some(cred))
+    }
+
     fn get_sts_endpoint(&self) -> String {
         match &self.config.sts_endpoint {
             Some(defined_sts_endpoint) => format!("https://{}", defined_sts_endpoint),
@@ -172,20 +302,77 @@ impl Loader {
     }
 }
 
+/// Endpoint of the ECS metadata service.
+const ECS_METADATA_ENDPOINT: &str = "http://100.100.100.200";
+
+/// AsciiSet for [Aliyun RPC percent encode](https://www.alibabacloud.com/help/en/sdk/product-overview/rpc-mechanism)
+///
+/// - Encode every byte except the unreserved characters: 'A'-'Z', 'a'-'z', '0'-'9', '-', '.', '_', and '~'.
+static RPC_ENCODE_SET: AsciiSet = NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Build the signed query of an Aliyun RPC request.
+///
+/// ```text
+/// StringToSign = Method + "&" + percentEncode("/") + "&" + percentEncode(CanonicalizedQueryString)
+/// Signature = Base64(HMAC-SHA1(AccessKeySecret + "&", StringToSign))
+/// ```
+fn rpc_signed_query(method: &str, mut params: Vec<(String, String)>, secret: &str) -> String {
+    params.sort();
+
+    let query = params
+        .iter()
+        .map(|(k, v)| {
+            format!(
+                "{}={}",
+                utf8_percent_encode(k, &RPC_ENCODE_SET),
+                utf8_percent_encode(v, &RPC_ENCODE_SET)
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let string_to_sign = format!(
+        "{method}&%2F&{}",
+        utf8_percent_encode(&query, &RPC_ENCODE_SET)
+    );
+    debug!("string to sign: {string_to_sign}");
+    let signature = base64_hmac_sha1(format!("{secret}&").as_bytes(), string_to_sign.as_bytes());
+
+    format!(
+        "{query}&Signature={}",
+        utf8_percent_encode(&signature, &RPC_ENCODE_SET)
+    )
+}
+
+/// Response of `AssumeRole` and `AssumeRoleWithOIDC`.
 #[derive(Default, Debug, Deserialize)]
 #[serde(default)]
-struct AssumeRoleWithOidcResponse {
+struct AssumeRoleResponse {
     #[serde(rename = "Credentials")]
-    credentials: AssumeRoleWithOidcCredentials,
+    credentials: AssumeRoleCredentials,
+}
+
+#[derive(Default, Debug, Deserialize)]
+#[serde(default, rename_all = "PascalCase")]
+struct AssumeRoleCredentials {
+    access_key_id: String,
+    access_key_secret: String,
+    security_token: String,
+    expiration: String,
 }
 
 #[derive(Default, Debug, Deserialize)]
 #[serde(default, rename_all = "PascalCase")]
-struct AssumeRoleWithOidcCredentials {
+struct EcsRamRoleCredentials {
     access_key_id: String,
     access_key_secret: String,
     security_token: String,
     expiration: String,
+    code: String,
 }
 
 #[cfg(test)]
@@ -233,7 +420,7 @@ mod tests {
     }
 }"#;
 
-        let resp: AssumeRoleWithOidcResponse =
+        let resp: AssumeRoleResponse =
             serde_json::from_str(content).expect("json deserialize must success");
 
         assert_eq!(
@@ -253,6 +440,52 @@ mod tests {
         Ok(())
     }
 
+    /// Example from [RPC signature](https://www.alibabacloud.com/help/en/sdk/product-overview/rpc-mechanism)
+    #[test]
+    fn test_rpc_signed_query() {
+        let params = vec![
+            ("Version".to_string(), "2014-05-26".to_string()),
+            ("Action".to_string(), "DescribeRegions".to_string()),
+            ("AccessKeyId".to_string(), "testid".to_string()),
+            ("Format".to_string(), "XML".to_string()),
+            ("SignatureMethod".to_string(), "HMAC-SHA1".to_string()),
+            (
+                "SignatureNonce".to_string(),
+                "3ee8c1b8-83d3-44af-a94f-4e0ad82fd6cf".to_string(),
+            ),
+            ("SignatureVersion".to_string(), "1.0".to_string()),
+            ("Timestamp".to_string(), "2016-02-23T12:46:24Z".to_string()),
+        ];
+
+        assert_eq!(
+            rpc_signed_query("GET", params, "testsecret"),
+            "AccessKeyId=testid&Action=DescribeRegions&Format=XML&SignatureMethod=HMAC-SHA1&SignatureNonce=3ee8c1b8-83d3-44af-a94f-4e0ad82fd6cf&SignatureVersion=1.0&Timestamp=2016-02-23T12%3A46%3A24Z&Version=2014-05-26&Signature=OLeaidS1JvxuMvnyHOwuJ%2BuX5qY%3D"
+        );
+    }
+
+    #[test]
+    fn test_parse_ecs_ram_role_response() -> Result<()> {
+        let content = r#"{
+    "AccessKeyId": "STS.*******6YSE",
+    "AccessKeySecret": "aj******jDU",
+    "Expiration": "2017-11-01T05:20:01Z",
+    "SecurityToken": "CAISng********",
+    "LastUpdated": "2017-10-31T23:20:01Z",
+    "Code": "Success"
+}"#;
+
+        let resp: EcsRamRoleCredentials =
+            serde_json::from_str(content).expect("json deserialize must success");
+
+        assert_eq!(&resp.access_key_id, "STS.*******6YSE");
+        assert_eq!(&resp.access_key_secret, "aj******jDU");
+        assert_eq!(&resp.security_token, "CAISng********");
+        assert_eq!(&resp.expiration, "2017-11-01T05:20:01Z");
+        assert_eq!(&resp.code, "Success");
+
+        Ok(())
+    }
+
     #[test]
     fn test_signer_with_oidc() -> Result<()> {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -1,31 +1,41 @@
 //! Aliyun OSS Signer
 
 use std::collections::HashSet;
-use std::fmt::Write;
 use std::time::Duration;
 
 use anyhow::Result;
 use http::header::AUTHORIZATION;
-use http::header::CONTENT_TYPE;
 use http::header::DATE;
 use http::HeaderValue;
 use log::debug;
 use once_cell::sync::Lazy;
 use percent_encoding::utf8_percent_encode;
 
+use super::constants::*;
 use super::credential::Credential;
-use reqsign_core::hash::base64_hmac_sha1;
+use reqsign_core::canonical;
+use reqsign_core::canonical::CanonicalRules;
+use reqsign_core::canonical::HmacAlgorithm;
+use reqsign_core::hash::hex_hmac_sha256;
+use reqsign_core::hash::hex_sha256;
+use reqsign_core::hash::hmac_sha256;
 use reqsign_core::time;
+use reqsign_core::time::format_date;
 use reqsign_core::time::format_http_date;
+use reqsign_core::time::format_iso8601;
 use reqsign_core::time::DateTime;
+use reqsign_core::Error;
 use reqsign_core::SigningMethod;
 use reqsign_core::SigningRequest;
 
-const CONTENT_MD5: &str = "content-md5";
-
 /// Signer for Aliyun OSS.
+///
+/// Requests are signed with the legacy v1 signature by default, use
+/// [`Signer::with_v4`] to sign with `OSS4-HMAC-SHA256` instead.
 pub struct Signer {
     bucket: String,
+    region: Option<String>,
+    time: Option<DateTime>,
 }
 
 impl Signer {
@@ -33,9 +43,36 @@ impl Signer {
     pub fn new(bucket: &str) -> Self {
         Self {
             bucket: bucket.to_owned(),
+            region: None,
+            time: None,
         }
     }
 
+    /// Sign requests with the v4 signature (`OSS4-HMAC-SHA256`) in `region`.
+    ///
+    /// `region` is the region id without the `oss-` prefix, like
+    /// `cn-hangzhou`. Only header signing is supported for v4 for now.
+    ///
+    /// # Reference
+    ///
+    /// [Signature Version 4](https://help.aliyun.com/zh/oss/developer-reference/recommend-to-use-signature-version-4)
+    pub fn with_v4(mut self, region: &str) -> Self {
+        self.region = Some(region.to_owned());
+        self
+    }
+
+    /// Specify the signing time.
+    ///
+    /// # Note
+    ///
+    /// We should always take current time to sign requests.
+    /// Only use this function for testing.
+    #[cfg(test)]
+    pub fn time(mut self, time: DateTime) -> Self {
+        self.time = Some(time);
+        self
+    }
+
     /// Building a signing context.
     fn build(
         &self,
@@ -43,12 +80,22 @@ impl Signer {
         method: SigningMethod,
         cred: &Credential,
     ) -> Result<SigningRequest> {
-        let now = time::now();
+        if self.region.is_some() && method != SigningMethod::Header {
+            return Err(
+                Error::Config("query signing is not supported by OSS v4 yet".to_string()).into(),
+            );
+        }
+
+        let now = self.time.unwrap_or_else(time::now);
         let mut ctx = SigningRequest::build(req)?;
+        if let Some(region) = &self.region {
+            self.build_v4(&mut ctx, cred, now, region)?;
+            return Ok(ctx);
+        }
 
         let string_to_sign = string_to_sign(&mut ctx, cred, now, method, &self.bucket)?;
-        let signature =
-            base64_hmac_sha1(cred.access_key_secret.as_bytes(), string_to_sign.as_bytes());
+        let signature = HmacAlgorithm::HmacSha1
+            .sign(cred.access_key_secret.as_bytes(), string_to_sign.as_bytes());
 
         match method {
             SigningMethod::Header => {
@@ -80,6 +127,48 @@ impl Signer {
         Ok(ctx)
     }
 
+    /// Sign the request with `OSS4-HMAC-SHA256` in header.
+    fn build_v4(
+        &self,
+        ctx: &mut SigningRequest,
+        cred: &Credential,
+        now: DateTime,
+        region: &str,
+    ) -> Result<()> {
+        ctx.headers.insert(X_OSS_DATE, format_iso8601(now).parse()?);
+        if !ctx.headers.contains_key(X_OSS_CONTENT_SHA_256) {
+            ctx.headers
+                .insert(X_OSS_CONTENT_SHA_256, "UNSIGNED-PAYLOAD".parse()?);
+        }
+        if let Some(token) = &cred.security_token {
+            let mut value: HeaderValue = token.parse()?;
+            value.set_sensitive(true);
+            ctx.headers.insert(X_OSS_SECURITY_TOKEN, value);
+        }
+
+        let scope = format!("{}/{region}/oss/aliyun_v4_request", format_date(now));
+        let creq = canonical_request_v4(ctx, &self.bucket)?;
+        debug!("canonical request: {creq}");
+        let string_to_sign = format!(
+            "OSS4-HMAC-SHA256\n{}\n{scope}\n{}",
+            format_iso8601(now),
+            hex_sha256(creq.as_bytes())
+        );
+        debug!("string to sign: {string_to_sign}");
+
+        let signing_key = signing_key_v4(&cred.access_key_secret, now, region);
+        let signature = hex_hmac_sha256(&signing_key, string_to_sign.as_bytes());
+
+        let mut value: HeaderValue = format!(
+            "OSS4-HMAC-SHA256 Credential={}/{scope},Signature={signature}",
+            cred.access_key_id
+        )
+        .parse()?;
+        value.set_sensitive(true);
+        ctx.headers.insert(AUTHORIZATION, value);
+        Ok(())
+    }
+
     /// Signing request with header.
     pub fn sign(&self, parts: &mut http::request::Parts, cred: &Credential) -> Result<()> {
         let ctx = self.build(parts, SigningMethod::Header, cred)?;
@@ -98,6 +187,28 @@ impl Signer {
     }
 }
 
+/// Canonicalization rules for Aliyun OSS.
+///
+/// # Reference
+///
+/// [Building CanonicalizedOSSHeaders and CanonicalizedResource](https://help.aliyun.com/document_detail/31951.html#section-w2k-sw2-xdb)
+struct OssRules;
+
+impl CanonicalRules for OssRules {
+    fn header_prefix(&self) -> &str {
+        "x-oss-"
+    }
+
+    fn is_sub_resource(&self, key: &str) -> bool {
+        is_sub_resource(key)
+    }
+
+    // OSS requires that the path and query string be percent-decoded.
+    fn percent_decode(&self) -> bool {
+        true
+    }
+}
+
 /// Construct string to sign.
 ///
 /// # Format
@@ -117,97 +228,102 @@ fn string_to_sign(
     method: SigningMethod,
     bucket: &str,
 ) -> Result<String> {
-    let mut s = String::new();
-    s.write_str(ctx.method.as_str())?;
-    s.write_str("\n")?;
-    s.write_str(ctx.header_get_or_default(&CONTENT_MD5.parse()?)?)?;
-    s.write_str("\n")?;
-    s.write_str(ctx.header_get_or_default(&CONTENT_TYPE)?)?;
-    s.write_str("\n")?;
-    match method {
-        SigningMethod::Header => {
-            writeln!(&mut s, "{}", format_http_date(now))?;
-        }
-        SigningMethod::Query(expires) => {
-            writeln!(
-                &mut s,
-                "{}",
-                (now + chrono::TimeDelta::from_std(expires).unwrap()).timestamp()
-            )?;
+    // Insert security token
+    if let Some(token) = &cred.security_token {
+        match method {
+            SigningMethod::Header => {
+                ctx.headers.insert("x-oss-security-token", token.parse()?);
+            }
+            SigningMethod::Query(_) => {
+                ctx.query.push((
+                    "security-token".to_string(),
+                    utf8_percent_encode(token, percent_encoding::NON_ALPHANUMERIC).to_string(),
+                ));
+            }
         }
     }
 
-    {
-        let headers = canonicalize_header(ctx, method, cred)?;
-        if !headers.is_empty() {
-            writeln!(&mut s, "{headers}",)?;
-        }
-    }
-    write!(
-        &mut s,
-        "{}",
-        canonicalize_resource(ctx, bucket, method, cred)
-    )?;
+    let date = match method {
+        SigningMethod::Header => format_http_date(now),
+        SigningMethod::Query(expires) => (now + chrono::TimeDelta::from_std(expires).unwrap())
+            .timestamp()
+            .to_string(),
+    };
 
+    let s = canonical::string_to_sign(ctx, &OssRules, &date, bucket)?;
     debug!("string to sign: {}", &s);
     Ok(s)
 }
 
-/// Build canonicalize header
+/// Construct the canonical request of OSS v4.
 ///
-/// # Reference
-///
-/// [Building CanonicalizedOSSHeaders](https://help.aliyun.com/document_detail/31951.html#section-w2k-sw2-xdb)
-fn canonicalize_header(
-    ctx: &mut SigningRequest,
-    method: SigningMethod,
-    cred: &Credential,
-) -> Result<String> {
-    if method == SigningMethod::Header {
-        // Insert security token
-        if let Some(token) = &cred.security_token {
-            ctx.headers.insert("x-oss-security-token", token.parse()?);
-        }
-    }
-
-    Ok(SigningRequest::header_to_string(
-        ctx.header_to_vec_with_prefix("x-oss-"),
-        ":",
-        "\n",
-    ))
-}
-
-/// Build canonicalize resource
+/// The query in `ctx` is replaced by the encoded one so that it's sent as
+/// signed.
 ///
-/// # Reference
+/// # Format
 ///
-/// [Building CanonicalizedResource](https://help.aliyun.com/document_detail/31951.html#section-w2k-sw2-xdb)
-fn canonicalize_resource(
-    ctx: &mut SigningRequest,
-    bucket: &str,
-    method: SigningMethod,
-    cred: &Credential,
-) -> String {
-    if let SigningMethod::Query(_) = method {
-        // Insert security token
-        if let Some(token) = &cred.security_token {
-            ctx.query.push((
-                "security-token".to_string(),
-                utf8_percent_encode(token, percent_encoding::NON_ALPHANUMERIC).to_string(),
-            ));
-        };
-    }
+/// ```text
+///   VERB + "\n"
+/// + CanonicalURI + "\n"
+/// + CanonicalQueryString + "\n"
+/// + CanonicalHeaders + "\n"
+/// + AdditionalHeaders + "\n"
+/// + HashedPayload
+/// ```
+fn canonical_request_v4(ctx: &mut SigningRequest, bucket: &str) -> Result<String> {
+    let path = ctx.path_percent_decoded();
+    let uri = if bucket.is_empty() {
+        path.to_string()
+    } else {
+        format!("/{bucket}{path}")
+    };
 
-    let params = ctx.query_to_vec_with_filter(is_sub_resource);
+    let mut query = ctx
+        .query
+        .iter()
+        .map(|(k, v)| {
+            (
+                utf8_percent_encode(k, &OSS_QUERY_ENCODE_SET).to_string(),
+                utf8_percent_encode(v, &OSS_QUERY_ENCODE_SET).to_string(),
+            )
+        })
+        .collect::<Vec<_>>();
+    query.sort();
+    ctx.query = query;
 
-    // OSS requires that the query string be percent-decoded.
-    let params_str = SigningRequest::query_to_percent_decoded_string(params, "=", "&");
+    // Only `x-oss-*`, `content-type` and `content-md5` are signed, no
+    // additional headers are listed.
+    let mut headers = ctx
+        .headers
+        .iter()
+        .filter(|(k, _)| {
+            let k = k.as_str();
+            k.starts_with("x-oss-") || k == "content-type" || k == "content-md5"
+        })
+        .map(|(k, v)| Ok((k.as_str().to_string(), v.to_str()?.trim().to_string())))
+        .collect::<Result<Vec<_>>>()?;
+    headers.sort();
+    let headers = headers
+        .into_iter()
+        .map(|(k, v)| format!("{k}:{v}\n"))
+        .collect::<String>();
 
-    if params_str.is_empty() {
-        format!("/{bucket}{}", ctx.path_percent_decoded())
-    } else {
-        format!("/{bucket}{}?{params_str}", ctx.path_percent_decoded())
-    }
+    Ok(format!(
+        "{}\n{}\n{}\n{headers}\n\n{}",
+        ctx.method,
+        utf8_percent_encode(&uri, &OSS_URI_ENCODE_SET),
+        SigningRequest::query_to_string(ctx.query.clone(), "=", "&"),
+        ctx.header_get_or_default(&X_OSS_CONTENT_SHA_256.parse()?)?
+    ))
+}
+
+/// Derive the signing key of OSS v4 from the secret.
+fn signing_key_v4(secret: &str, now: DateTime, region: &str) -> Vec<u8> {
+    let secret = format!("aliyun_v4{secret}");
+    let sign_date = hmac_sha256(secret.as_bytes(), format_date(now).as_bytes());
+    let sign_region = hmac_sha256(&sign_date, region.as_bytes());
+    let sign_service = hmac_sha256(&sign_region, "oss".as_bytes());
+    hmac_sha256(&sign_service, "aliyun_v4_request".as_bytes())
 }
 
 fn is_sub_resource(v: &str) -> bool {
@@ -301,3 +417,174 @@ static SUB_RESOURCES: Lazy<HashSet<&'static str>> = Lazy::new(|| {
         "metaQuery",
     ])
 });
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::header::CONTENT_TYPE;
+    use http::Request;
+
+    const CONTENT_MD5: &str = "content-md5";
+
+    fn test_time() -> DateTime {
+        chrono::DateTime::parse_from_rfc2822("Thu, 17 Nov 2005 18:49:58 GMT")
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+    }
+
+    fn test_credential() -> Credential {
+        Credential {
+            access_key_id: "44CF9590006BF252F707".to_string(),
+            access_key_secret: "OtxrzxIsfpFjA7SwPzILwy8Bw21TLhquhboDYROV".to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Example from [OSS documentation](https://help.aliyun.com/document_detail/31951.html)
+    #[test]
+    fn test_sign_oss_example() -> Result<()> {
+        let req = Request::put("https://oss-example.oss-cn-hangzhou.aliyuncs.com/nelson")
+            .header(CONTENT_MD5, "ODBGOERFMDMzQTczRUY3NUE3NzA5QzdFNUYzMDQxNEM=")
+            .header(CONTENT_TYPE, "text/html")
+            .header("X-OSS-Meta-Author", "foo@bar.com")
+            .header("X-OSS-Magic", "abracadabra")
+            .body(())?;
+        let (mut parts, _) = req.into_parts();
+
+        Signer::new("oss-example")
+            .time(test_time())
+            .sign(&mut parts, &test_credential())?;
+
+        assert_eq!(
+            parts.headers[AUTHORIZATION].to_str()?,
+            "OSS 44CF9590006BF252F707:26NBxoKdsyly4EDv6inkoDft/yA="
+        );
+        assert_eq!(parts.headers[DATE], "Thu, 17 Nov 2005 18:49:58 GMT");
+        Ok(())
+    }
+
+    #[test]
+    fn test_string_to_sign_with_security_token_and_sub_resources() -> Result<()> {
+        let req = Request::get(
+            "https://oss-example.oss-cn-hangzhou.aliyuncs.com/nelson?uploadId=0004B9895DBBB6EC98E&acl&foo=bar&partNumber=1",
+        )
+        .body(())?;
+        let (mut parts, _) = req.into_parts();
+        let mut ctx = SigningRequest::build(&mut parts)?;
+
+        let cred = Credential {
+            security_token: Some("security_token".to_string()),
+            ..test_credential()
+        };
+        let s = string_to_sign(
+            &mut ctx,
+            &cred,
+            test_time(),
+            SigningMethod::Header,
+            "oss-example",
+        )?;
+
+        assert_eq!(
+            s,
+            "GET\n\n\nThu, 17 Nov 2005 18:49:58 GMT\nx-oss-security-token:security_token\n/oss-example/nelson?acl&partNumber=1&uploadId=0004B9895DBBB6EC98E"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign_query_with_security_token() -> Result<()> {
+        let req =
+            Request::get("https://oss-example.oss-cn-hangzhou.aliyuncs.com/nelson").body(())?;
+        let (mut parts, _) = req.into_parts();
+
+        let cred = Credential {
+            security_token: Some("security_token".to_string()),
+            ..test_credential()
+        };
+        Signer::new("oss-example").time(test_time()).sign_query(
+            &mut parts,
+            Duration::from_secs(3600),
+            &cred,
+        )?;
+
+        let query = parts.uri.query().unwrap();
+        assert!(query.contains("OSSAccessKeyId=44CF9590006BF252F707"));
+        assert!(query.contains("Expires=1132256998"));
+        assert!(query.contains("security-token=security%5Ftoken"));
+        assert!(query.contains("Signature="));
+        assert!(parts.headers.get(AUTHORIZATION).is_none());
+        Ok(())
+    }
+
+    /// Example from [aliyun-oss-go-sdk v2](https://github.com/aliyun/alibabacloud-oss-go-sdk-v2/blob/master/oss/signer/signer_v4_test.go)
+    #[test]
+    fn test_sign_v4_example() -> Result<()> {
+        let req = Request::put(
+            "http://bucket.oss-cn-hangzhou.aliyuncs.com/1234+-/123/1.txt?param1=value1&%2Bparam1=value3&%7Cparam1=value4&%2Bparam2&%7Cparam2&param2",
+        )
+        .header("x-oss-head1", "value")
+        .header("abc", "value")
+        .header("ZAbc", "value")
+        .header("XYZ", "value")
+        .header(CONTENT_TYPE, "text/plain")
+        .header("x-oss-content-sha256", "UNSIGNED-PAYLOAD")
+        .body(())?;
+        let (mut parts, _) = req.into_parts();
+        let cred = Credential {
+            access_key_id: "ak".to_string(),
+            access_key_secret: "sk".to_string(),
+            ..Default::default()
+        };
+
+        Signer::new("bucket")
+            .with_v4("cn-hangzhou")
+            .time(chrono::DateTime::from_timestamp(1702743657, 0).unwrap())
+            .sign(&mut parts, &cred)?;
+
+        assert_eq!(
+            parts.headers[AUTHORIZATION].to_str()?,
+            "OSS4-HMAC-SHA256 Credential=ak/20231216/cn-hangzhou/oss/aliyun_v4_request,Signature=e21d18daa82167720f9b1047ae7e7f1ce7cb77a31e8203a7d5f4624fa0284afe"
+        );
+        assert_eq!(parts.headers[X_OSS_DATE], "20231216T162057Z");
+        assert_eq!(
+            parts.uri.query(),
+            Some("%2Bparam1=value3&%2Bparam2&%7Cparam1=value4&%7Cparam2&param1=value1&param2")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_canonical_request_v4_with_security_token() -> Result<()> {
+        let req =
+            Request::get("https://oss-example.oss-cn-hangzhou.aliyuncs.com/nelson").body(())?;
+        let (mut parts, _) = req.into_parts();
+        let cred = Credential {
+            security_token: Some("security_token".to_string()),
+            ..test_credential()
+        };
+
+        Signer::new("oss-example")
+            .with_v4("cn-hangzhou")
+            .time(test_time())
+            .sign(&mut parts, &cred)?;
+        assert_eq!(parts.headers[X_OSS_SECURITY_TOKEN], "security_token");
+        assert_eq!(parts.headers[X_OSS_CONTENT_SHA_256], "UNSIGNED-PAYLOAD");
+
+        // The security token is signed as an x-oss header.
+        let mut ctx = SigningRequest::build(&mut parts)?;
+        assert_eq!(
+            canonical_request_v4(&mut ctx, "oss-example")?,
+            "GET\n/oss-example/nelson\n\nx-oss-content-sha256:UNSIGNED-PAYLOAD\nx-oss-date:20051117T184958Z\nx-oss-security-token:security_token\n\n\nUNSIGNED-PAYLOAD"
+        );
+
+        let err = Signer::new("oss-example")
+            .with_v4("cn-hangzhou")
+            .sign_query(&mut parts, Duration::from_secs(3600), &cred)
+            .expect_err("query signing is not supported");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Config(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+//! Get an S3 object through a `reqwest-middleware` client that signs every
+//! request.
+//!
+//! ```shell
+//! AWS_ACCESS_KEY_ID=... AWS_SECRET_ACCESS_KEY=... AWS_REGION=us-east-1 \
+//!     cargo run --example reqwest_middleware -- https://<bucket>.s3.amazonaws.com/<object>
+//! ```
+
+use std::env;
+use std::sync::Arc;
+
+use anyhow::Result;
+use reqsign_aws_v4::{Builder, Config, DefaultLoader};
+use reqsign_core::{Context, SignMiddleware, Signer};
+use reqsign_file_read_tokio::TokioFileRead;
+use reqsign_http_send_reqwest::ReqwestHttpSend;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let url = env::args().nth(1).expect("object url must be provided");
+    let region = env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+
+    let ctx = Context::new(TokioFileRead, ReqwestHttpSend::default());
+    let config = Arc::new(Config::default().from_env(&ctx));
+    let signer = Signer::new(ctx, DefaultLoader::new(config), Builder::new("s3", &region));
+
+    let client = reqwest_middleware::ClientBuilder::new(reqwest::Client::new())
+        .with(
+            SignMiddleware::new(signer)
+                .with_payload_hash(http::HeaderName::from_static("x-amz-content-sha256")),
+        )
+        .build();
+
+    let resp = client.get(url).send().await?;
+    println!("resp got status: {}", resp.status());
+    println!("resp got body: {}", resp.text().await?);
+    Ok(())
+}
@@ -0,0 +1,49 @@
+//! Sign S3 requests through a `tower` stack on top of a `hyper` client.
+//!
+//! ```shell
+//! AWS_ACCESS_KEY_ID=minioadmin AWS_SECRET_ACCESS_KEY=minioadmin \
+//!     cargo run --example tower_hyper -- http://127.0.0.1:9000/bucket/object
+//! ```
+
+use std::env;
+use std::sync::Arc;
+
+use anyhow::Result;
+use bytes::Bytes;
+use http_body_util::BodyExt;
+use http_body_util::Empty;
+use hyper_util::client::legacy::Client;
+use hyper_util::rt::TokioExecutor;
+use reqsign_aws_v4::{Builder, Config, DefaultLoader};
+use reqsign_core::{Context, SignLayer, Signer};
+use reqsign_file_read_tokio::TokioFileRead;
+use reqsign_http_send_reqwest::ReqwestHttpSend;
+use tower::{ServiceBuilder, ServiceExt};
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let url = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "http://127.0.0.1:9000/bucket/object".to_string());
+    let region = env::var("AWS_REGION").unwrap_or_else(|_| "us-east-1".to_string());
+
+    let ctx = Context::new(TokioFileRead, ReqwestHttpSend::default());
+    let config = Arc::new(Config::default().from_env(&ctx));
+    let signer = Signer::new(ctx, DefaultLoader::new(config), Builder::new("s3", &region));
+
+    let client = Client::builder(TokioExecutor::new()).build_http::<Empty<Bytes>>();
+    let client = ServiceBuilder::new()
+        .layer(SignLayer::new(signer))
+        .service(client);
+
+    let req = http::Request::get(url).body(Empty::new())?;
+    let resp = client
+        .oneshot(req)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))?;
+
+    println!("resp got status: {}", resp.status());
+    let body = resp.into_body().collect().await?.to_bytes();
+    println!("resp got body: {}", String::from_utf8_lossy(&body));
+    Ok(())
+}
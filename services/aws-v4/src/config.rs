@@ -1,16 +1,20 @@
 use std::fmt;
 
 use super::constants::*;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "load", not(target_arch = "wasm32")))]
 use anyhow::anyhow;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "load", not(target_arch = "wasm32")))]
 use anyhow::Result;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "load", not(target_arch = "wasm32")))]
 use ini::Ini;
-#[cfg(not(target_arch = "wasm32"))]
+#[cfg(all(feature = "load", not(target_arch = "wasm32")))]
 use log::debug;
+use log::warn;
+use reqsign_core::time::{parse_rfc3339, DateTime};
 use reqsign_core::utils::Redact;
 use reqsign_core::Context;
+#[cfg(all(feature = "load", not(target_arch = "wasm32")))]
+use reqsign_core::Error;
 
 /// Config for aws services.
 #[derive(Clone)]
@@ -36,14 +40,29 @@ pub struct Config {
     ///
     /// - this field if it's `is_some`
     /// - env value: [`AWS_REGION`]
+    /// - env value: [`AWS_DEFAULT_REGION`]
     /// - profile config: `region`
     pub region: Option<String>,
+    /// `sts_region` will be loaded from:
+    ///
+    /// - this field if it's `is_some`
+    ///
+    /// Region of the STS endpoint used by credential loaders, independent of
+    /// the region requests are signed for. The regional endpoint is always
+    /// used if it's set, otherwise STS falls back to `region`.
+    pub sts_region: Option<String>,
     /// `sts_regional_endpoints` will be loaded from:
     ///
     /// - env value: [`AWS_STS_REGIONAL_ENDPOINTS`]
     /// - profile config: `sts_regional_endpoints`
     /// - default to `legacy`
     pub sts_regional_endpoints: String,
+    /// `use_fips_endpoint` will be loaded from:
+    ///
+    /// - env value: [`AWS_USE_FIPS_ENDPOINT`]
+    /// - profile config: `use_fips_endpoint`
+    /// - default to `false`
+    pub use_fips_endpoint: bool,
     /// `access_key_id` will be loaded from
     ///
     /// - this field if it's `is_some`
@@ -60,14 +79,34 @@ pub struct Config {
     ///
     /// - this field if it's `is_some`
     /// - env value: [`AWS_SESSION_TOKEN`]
-    /// - profile config: `aws_session_token`
+    /// - profile config: `aws_session_token`, or the legacy `aws_security_token`
     pub session_token: Option<String>,
+    /// `credential_expiration` will be loaded from
+    ///
+    /// - this field if it's `is_some`
+    /// - env value: [`AWS_CREDENTIAL_EXPIRATION`] in RFC3339 format
+    ///
+    /// Static credentials are treated as never expiring if it's not set.
+    pub credential_expiration: Option<DateTime>,
     /// `role_arn` value will be load from:
     ///
     /// - this field if it's `is_some`.
     /// - env value: [`AWS_ROLE_ARN`]
     /// - profile config: `role_arn`
     pub role_arn: Option<String>,
+    /// `source_profile` value will be load from:
+    ///
+    /// - this field if it's `is_some`.
+    /// - profile config: `source_profile`
+    pub source_profile: Option<String>,
+    /// `credential_source` value will be load from:
+    ///
+    /// - this field if it's `is_some`.
+    /// - profile config: `credential_source`
+    ///
+    /// Could be `Ec2InstanceMetadata`, `EcsContainer` or `Environment`, and
+    /// must not be set together with `source_profile`.
+    pub credential_source: Option<String>,
     /// `role_session_name` value will be load from:
     ///
     /// - env value: [`AWS_ROLE_SESSION_NAME`]
@@ -76,9 +115,11 @@ pub struct Config {
     pub role_session_name: String,
     /// `duration_seconds` value will be load from:
     ///
-    /// - this field if it's `is_some`.
     /// - profile config: `duration_seconds`
-    /// - default to `3600`.
+    /// - this field, default to `3600`.
+    ///
+    /// [`AssumeRoleLoader::with_duration_seconds`](crate::AssumeRoleLoader::with_duration_seconds)
+    /// overrides both of them.
     pub duration_seconds: Option<usize>,
     /// `external_id` value will be load from:
     ///
@@ -89,6 +130,12 @@ pub struct Config {
     ///
     /// - this field if it's `is_some`
     pub tags: Option<Vec<(String, String)>>,
+    /// `transitive_tag_keys` value will be loaded from:
+    ///
+    /// - this field if it's `is_some`
+    ///
+    /// Keys of `tags` that persist to subsequent sessions in a role chain.
+    pub transitive_tag_keys: Option<Vec<String>>,
     /// `web_identity_token_file` value will be loaded from:
     ///
     /// - this field if it's `is_some`
@@ -105,6 +152,58 @@ pub struct Config {
     /// - this field
     /// - env value: [`AWS_ENDPOINT_URL`]
     pub endpoint_url: Option<String>,
+    /// `endpoint_url_sts` value will be loaded from:
+    ///
+    /// - this field
+    /// - env value: [`AWS_ENDPOINT_URL_STS`]
+    ///
+    /// STS loaders will fall back to `endpoint_url` if it's not set.
+    pub endpoint_url_sts: Option<String>,
+    /// `sso_session` value will be loaded from:
+    ///
+    /// - this field
+    /// - profile config: `sso_session`
+    ///
+    /// The `[sso-session <name>]` section it refers to provides
+    /// `sso_start_url` and `sso_region`.
+    pub sso_session: Option<String>,
+    /// `sso_start_url` value will be loaded from:
+    ///
+    /// - this field
+    /// - profile config: `sso_start_url`
+    /// - sso-session config: `sso_start_url`
+    pub sso_start_url: Option<String>,
+    /// `sso_region` value will be loaded from:
+    ///
+    /// - this field
+    /// - profile config: `sso_region`
+    /// - sso-session config: `sso_region`
+    pub sso_region: Option<String>,
+    /// `sso_account_id` value will be loaded from:
+    ///
+    /// - this field
+    /// - profile config: `sso_account_id`
+    pub sso_account_id: Option<String>,
+    /// `sso_role_name` value will be loaded from:
+    ///
+    /// - this field
+    /// - profile config: `sso_role_name`
+    pub sso_role_name: Option<String>,
+    /// `max_attempts` value will be loaded from:
+    ///
+    /// - this field
+    /// - env value: [`AWS_MAX_ATTEMPTS`]
+    /// - profile config: `max_attempts`
+    ///
+    /// The number of attempts of requests to AWS STS and IMDS including the
+    /// first one, loaders retry 3 times (4 attempts) if it's not set.
+    pub max_attempts: Option<usize>,
+    /// Skip checking the format of `region` and `sts_region` when building
+    /// STS endpoints, disabled by default.
+    ///
+    /// Regions are expected to look like `us-east-1` or `us-gov-west-1`,
+    /// enable this for custom or private regions like `cn-test`.
+    pub skip_region_validation: bool,
 }
 
 impl Default for Config {
@@ -114,18 +213,32 @@ impl Default for Config {
             shared_credentials_file: "~/.aws/credentials".to_string(),
             profile: "default".to_string(),
             region: None,
+            sts_region: None,
             sts_regional_endpoints: "legacy".to_string(),
+            use_fips_endpoint: false,
             access_key_id: None,
             secret_access_key: None,
             session_token: None,
+            credential_expiration: None,
             role_arn: None,
+            source_profile: None,
+            credential_source: None,
             role_session_name: "reqsign".to_string(),
             duration_seconds: Some(3600),
             external_id: None,
             tags: None,
+            transitive_tag_keys: None,
             web_identity_token_file: None,
             ec2_metadata_disabled: false,
             endpoint_url: None,
+            endpoint_url_sts: None,
+            sso_session: None,
+            sso_start_url: None,
+            sso_region: None,
+            sso_account_id: None,
+            sso_role_name: None,
+            max_attempts: None,
+            skip_region_validation: false,
         }
     }
 }
@@ -137,26 +250,44 @@ impl fmt::Debug for Config {
             .field("shared_credentials_file", &self.shared_credentials_file)
             .field("profile", &self.profile)
             .field("region", &self.region)
+            .field("sts_region", &self.sts_region)
             .field("sts_regional_endpoints", &self.sts_regional_endpoints)
+            .field("use_fips_endpoint", &self.use_fips_endpoint)
             .field("access_key_id", &Redact::from(&self.access_key_id))
             .field("secret_access_key", &Redact::from(&self.secret_access_key))
             .field("session_token", &Redact::from(&self.session_token))
+            .field("credential_expiration", &self.credential_expiration)
             .field("role_arn", &self.role_arn)
+            .field("source_profile", &self.source_profile)
+            .field("credential_source", &self.credential_source)
             .field("role_session_name", &self.role_session_name)
             .field("duration_seconds", &self.duration_seconds)
             .field("external_id", &Redact::from(&self.external_id))
             .field("tags", &self.tags)
+            .field("transitive_tag_keys", &self.transitive_tag_keys)
             .field("web_identity_token_file", &self.web_identity_token_file)
             .field("ec2_metadata_disabled", &self.ec2_metadata_disabled)
             .field("endpoint_url", &self.endpoint_url)
+            .field("endpoint_url_sts", &self.endpoint_url_sts)
+            .field("sso_session", &self.sso_session)
+            .field("sso_start_url", &self.sso_start_url)
+            .field("sso_region", &self.sso_region)
+            .field("sso_account_id", &self.sso_account_id)
+            .field("sso_role_name", &self.sso_role_name)
+            .field("max_attempts", &self.max_attempts)
+            .field("skip_region_validation", &self.skip_region_validation)
             .finish()
     }
 }
 
 impl Config {
     /// Load config from env.
+    ///
+    /// Empty env vars are treated as unset, like `AWS_SESSION_TOKEN=` left
+    /// by scripts that clear it.
     pub fn from_env(mut self, ctx: &Context) -> Self {
-        let envs = ctx.env_vars();
+        let mut envs = ctx.env_vars();
+        envs.retain(|_, v| !v.is_empty());
 
         if let Some(v) = envs.get(AWS_CONFIG_FILE) {
             self.config_file = v.to_string();
@@ -167,12 +298,15 @@ impl Config {
         if let Some(v) = envs.get(AWS_PROFILE) {
             self.profile = v.to_string();
         }
-        if let Some(v) = envs.get(AWS_REGION) {
+        if let Some(v) = envs.get(AWS_REGION).or(envs.get(AWS_DEFAULT_REGION)) {
             self.region = Some(v.to_string())
         }
         if let Some(v) = envs.get(AWS_STS_REGIONAL_ENDPOINTS) {
             self.sts_regional_endpoints = v.to_string();
         }
+        if let Some(v) = envs.get(AWS_USE_FIPS_ENDPOINT) {
+            self.use_fips_endpoint = v.eq_ignore_ascii_case("true");
+        }
         if let Some(v) = envs.get(AWS_ACCESS_KEY_ID) {
             self.access_key_id = Some(v.to_string())
         }
@@ -182,6 +316,12 @@ impl Config {
         if let Some(v) = envs.get(AWS_SESSION_TOKEN) {
             self.session_token = Some(v.to_string())
         }
+        if let Some(v) = envs.get(AWS_CREDENTIAL_EXPIRATION) {
+            match parse_rfc3339(v) {
+                Ok(v) => self.credential_expiration = Some(v),
+                Err(err) => warn!("ignore invalid {AWS_CREDENTIAL_EXPIRATION} {v}: {err:?}"),
+            }
+        }
         if let Some(v) = envs.get(AWS_ROLE_ARN) {
             self.role_arn = Some(v.to_string())
         }
@@ -197,6 +337,15 @@ impl Config {
         if let Some(v) = envs.get(AWS_ENDPOINT_URL) {
             self.endpoint_url = Some(v.to_string());
         }
+        if let Some(v) = envs.get(AWS_ENDPOINT_URL_STS) {
+            self.endpoint_url_sts = Some(v.to_string());
+        }
+        if let Some(v) = envs.get(AWS_MAX_ATTEMPTS) {
+            match parse_max_attempts(v) {
+                Some(v) => self.max_attempts = Some(v),
+                None => warn!("ignore invalid {AWS_MAX_ATTEMPTS} {v}"),
+            }
+        }
         self
     }
 
@@ -204,8 +353,15 @@ impl Config {
     ///
     /// If the env var AWS_PROFILE is set, this profile will be used,
     /// otherwise the contents of `self.profile` will be used.
-    #[cfg(not(target_arch = "wasm32"))]
-    pub async fn from_profile(mut self, ctx: &Context) -> Self {
+    ///
+    /// The profile is read from the `[profile <name>]` section of the config
+    /// file and the `[<name>]` section of the shared credentials file, the
+    /// `default` profile uses `[default]` in both. Values from the shared
+    /// credentials file take precedence.
+    ///
+    /// Returns an error if a non-default profile is found in neither file.
+    #[cfg(all(feature = "load", not(target_arch = "wasm32")))]
+    pub async fn from_profile(mut self, ctx: &Context) -> Result<Self> {
         // self.profile is checked by the two load methods.
         if let Some(profile) = ctx.env_var(AWS_PROFILE) {
             self.profile = profile;
@@ -222,36 +378,56 @@ impl Config {
             self.shared_credentials_file = shared_credentials_file;
         }
 
-        // Ignore all errors happened internally.
-        let _ = self.load_via_profile_config_file(ctx).await.map_err(|err| {
-            debug!("load_via_profile_config_file failed: {err:?}");
-        });
+        // Errors happened internally are ignored unless the profile can't
+        // be found at all.
+        let in_config_file = self
+            .load_via_profile_config_file(ctx)
+            .await
+            .map_err(|err| debug!("load_via_profile_config_file failed: {err:?}"))
+            .is_ok();
 
-        let _ = self
+        let in_shared_credentials_file = self
             .load_via_profile_shared_credentials_file(ctx)
             .await
-            .map_err(|err| debug!("load_via_profile_shared_credentials_file failed: {err:?}"));
+            .map_err(|err| debug!("load_via_profile_shared_credentials_file failed: {err:?}"))
+            .is_ok();
+
+        if !in_config_file && !in_shared_credentials_file && self.profile != "default" {
+            return Err(Error::Config(format!(
+                "profile {} is not found in config file {} or shared credentials file {}",
+                self.profile, self.config_file, self.shared_credentials_file
+            ))
+            .into());
+        }
 
-        self
+        Ok(self)
+    }
+
+    /// Profiles are not available on wasm32, the config is returned as is.
+    #[cfg(all(feature = "load", target_arch = "wasm32"))]
+    pub async fn from_profile(self, _: &Context) -> anyhow::Result<Self> {
+        Ok(self)
     }
 
     /// Only the following fields will exist in shared_credentials_file:
     ///
     /// - `aws_access_key_id`
     /// - `aws_secret_access_key`
-    /// - `aws_session_token`
-    #[cfg(not(target_arch = "wasm32"))]
+    /// - `aws_session_token`, or the legacy `aws_security_token`
+    #[cfg(all(feature = "load", not(target_arch = "wasm32")))]
     async fn load_via_profile_shared_credentials_file(&mut self, ctx: &Context) -> Result<()> {
         let path = ctx
             .expand_home_dir(&self.shared_credentials_file)
             .ok_or_else(|| anyhow!("expand homedir failed"))?;
 
         let content = ctx.file_read(&path).await?;
-        let conf = Ini::load_from_str(&String::from_utf8_lossy(&content))?;
+        let conf = Ini::load_from_str(&String::from_utf8_lossy(&content))
+            .map_err(|e| Error::Parse(format!("invalid shared credentials file: {e}")))?;
 
-        let props = conf
+        let section = conf
             .section(Some(&self.profile))
-            .ok_or_else(|| anyhow!("section {} is not found", self.profile))?;
+            .ok_or_else(|| Error::Config(format!("section {} is not found", self.profile)))?;
+        let props = Props(section);
 
         if let Some(v) = props.get("aws_access_key_id") {
             self.access_key_id = Some(v.to_string())
@@ -259,21 +435,26 @@ impl Config {
         if let Some(v) = props.get("aws_secret_access_key") {
             self.secret_access_key = Some(v.to_string())
         }
-        if let Some(v) = props.get("aws_session_token") {
+        // `aws_security_token` is the legacy name still written by some tools.
+        if let Some(v) = props
+            .get("aws_session_token")
+            .or_else(|| props.get("aws_security_token"))
+        {
             self.session_token = Some(v.to_string())
         }
 
         Ok(())
     }
 
-    #[cfg(not(target_arch = "wasm32"))]
+    #[cfg(all(feature = "load", not(target_arch = "wasm32")))]
     async fn load_via_profile_config_file(&mut self, ctx: &Context) -> Result<()> {
         let path = ctx
             .expand_home_dir(&self.config_file)
             .ok_or_else(|| anyhow!("expand homedir failed"))?;
 
         let content = ctx.file_read(&path).await?;
-        let conf = Ini::load_from_str(&String::from_utf8_lossy(&content))?;
+        let conf = Ini::load_from_str(&String::from_utf8_lossy(&content))
+            .map_err(|e| Error::Parse(format!("invalid config file: {e}")))?;
 
         let section = match self.profile.as_str() {
             "default" => "default".to_string(),
@@ -281,31 +462,49 @@ impl Config {
         };
         let props = conf
             .section(Some(section))
-            .ok_or_else(|| anyhow!("section {} is not found", self.profile))?;
+            .ok_or_else(|| Error::Config(format!("section {} is not found", self.profile)))?;
+        let props = Props(props);
 
+        // Keep the region from env since it takes precedence over profile.
         if let Some(v) = props.get("region") {
-            self.region = Some(v.to_string())
+            self.region.get_or_insert_with(|| v.to_string());
         }
         if let Some(v) = props.get("sts_regional_endpoints") {
             self.sts_regional_endpoints = v.to_string();
         }
+        if let Some(v) = props.get("use_fips_endpoint") {
+            self.use_fips_endpoint = v.eq_ignore_ascii_case("true");
+        }
         if let Some(v) = props.get("aws_access_key_id") {
             self.access_key_id = Some(v.to_string())
         }
         if let Some(v) = props.get("aws_secret_access_key") {
             self.secret_access_key = Some(v.to_string())
         }
-        if let Some(v) = props.get("aws_session_token") {
+        // `aws_security_token` is the legacy name still written by some tools.
+        if let Some(v) = props
+            .get("aws_session_token")
+            .or_else(|| props.get("aws_security_token"))
+        {
             self.session_token = Some(v.to_string())
         }
         if let Some(v) = props.get("role_arn") {
             self.role_arn = Some(v.to_string())
         }
+        if let Some(v) = props.get("source_profile") {
+            self.source_profile = Some(v.to_string())
+        }
+        if let Some(v) = props.get("credential_source") {
+            self.credential_source = Some(v.to_string())
+        }
         if let Some(v) = props.get("role_session_name") {
             self.role_session_name = v.to_string()
         }
         if let Some(v) = props.get("duration_seconds") {
-            self.duration_seconds = Some(v.to_string().parse::<usize>().unwrap())
+            match v.parse::<usize>() {
+                Ok(v) => self.duration_seconds = Some(v),
+                Err(err) => warn!("ignore invalid duration_seconds {v} in profile: {err}"),
+            }
         }
         if let Some(v) = props.get("web_identity_token_file") {
             self.web_identity_token_file = Some(v.to_string())
@@ -313,12 +512,73 @@ impl Config {
         if let Some(v) = props.get("endpoint_url") {
             self.endpoint_url = Some(v.to_string())
         }
+        // Keep max_attempts from env since it takes precedence over profile.
+        if let Some(v) = props.get("max_attempts") {
+            match parse_max_attempts(v) {
+                Some(v) => {
+                    self.max_attempts.get_or_insert(v);
+                }
+                None => warn!("ignore invalid max_attempts {v} in profile"),
+            }
+        }
+        if let Some(v) = props.get("sso_session") {
+            self.sso_session = Some(v.to_string())
+        }
+        if let Some(v) = props.get("sso_start_url") {
+            self.sso_start_url = Some(v.to_string())
+        }
+        if let Some(v) = props.get("sso_region") {
+            self.sso_region = Some(v.to_string())
+        }
+        if let Some(v) = props.get("sso_account_id") {
+            self.sso_account_id = Some(v.to_string())
+        }
+        if let Some(v) = props.get("sso_role_name") {
+            self.sso_role_name = Some(v.to_string())
+        }
+
+        // Resolve the linked `[sso-session <name>]` section, the session
+        // values take precedence over the legacy ones in the profile.
+        if let Some(name) = &self.sso_session {
+            match conf.section(Some(format!("sso-session {name}"))).map(Props) {
+                Some(session) => {
+                    if let Some(v) = session.get("sso_start_url") {
+                        self.sso_start_url = Some(v.to_string())
+                    }
+                    if let Some(v) = session.get("sso_region") {
+                        self.sso_region = Some(v.to_string())
+                    }
+                }
+                None => warn!(
+                    "sso-session {name} referenced by profile {} is not found",
+                    self.profile
+                ),
+            }
+        }
 
         Ok(())
     }
 }
 
-#[cfg(test)]
+/// Properties of a profile section, empty values like `aws_session_token =`
+/// are treated as unset.
+#[cfg(all(feature = "load", not(target_arch = "wasm32")))]
+struct Props<'a>(&'a ini::Properties);
+
+#[cfg(all(feature = "load", not(target_arch = "wasm32")))]
+impl Props<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).filter(|v| !v.is_empty())
+    }
+}
+
+/// `max_attempts` must be a positive integer.
+fn parse_max_attempts(v: &str) -> Option<usize> {
+    v.trim().parse::<usize>().ok().filter(|v| *v > 0)
+}
+
+// Profiles are only loaded with the `load` feature, and never on wasm32.
+#[cfg(all(test, feature = "load", not(target_arch = "wasm32")))]
 mod tests {
     use super::*;
     use pretty_assertions::assert_eq;
@@ -331,7 +591,6 @@ mod tests {
     use tempfile::tempdir;
 
     #[tokio::test]
-    #[cfg(not(target_arch = "wasm32"))]
     async fn test_config_from_profile_shared_credentials() -> Result<()> {
         let _ = env_logger::builder().is_test(true).try_init();
 
@@ -361,7 +620,7 @@ mod tests {
             ]),
         });
 
-        let config = Config::default().from_profile(&context).await;
+        let config = Config::default().from_profile(&context).await?;
 
         assert_eq!(config.profile, "profile1".to_owned());
         assert_eq!(config.access_key_id, Some("PROFILE1ACCESSKEYID".to_owned()));
@@ -378,7 +637,6 @@ mod tests {
     }
 
     #[tokio::test]
-    #[cfg(not(target_arch = "wasm32"))]
     async fn test_config_from_profile_config() -> Result<()> {
         let _ = env_logger::builder().is_test(true).try_init();
 
@@ -409,7 +667,7 @@ mod tests {
             ]),
         });
 
-        let config = Config::default().from_profile(&context).await;
+        let config = Config::default().from_profile(&context).await?;
 
         assert_eq!(config.profile, "profile1".to_owned());
         assert_eq!(config.access_key_id, Some("PROFILE1ACCESSKEYID".to_owned()));
@@ -428,4 +686,277 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_config_from_profile_section_prefix() -> Result<()> {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let testdata = format!("{}/testdata", env!("CARGO_MANIFEST_DIR"));
+        let context = Context::new(TokioFileRead, ReqwestHttpSend::default());
+        let context = context.with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([
+                (AWS_PROFILE.to_string(), "dev".to_string()),
+                (
+                    AWS_CONFIG_FILE.to_string(),
+                    format!("{testdata}/profile_config"),
+                ),
+                (
+                    AWS_SHARED_CREDENTIALS_FILE.to_string(),
+                    format!("{testdata}/profile_credential"),
+                ),
+            ]),
+        });
+
+        let config = Config::default().from_profile(&context).await?;
+
+        assert_eq!(config.profile, "dev");
+        // `[profile dev]` from config file.
+        assert_eq!(config.region, Some("us-west-2".to_owned()));
+        // `[dev]` from shared credentials file.
+        assert_eq!(config.access_key_id, Some("DEVACCESSKEYID".to_owned()));
+        assert_eq!(
+            config.secret_access_key,
+            Some("DEVSECRETACCESSKEY".to_owned())
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_config_from_profile_legacy_security_token() -> Result<()> {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let testdata = format!("{}/testdata", env!("CARGO_MANIFEST_DIR"));
+        let context = |profile: &str| {
+            Context::new(TokioFileRead, ReqwestHttpSend::default()).with_env(StaticEnv {
+                home_dir: None,
+                envs: HashMap::from_iter([
+                    (AWS_PROFILE.to_string(), profile.to_string()),
+                    (AWS_CONFIG_FILE.to_string(), format!("{testdata}/not_exist")),
+                    (
+                        AWS_SHARED_CREDENTIALS_FILE.to_string(),
+                        format!("{testdata}/legacy_credential"),
+                    ),
+                ]),
+            })
+        };
+
+        let config = Config::default().from_profile(&context("default")).await?;
+        assert_eq!(config.access_key_id, Some("LEGACYACCESSKEYID".to_owned()));
+        assert_eq!(config.session_token, Some("LEGACYSECURITYTOKEN".to_owned()));
+
+        // `aws_session_token` is preferred when both are present.
+        let config = Config::default().from_profile(&context("both")).await?;
+        assert_eq!(config.session_token, Some("BOTHSESSIONTOKEN".to_owned()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_config_empty_values_are_unset() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let file_path = tmp_dir.path().join("credentials");
+        std::fs::write(
+            &file_path,
+            "[default]\naws_access_key_id = ACCESSKEYID\naws_secret_access_key = SECRETACCESSKEY\naws_session_token =\n",
+        )?;
+
+        let context = Context::new(TokioFileRead, ReqwestHttpSend::default()).with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([
+                (AWS_SESSION_TOKEN.to_string(), "".to_string()),
+                (AWS_REGION.to_string(), "".to_string()),
+            ]),
+        });
+        let config = Config::default().from_env(&context);
+        assert_eq!(config.session_token, None);
+        assert_eq!(config.region, None);
+
+        let context = context.with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([(
+                AWS_SHARED_CREDENTIALS_FILE.to_string(),
+                file_path.to_str().unwrap().to_owned(),
+            )]),
+        });
+        let config = Config::default().from_profile(&context).await?;
+        assert_eq!(config.access_key_id, Some("ACCESSKEYID".to_owned()));
+        assert_eq!(config.session_token, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_config_from_profile_not_found() -> Result<()> {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let testdata = format!("{}/testdata", env!("CARGO_MANIFEST_DIR"));
+        let context = Context::new(TokioFileRead, ReqwestHttpSend::default());
+        let context = context.with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([
+                (AWS_PROFILE.to_string(), "not_exist".to_string()),
+                (
+                    AWS_CONFIG_FILE.to_string(),
+                    format!("{testdata}/profile_config"),
+                ),
+                (
+                    AWS_SHARED_CREDENTIALS_FILE.to_string(),
+                    format!("{testdata}/profile_credential"),
+                ),
+            ]),
+        });
+
+        let err = Config::default()
+            .from_profile(&context)
+            .await
+            .expect_err("profile must not be found");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Config(_))
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_config_from_profile_sso_session() -> Result<()> {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let testdata = format!("{}/testdata", env!("CARGO_MANIFEST_DIR"));
+        let context = Context::new(TokioFileRead, ReqwestHttpSend::default());
+        let load = |profile: &str| {
+            let context = context.clone().with_env(StaticEnv {
+                home_dir: None,
+                envs: HashMap::from_iter([
+                    (AWS_PROFILE.to_string(), profile.to_string()),
+                    (
+                        AWS_CONFIG_FILE.to_string(),
+                        format!("{testdata}/sso_session_config"),
+                    ),
+                    (
+                        AWS_SHARED_CREDENTIALS_FILE.to_string(),
+                        format!("{testdata}/not_exist"),
+                    ),
+                ]),
+            });
+            async move { Config::default().from_profile(&context).await }
+        };
+
+        // Linked `[sso-session]` format written by `aws configure sso`.
+        let config = load("dev").await?;
+        assert_eq!(config.sso_session.as_deref(), Some("my-sso"));
+        assert_eq!(
+            config.sso_start_url.as_deref(),
+            Some("https://my-sso-portal.awsapps.com/start")
+        );
+        assert_eq!(config.sso_region.as_deref(), Some("us-east-1"));
+        assert_eq!(config.sso_account_id.as_deref(), Some("111122223333"));
+        assert_eq!(config.sso_role_name.as_deref(), Some("ReadOnly"));
+        assert_eq!(config.region.as_deref(), Some("us-west-2"));
+
+        // Legacy format with the session values inlined in the profile.
+        let config = load("legacy").await?;
+        assert_eq!(config.sso_session, None);
+        assert_eq!(
+            config.sso_start_url.as_deref(),
+            Some("https://legacy.awsapps.com/start")
+        );
+        assert_eq!(config.sso_region.as_deref(), Some("us-east-2"));
+
+        // A dangling reference leaves the session values unresolved.
+        let config = load("broken").await?;
+        assert_eq!(config.sso_session.as_deref(), Some("not-exist"));
+        assert_eq!(config.sso_start_url, None);
+        assert_eq!(config.sso_region, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_config_max_attempts() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let file_path = tmp_dir.path().join("config");
+        std::fs::write(&file_path, "[default]\nmax_attempts = 7\n")?;
+
+        let context = Context::new(TokioFileRead, ReqwestHttpSend::default());
+        let load = |max_attempts: Option<&str>| {
+            let mut envs = HashMap::from_iter([(
+                AWS_CONFIG_FILE.to_string(),
+                file_path.to_str().unwrap().to_owned(),
+            )]);
+            if let Some(v) = max_attempts {
+                envs.insert(AWS_MAX_ATTEMPTS.to_string(), v.to_string());
+            }
+            let context = context.clone().with_env(StaticEnv {
+                home_dir: None,
+                envs,
+            });
+            async move {
+                Config::default()
+                    .from_env(&context)
+                    .from_profile(&context)
+                    .await
+            }
+        };
+
+        assert_eq!(load(None).await?.max_attempts, Some(7));
+        // Env takes precedence over profile.
+        assert_eq!(load(Some("2")).await?.max_attempts, Some(2));
+        // Invalid values are ignored.
+        assert_eq!(load(Some("0")).await?.max_attempts, Some(7));
+        assert_eq!(load(Some("many")).await?.max_attempts, Some(7));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_config_region() -> Result<()> {
+        let tmp_dir = tempdir()?;
+        let file_path = tmp_dir.path().join("config");
+        std::fs::write(&file_path, "[default]\nregion = us-west-2\n")?;
+
+        let context = Context::new(TokioFileRead, ReqwestHttpSend::default());
+        let load = |region: Option<&str>, default_region: Option<&str>| {
+            let mut envs = HashMap::from_iter([(
+                AWS_CONFIG_FILE.to_string(),
+                file_path.to_str().unwrap().to_owned(),
+            )]);
+            if let Some(v) = region {
+                envs.insert(AWS_REGION.to_string(), v.to_string());
+            }
+            if let Some(v) = default_region {
+                envs.insert(AWS_DEFAULT_REGION.to_string(), v.to_string());
+            }
+            let context = context.clone().with_env(StaticEnv {
+                home_dir: None,
+                envs,
+            });
+            async move {
+                let config = Config::default()
+                    .from_env(&context)
+                    .from_profile(&context)
+                    .await?;
+                Ok::<_, anyhow::Error>(crate::ConfigLoader::new(config.into()).region())
+            }
+        };
+
+        assert_eq!(load(None, None).await?.as_deref(), Some("us-west-2"));
+        assert_eq!(
+            load(None, Some("eu-west-1")).await?.as_deref(),
+            Some("eu-west-1")
+        );
+        assert_eq!(
+            load(Some("ap-east-1"), None).await?.as_deref(),
+            Some("ap-east-1")
+        );
+        // `AWS_REGION` takes precedence over `AWS_DEFAULT_REGION`.
+        assert_eq!(
+            load(Some("ap-east-1"), Some("eu-west-1")).await?.as_deref(),
+            Some("ap-east-1")
+        );
+
+        Ok(())
+    }
 }
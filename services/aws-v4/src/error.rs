@@ -0,0 +1,211 @@
+use quick_xml::de;
+use reqsign_core::time::{parse_rfc3339, DateTime};
+use reqsign_core::Error;
+use serde::Deserialize;
+
+/// Kind of the error returned by AWS services for a signed request.
+///
+/// Middleware can use it to tell clock skew, which is fixed by resyncing the
+/// time and signing again via [`reqsign_core::Signer::sign_with_time`], apart
+/// from invalid credentials that should fail fast.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ServiceErrorKind {
+    /// The request time is too far from the server time.
+    ///
+    /// `server_time` is set if the service returns it, like
+    /// `RequestTimeTooSkewed` from S3.
+    ClockSkew { server_time: Option<DateTime> },
+    /// The credential is unknown, expired or doesn't match the signature.
+    InvalidCredential,
+    /// Other errors with their error code.
+    Other(String),
+}
+
+impl ServiceErrorKind {
+    /// Classify the XML error body returned by AWS services.
+    ///
+    /// Both the REST format `<Error><Code>..</Code></Error>` used by S3 and
+    /// the query format `<ErrorResponse><Error>..</Error></ErrorResponse>`
+    /// used by STS are supported. Returns `None` if the body is not an AWS
+    /// error.
+    pub fn from_xml(body: &str) -> Option<Self> {
+        let resp = ErrorBody::parse(body)?;
+        Some(Self::from_code(
+            &resp.code,
+            &resp.message,
+            parse_rfc3339(&resp.server_time).ok(),
+        ))
+    }
+
+    /// Classify the error code and message returned by AWS services.
+    fn from_code(code: &str, message: &str, server_time: Option<DateTime>) -> Self {
+        match code {
+            "RequestTimeTooSkewed" | "RequestExpired" | "RequestInTheFuture" => {
+                Self::ClockSkew { server_time }
+            }
+            // STS reports skewed requests as `SignatureDoesNotMatch` with
+            // messages like `Signature expired: ... is now earlier than ...`.
+            "SignatureDoesNotMatch" if message.contains("Signature expired") => {
+                Self::ClockSkew { server_time: None }
+            }
+            "SignatureDoesNotMatch"
+            | "InvalidAccessKeyId"
+            | "InvalidClientTokenId"
+            | "InvalidToken"
+            | "ExpiredToken"
+            | "ExpiredTokenException"
+            | "UnrecognizedClientException" => Self::InvalidCredential,
+            code => Self::Other(code.to_string()),
+        }
+    }
+
+    /// Classify the error returned by loaders in this crate, like STS
+    /// failures reported as [`Error::Service`].
+    ///
+    /// Returns `None` for other errors or if the service didn't return an
+    /// error code.
+    pub fn from_error(err: &anyhow::Error) -> Option<Self> {
+        let Some(Error::Service {
+            code: Some(code),
+            message,
+            ..
+        }) = err.downcast_ref::<Error>()
+        else {
+            return None;
+        };
+        Some(Self::from_code(code, message, None))
+    }
+}
+
+/// Error body returned by AWS services, in REST or query format.
+#[derive(Default, Debug, Deserialize)]
+#[serde(default, rename_all = "PascalCase")]
+pub(crate) struct ErrorBody {
+    pub(crate) code: String,
+    pub(crate) message: String,
+    pub(crate) server_time: String,
+    pub(crate) request_id: String,
+    error: Option<Box<ErrorBody>>,
+}
+
+impl ErrorBody {
+    /// Parse the error body, returns `None` if it's not an AWS error.
+    pub(crate) fn parse(body: &str) -> Option<Self> {
+        let mut resp: ErrorBody = de::from_str(body.trim()).ok()?;
+        if let Some(mut inner) = resp.error.take() {
+            // The query format puts `RequestId` next to `Error`.
+            if inner.request_id.is_empty() {
+                inner.request_id = resp.request_id;
+            }
+            resp = *inner;
+        }
+        (!resp.code.is_empty()).then_some(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_service_error_kind_from_xml() -> anyhow::Result<()> {
+        let cases = vec![
+            (
+                "s3 skewed",
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<Error>
+  <Code>RequestTimeTooSkewed</Code>
+  <Message>The difference between the request time and the current time is too large.</Message>
+  <RequestTime>20240101T000000Z</RequestTime>
+  <ServerTime>2024-01-01T00:20:00Z</ServerTime>
+  <MaxAllowedSkewMilliseconds>900000</MaxAllowedSkewMilliseconds>
+  <RequestId>4442587FB7D0A2F9</RequestId>
+</Error>"#,
+                Some(ServiceErrorKind::ClockSkew {
+                    server_time: Some(parse_rfc3339("2024-01-01T00:20:00Z")?),
+                }),
+            ),
+            (
+                "sts signature expired",
+                r#"<ErrorResponse xmlns="https://sts.amazonaws.com/doc/2011-06-15/">
+  <Error>
+    <Type>Sender</Type>
+    <Code>SignatureDoesNotMatch</Code>
+    <Message>Signature expired: 20240101T000000Z is now earlier than 20240101T001500Z (20240101T002000Z - 5 min.)</Message>
+  </Error>
+  <RequestId>c6104cbe-af31-11e0-8154-cbc7ccf896c7</RequestId>
+</ErrorResponse>"#,
+                Some(ServiceErrorKind::ClockSkew { server_time: None }),
+            ),
+            (
+                "s3 signature mismatch",
+                r#"<Error>
+  <Code>SignatureDoesNotMatch</Code>
+  <Message>The request signature we calculated does not match the signature you provided. Check your key and signing method.</Message>
+</Error>"#,
+                Some(ServiceErrorKind::InvalidCredential),
+            ),
+            (
+                "sts invalid token",
+                r#"<ErrorResponse xmlns="https://sts.amazonaws.com/doc/2011-06-15/">
+  <Error>
+    <Type>Sender</Type>
+    <Code>InvalidClientTokenId</Code>
+    <Message>The security token included in the request is invalid.</Message>
+  </Error>
+</ErrorResponse>"#,
+                Some(ServiceErrorKind::InvalidCredential),
+            ),
+            (
+                "other",
+                r#"<Error><Code>NoSuchKey</Code><Message>The specified key does not exist.</Message></Error>"#,
+                Some(ServiceErrorKind::Other("NoSuchKey".to_string())),
+            ),
+            ("not xml", "Service Unavailable", None),
+        ];
+
+        for (name, body, expected) in cases {
+            assert_eq!(ServiceErrorKind::from_xml(body), expected, "case {name}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_service_error_kind_from_error() {
+        let service_error = |code: Option<&str>, message: &str| -> anyhow::Error {
+            Error::Service {
+                status: http::StatusCode::FORBIDDEN,
+                code: code.map(String::from),
+                message: message.to_string(),
+                request_id: None,
+            }
+            .into()
+        };
+
+        let err = service_error(
+            Some("ExpiredToken"),
+            "The security token included in the request is expired",
+        );
+        assert_eq!(
+            ServiceErrorKind::from_error(&err),
+            Some(ServiceErrorKind::InvalidCredential)
+        );
+
+        // Brackets in messages don't confuse the classification.
+        let err = service_error(
+            Some("AccessDenied"),
+            "User [arn:aws:iam::123456789012:user/demo] is not authorized",
+        );
+        assert_eq!(
+            ServiceErrorKind::from_error(&err),
+            Some(ServiceErrorKind::Other("AccessDenied".to_string()))
+        );
+
+        let err = service_error(None, "[ExpiredToken] Forbidden");
+        assert_eq!(ServiceErrorKind::from_error(&err), None);
+
+        let err: anyhow::Error = Error::Timeout("operation timed out".to_string()).into();
+        assert_eq!(ServiceErrorKind::from_error(&err), None);
+    }
+}
@@ -1,4 +1,26 @@
 //! AWS service signer
+//!
+//! Credential loaders are enabled by the default `load` feature. With
+//! `default-features = false`, only the I/O free signing [`Builder`] and the
+//! primitives in [`sign`] are available.
+//!
+//! ## WASM
+//!
+//! Loaders send requests via [`reqsign_core::Context`], so they run in
+//! browsers with a `fetch` based `HttpSend` like the one documented on
+//! [`reqsign_core::Context`]. Requests are not retried on wasm32.
+//!
+//! - `ConfigLoader` works with credentials from the config or env, profile
+//!   files are not available.
+//! - `AssumeRoleLoader`, `AssumeRoleWithWebIdentityLoader`,
+//!   `AssumeRoleWithSAMLLoader` and `GetSessionTokenLoader` work if STS is
+//!   reached via a CORS enabled reverse proxy set in `endpoint_url_sts`.
+//!   Use `with_token_loader` to pass the web identity token.
+//! - `VaultLoader` works if the Vault server allows CORS.
+//! - `IMDSv2Loader` and `ECSLoader` don't work since the metadata endpoints
+//!   are not reachable from browsers, use `DefaultLoader::with_sources` to
+//!   skip them.
+//! - `FileCredentialCache` doesn't work, use `MemoryCredentialCache`.
 
 mod constants;
 
@@ -8,8 +30,26 @@ mod key;
 pub use key::Credential;
 mod build;
 pub use build::Builder;
+pub use build::SignedHeaders;
+pub use build::SigningScope;
+pub use build::UnsignedPayload;
+mod error;
+pub use error::ServiceErrorKind;
+mod chunk;
+pub use chunk::ChunkSigner;
+mod post_policy;
+pub use post_policy::PostPolicy;
+#[cfg(feature = "sigv4a")]
+mod v4a;
+#[cfg(feature = "sigv4a")]
+pub use v4a::V4aBuilder;
+#[cfg(feature = "load")]
 mod load;
+#[cfg(feature = "load")]
 pub use load::*;
+mod partition;
+pub use partition::Partition;
+pub mod sign;
 
 pub const EMPTY_STRING_SHA256: &str =
     "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
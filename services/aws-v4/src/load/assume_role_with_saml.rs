@@ -0,0 +1,269 @@
+use crate::constants::AWS_QUERY_ENCODE_SET;
+use crate::load::utils::{http_send, sts_endpoint, sts_error, RetryPolicy};
+use crate::{Config, Credential};
+use async_trait::async_trait;
+use bytes::Bytes;
+use percent_encoding::utf8_percent_encode;
+use quick_xml::de;
+use reqsign_core::time::parse_rfc3339;
+use reqsign_core::{BearerToken, Context, Error, HttpTimeout, Load};
+use serde::Deserialize;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// AssumeRoleWithSAMLLoader will load credential via STS
+/// `AssumeRoleWithSAML`.
+///
+/// This is used by enterprises federating via SAML identity providers like
+/// ADFS or Okta. The base64 encoded SAML assertion is loaded from the given
+/// source on every request, since assertions are short-lived.
+///
+/// - [AssumeRoleWithSAML](https://docs.aws.amazon.com/STS/latest/APIReference/API_AssumeRoleWithSAML.html)
+#[derive(Debug)]
+pub struct AssumeRoleWithSAMLLoader {
+    config: Arc<Config>,
+
+    principal_arn: String,
+    assertion_loader: Arc<dyn Load<Key = BearerToken>>,
+    retry: RetryPolicy,
+    timeout: HttpTimeout,
+}
+
+impl AssumeRoleWithSAMLLoader {
+    /// Create a new `AssumeRoleWithSAMLLoader` instance.
+    ///
+    /// `principal_arn` is the ARN of the SAML provider in IAM, and
+    /// `assertion_loader` returns the base64 encoded SAML assertion, for
+    /// example, [`reqsign_core::TokenFileLoader`] for an assertion file
+    /// written by a login helper. The role to assume is `role_arn` in config.
+    pub fn new(
+        config: Arc<Config>,
+        principal_arn: &str,
+        assertion_loader: impl Load<Key = BearerToken>,
+    ) -> Self {
+        Self {
+            retry: RetryPolicy::from_config(&config),
+            config,
+            principal_arn: principal_arn.to_string(),
+            assertion_loader: Arc::new(assertion_loader),
+            timeout: HttpTimeout::default(),
+        }
+    }
+
+    /// Set the region of the AWS STS endpoint that `AssumeRoleWithSAML` is
+    /// sent to.
+    ///
+    /// The request is authenticated by the SAML assertion instead of a
+    /// signature, so only the endpoint changes, for example, to
+    /// `sts.eu-west-1.amazonaws.com` if the global endpoint is not reachable.
+    pub fn with_sts_region(mut self, region: &str) -> Self {
+        Arc::make_mut(&mut self.config).sts_region = Some(region.to_string());
+        self
+    }
+
+    /// Set the retry policy for `AssumeRoleWithSAML` requests.
+    ///
+    /// Transport failures, 5xx responses and throttling are retried 3 times
+    /// with jitter by default, set `max_times` to `0` to disable retries.
+    /// Every attempt loads the SAML assertion again from the assertion
+    /// loader.
+    ///
+    /// This overrides `max_attempts` from config.
+    pub fn with_retry(mut self, max_times: usize, jitter: bool) -> Self {
+        self.retry = RetryPolicy::new(max_times, jitter);
+        self
+    }
+
+    /// Set the connect and read timeouts for `AssumeRoleWithSAML` requests.
+    ///
+    /// No timeouts are set by default.
+    pub fn with_timeout(mut self, connect: Duration, read: Duration) -> Self {
+        self.timeout = HttpTimeout::new(connect, read);
+        self
+    }
+}
+
+#[async_trait]
+impl Load for AssumeRoleWithSAMLLoader {
+    type Key = Credential;
+
+    async fn load(&self, ctx: &Context) -> anyhow::Result<Option<Self::Key>> {
+        let Some(role_arn) = &self.config.role_arn else {
+            return Ok(None);
+        };
+
+        let endpoint = sts_endpoint(&self.config)?;
+
+        let content = self
+            .retry
+            .run(|| async {
+                let assertion = self
+                    .assertion_loader
+                    .load(ctx)
+                    .await?
+                    .ok_or_else(|| Error::Config("SAML assertion is empty".to_string()))?;
+
+                // SAML assertions are too large for the query string, send
+                // them in the form body instead.
+                let mut body = format!(
+                    "Action=AssumeRoleWithSAML&Version=2011-06-15&RoleArn={}&PrincipalArn={}&SAMLAssertion={}",
+                    utf8_percent_encode(role_arn, &AWS_QUERY_ENCODE_SET),
+                    utf8_percent_encode(&self.principal_arn, &AWS_QUERY_ENCODE_SET),
+                    utf8_percent_encode(&assertion.token, &AWS_QUERY_ENCODE_SET),
+                );
+                if let Some(duration_seconds) = &self.config.duration_seconds {
+                    write!(body, "&DurationSeconds={duration_seconds}")?;
+                }
+
+                // Construct request to AWS STS Service.
+                let req = http::request::Request::builder()
+                    .extension(self.timeout)
+                    .method("POST")
+                    .uri(format!("{endpoint}/"))
+                    .header(
+                        http::header::CONTENT_TYPE.as_str(),
+                        "application/x-www-form-urlencoded",
+                    )
+                    .body(Bytes::from(body))?;
+
+                let resp = http_send(ctx, req).await?;
+                if resp.status() != http::StatusCode::OK {
+                    return Err(sts_error(resp.status(), resp.body()).into());
+                }
+                Ok(resp.into_body())
+            })
+            .await?;
+
+        let resp: AssumeRoleWithSAMLResponse = de::from_str(&content)
+            .map_err(|e| Error::Parse(format!("invalid AssumeRoleWithSAML response: {e}")))?;
+        let resp_cred = resp.result.credentials;
+
+        let cred = Credential {
+            access_key_id: resp_cred.access_key_id,
+            secret_access_key: resp_cred.secret_access_key,
+            session_token: Some(resp_cred.session_token),
+            expires_in: Some(parse_rfc3339(&resp_cred.expiration)?),
+        };
+
+        Ok(Some(cred))
+    }
+}
+
+#[derive(Default, Debug, Deserialize)]
+#[serde(default, rename_all = "PascalCase")]
+struct AssumeRoleWithSAMLResponse {
+    #[serde(rename = "AssumeRoleWithSAMLResult")]
+    result: AssumeRoleWithSAMLResult,
+}
+
+#[derive(Default, Debug, Deserialize)]
+#[serde(default, rename_all = "PascalCase")]
+struct AssumeRoleWithSAMLResult {
+    credentials: AssumeRoleWithSAMLCredentials,
+}
+
+#[derive(Default, Debug, Deserialize)]
+#[serde(default, rename_all = "PascalCase")]
+struct AssumeRoleWithSAMLCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: String,
+    expiration: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use http::{Method, StatusCode};
+    use reqsign_core::MockContext;
+
+    const ASSUME_ROLE_WITH_SAML_RESPONSE: &str = r#"<AssumeRoleWithSAMLResponse xmlns="https://sts.amazonaws.com/doc/2011-06-15/">
+  <AssumeRoleWithSAMLResult>
+    <Issuer>https://integ.example.com/idp/shibboleth</Issuer>
+    <AssumedRoleUser>
+      <Arn>arn:aws:sts::123456789012:assumed-role/TestSaml</Arn>
+      <AssumedRoleId>ARO456EXAMPLE789:TestSaml</AssumedRoleId>
+    </AssumedRoleUser>
+    <Credentials>
+      <AccessKeyId>ASIAV3ZUEFP6EXAMPLE</AccessKeyId>
+      <SecretAccessKey>8P+SQvWIuLnKhh8d++jpw0nNmQRBZvNEXAMPLEKEY</SecretAccessKey>
+      <SessionToken>IQoJb3JpZ2luX2VjEOz////////////////////wEXAMPLEtMSJHMEUCIDoKK3JH9uG</SessionToken>
+      <Expiration>2019-11-01T20:26:47Z</Expiration>
+    </Credentials>
+    <Audience>https://signin.aws.amazon.com/saml</Audience>
+    <SubjectType>transient</SubjectType>
+    <PackedPolicySize>6</PackedPolicySize>
+    <NameQualifier>SbdGOnUkh1i4+EXAMPLExL/jEvs=</NameQualifier>
+    <Subject>SamlExample</Subject>
+  </AssumeRoleWithSAMLResult>
+  <ResponseMetadata>
+    <RequestId>c6104cbe-af31-11e0-8154-cbc7ccf896c7</RequestId>
+  </ResponseMetadata>
+</AssumeRoleWithSAMLResponse>"#;
+
+    #[tokio::test]
+    async fn test_assume_role_with_saml() -> Result<()> {
+        let assertion_file = "/tmp/assertion";
+        let mock = MockContext::new()
+            .with_response(
+                Method::POST,
+                "https://sts.amazonaws.com/",
+                StatusCode::OK,
+                ASSUME_ROLE_WITH_SAML_RESPONSE,
+            )
+            .with_file(assertion_file, "PHNhbWxwOlJlc3BvbnNlPg+/=\n");
+        let ctx = mock.context();
+        let config = Arc::new(Config {
+            role_arn: Some("arn:aws:iam::123456789012:role/TestSaml".to_string()),
+            duration_seconds: Some(1800),
+            ..Default::default()
+        });
+        let loader = AssumeRoleWithSAMLLoader::new(
+            config,
+            "arn:aws:iam::123456789012:saml-provider/SAML-test",
+            reqsign_core::TokenFileLoader::new(assertion_file),
+        );
+
+        let cred = loader.load(&ctx).await?.expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "ASIAV3ZUEFP6EXAMPLE");
+        assert_eq!(
+            cred.secret_access_key,
+            "8P+SQvWIuLnKhh8d++jpw0nNmQRBZvNEXAMPLEKEY"
+        );
+        assert_eq!(
+            cred.session_token.as_deref(),
+            Some("IQoJb3JpZ2luX2VjEOz////////////////////wEXAMPLEtMSJHMEUCIDoKK3JH9uG")
+        );
+        assert_eq!(
+            cred.expires_in,
+            Some(parse_rfc3339("2019-11-01T20:26:47Z")?)
+        );
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            String::from_utf8(requests[0].body().to_vec())?,
+            "Action=AssumeRoleWithSAML&Version=2011-06-15\
+             &RoleArn=arn%3Aaws%3Aiam%3A%3A123456789012%3Arole%2FTestSaml\
+             &PrincipalArn=arn%3Aaws%3Aiam%3A%3A123456789012%3Asaml-provider%2FSAML-test\
+             &SAMLAssertion=PHNhbWxwOlJlc3BvbnNlPg%2B%2F%3D\
+             &DurationSeconds=1800"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_assume_role_with_saml_without_role_arn() -> Result<()> {
+        let ctx = MockContext::new().context();
+        let loader = AssumeRoleWithSAMLLoader::new(
+            Arc::new(Config::default()),
+            "arn:aws:iam::123456789012:saml-provider/SAML-test",
+            reqsign_core::TokenFileLoader::new("/not/exist"),
+        );
+
+        assert!(loader.load(&ctx).await?.is_none());
+        Ok(())
+    }
+}
@@ -1,24 +1,97 @@
-use crate::load::utils::sts_endpoint;
+use crate::constants::AWS_QUERY_ENCODE_SET;
+use crate::load::cache::{cache_key, CredentialCache, FileCredentialCache, MemoryCredentialCache};
+use crate::load::utils::{http_send, sts_endpoint, sts_error, RetryPolicy};
 use crate::{Config, Credential};
-use anyhow::anyhow;
 use async_trait::async_trait;
 use bytes::Bytes;
+use percent_encoding::utf8_percent_encode;
 use quick_xml::de;
 use reqsign_core::time::parse_rfc3339;
-use reqsign_core::{Context, Load};
+use reqsign_core::{BearerToken, Context, Error, HttpTimeout, Load, TokenFileLoader};
 use serde::Deserialize;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// AssumeRoleLoader will load credential via assume role.
 #[derive(Debug)]
 pub struct AssumeRoleWithWebIdentityLoader {
     config: Arc<Config>,
+    retry: RetryPolicy,
+    timeout: HttpTimeout,
+    cache: Arc<dyn CredentialCache>,
+    token_loader: Option<Arc<dyn Load<Key = BearerToken>>>,
 }
 
 impl AssumeRoleWithWebIdentityLoader {
     /// Create a new `AssumeRoleWithWebIdentityLoader` instance.
     pub fn new(cfg: Arc<Config>) -> Self {
-        Self { config: cfg }
+        Self {
+            retry: RetryPolicy::from_config(&cfg),
+            config: cfg,
+            timeout: HttpTimeout::default(),
+            cache: Arc::new(MemoryCredentialCache::default()),
+            token_loader: None,
+        }
+    }
+
+    /// Load the web identity token from the given loader instead of
+    /// `web_identity_token_file` in config.
+    ///
+    /// For example, [`reqsign_core::TokenFileLoader`] for a projected service
+    /// account token at a custom path.
+    pub fn with_token_loader(mut self, loader: impl Load<Key = BearerToken>) -> Self {
+        self.token_loader = Some(Arc::new(loader));
+        self
+    }
+
+    /// Set the region of the AWS STS endpoint that
+    /// `AssumeRoleWithWebIdentity` is sent to.
+    ///
+    /// The request is authenticated by the web identity token instead of a
+    /// signature, so only the endpoint changes, for example, to
+    /// `sts.us-west-2.amazonaws.com` for an EKS cluster in `us-west-2`.
+    pub fn with_sts_region(mut self, region: &str) -> Self {
+        Arc::make_mut(&mut self.config).sts_region = Some(region.to_string());
+        self
+    }
+
+    /// Set the retry policy for `AssumeRoleWithWebIdentity` requests.
+    ///
+    /// Transport failures, 5xx responses and throttling are retried 3 times
+    /// with jitter by default, set `max_times` to `0` to disable retries.
+    /// Every attempt reads the token again since it could be rotated in the
+    /// meantime.
+    ///
+    /// This overrides `max_attempts` from config.
+    pub fn with_retry(mut self, max_times: usize, jitter: bool) -> Self {
+        self.retry = RetryPolicy::new(max_times, jitter);
+        self
+    }
+
+    /// Set the connect and read timeouts for `AssumeRoleWithWebIdentity`
+    /// requests.
+    ///
+    /// No timeouts are set by default.
+    pub fn with_timeout(mut self, connect: Duration, read: Duration) -> Self {
+        self.timeout = HttpTimeout::new(connect, read);
+        self
+    }
+
+    /// Cache assumed credentials in the given directory, like
+    /// `~/.aws/cli/cache` used by the AWS CLI.
+    ///
+    /// Cached credentials are keyed by role arn and session name, and reused
+    /// across processes until they expire.
+    pub fn with_credential_cache(self, path: impl Into<PathBuf>) -> Self {
+        self.with_cache(FileCredentialCache::new(path))
+    }
+
+    /// Cache assumed credentials in the given cache, credentials are cached
+    /// in memory by default.
+    pub fn with_cache(mut self, cache: impl CredentialCache) -> Self {
+        self.cache = Arc::new(cache);
+        self
     }
 }
 
@@ -27,35 +100,65 @@ impl Load for AssumeRoleWithWebIdentityLoader {
     type Key = Credential;
 
     async fn load(&self, ctx: &Context) -> anyhow::Result<Option<Self::Key>> {
-        let (token_file, role_arn) =
-            match (&self.config.web_identity_token_file, &self.config.role_arn) {
-                (Some(token_file), Some(role_arn)) => (token_file, role_arn),
-                _ => return Ok(None),
-            };
+        let Some(role_arn) = &self.config.role_arn else {
+            return Ok(None);
+        };
+        let token_file = &self.config.web_identity_token_file;
+        if self.token_loader.is_none() && token_file.is_none() {
+            return Ok(None);
+        }
 
-        let token = ctx.file_read_as_string(token_file).await?;
-        let role_session_name = &self.config.role_session_name;
+        let role_arn = utf8_percent_encode(role_arn, &AWS_QUERY_ENCODE_SET);
+        let role_session_name =
+            utf8_percent_encode(&self.config.role_session_name, &AWS_QUERY_ENCODE_SET);
+        // The token is left out since it's a secret and rotated.
+        let cache_key = cache_key(&format!(
+            "Action=AssumeRoleWithWebIdentity&RoleArn={role_arn}&Version=2011-06-15&RoleSessionName={role_session_name}"
+        ));
+        if let Some(cred) = self.cache.get(ctx, &cache_key).await {
+            return Ok(Some(cred));
+        }
 
         let endpoint = sts_endpoint(&self.config)?;
 
-        // Construct request to AWS STS Service.
-        let url = format!("https://{endpoint}/?Action=AssumeRoleWithWebIdentity&RoleArn={role_arn}&WebIdentityToken={token}&Version=2011-06-15&RoleSessionName={role_session_name}");
-        let req = http::request::Request::builder()
-            .method("GET")
-            .uri(url)
-            .header(
-                http::header::CONTENT_TYPE.as_str(),
-                "application/x-www-form-urlencoded",
-            )
-            .body(Bytes::new())?;
-
-        let resp = ctx.http_send_as_string(req).await?;
-        if resp.status() != http::StatusCode::OK {
-            let content = resp.into_body();
-            return Err(anyhow!("request to AWS STS Services failed: {content}"));
-        }
+        let content = self
+            .retry
+            .run(|| async {
+                // Read the token file on every attempt since the token could be
+                // rotated, for example, projected service account tokens in EKS.
+                let token = match (&self.token_loader, token_file) {
+                    (Some(loader), _) => loader.load(ctx).await?,
+                    (None, Some(token_file)) => TokenFileLoader::new(token_file).load(ctx).await?,
+                    (None, None) => unreachable!("token source must be checked before"),
+                }
+                .ok_or_else(|| Error::Config("web identity token is empty".to_string()))?;
+
+                // Construct request to AWS STS Service.
+                let url = format!(
+                    "{endpoint}/?Action=AssumeRoleWithWebIdentity&RoleArn={role_arn}&WebIdentityToken={}&Version=2011-06-15&RoleSessionName={role_session_name}",
+                    utf8_percent_encode(&token.token, &AWS_QUERY_ENCODE_SET),
+                );
+                let req = http::request::Request::builder()
+                    .extension(self.timeout)
+                    .method("GET")
+                    .uri(&url)
+                    .header(
+                        http::header::CONTENT_TYPE.as_str(),
+                        "application/x-www-form-urlencoded",
+                    )
+                    .body(Bytes::new())?;
 
-        let resp: AssumeRoleWithWebIdentityResponse = de::from_str(&resp.into_body())?;
+                let resp = http_send(ctx, req).await?;
+                if resp.status() != http::StatusCode::OK {
+                    return Err(sts_error(resp.status(), resp.body()).into());
+                }
+                Ok(resp.into_body())
+            })
+            .await?;
+
+        let resp: AssumeRoleWithWebIdentityResponse = de::from_str(&content).map_err(|e| {
+            Error::Parse(format!("invalid AssumeRoleWithWebIdentity response: {e}"))
+        })?;
         let resp_cred = resp.result.credentials;
 
         let cred = Credential {
@@ -64,6 +167,7 @@ impl Load for AssumeRoleWithWebIdentityLoader {
             session_token: Some(resp_cred.session_token),
             expires_in: Some(parse_rfc3339(&resp_cred.expiration)?),
         };
+        self.cache.set(ctx, &cache_key, &cred).await;
 
         Ok(Some(cred))
     }
@@ -95,6 +199,146 @@ struct AssumeRoleWithWebIdentityCredentials {
 mod tests {
     use super::*;
     use anyhow::Result;
+    use http::{Method, StatusCode};
+    use reqsign_core::MockContext;
+
+    const ASSUME_ROLE_WITH_WEB_IDENTITY_RESPONSE: &str = r#"<AssumeRoleWithWebIdentityResponse xmlns="https://sts.amazonaws.com/doc/2011-06-15/">
+  <AssumeRoleWithWebIdentityResult>
+    <Credentials>
+      <AccessKeyId>access_key_id</AccessKeyId>
+      <SecretAccessKey>secret_access_key</SecretAccessKey>
+      <SessionToken>session_token</SessionToken>
+      <Expiration>2022-05-25T11:45:17Z</Expiration>
+    </Credentials>
+  </AssumeRoleWithWebIdentityResult>
+</AssumeRoleWithWebIdentityResponse>"#;
+
+    /// Mock AWS STS to return the canned response for the given encoded
+    /// token and session name.
+    fn mock_sts(mock: MockContext, token: &str, role_session_name: &str) -> MockContext {
+        mock.with_response(
+            Method::GET,
+            &format!("https://sts.amazonaws.com/?Action=AssumeRoleWithWebIdentity&RoleArn=arn%3Aaws%3Aiam%3A%3A123456789012%3Arole%2Fdemo&WebIdentityToken={token}&Version=2011-06-15&RoleSessionName={role_session_name}"),
+            StatusCode::OK,
+            ASSUME_ROLE_WITH_WEB_IDENTITY_RESPONSE,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_assume_role_with_web_identity_reread_token() -> Result<()> {
+        let token_file = "/var/run/secrets/token";
+        let mock =
+            mock_sts(MockContext::new(), "token_v1", "reqsign").with_file(token_file, "token_v1\n");
+        let mock = mock_sts(mock, "token_v2", "reqsign");
+        let ctx = mock.context();
+        let config = Arc::new(Config {
+            role_arn: Some("arn:aws:iam::123456789012:role/demo".to_string()),
+            web_identity_token_file: Some(token_file.to_string()),
+            ..Default::default()
+        });
+        let loader = AssumeRoleWithWebIdentityLoader::new(config);
+
+        loader.load(&ctx).await?.expect("credential must be loaded");
+        // The token has been rotated.
+        ctx.file_write(token_file, b"token_v2").await?;
+        loader.load(&ctx).await?.expect("credential must be loaded");
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0]
+            .uri()
+            .to_string()
+            .contains("WebIdentityToken=token_v1&"));
+        assert!(requests[1]
+            .uri()
+            .to_string()
+            .contains("WebIdentityToken=token_v2&"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_assume_role_with_web_identity_encode_query() -> Result<()> {
+        let token_file = "/var/run/secrets/token";
+        // The mocked url only matches the trimmed and encoded values.
+        let mock = mock_sts(MockContext::new(), "a%2Bb%2Fc%3D", "session%20name")
+            .with_file(token_file, "a+b/c=\n");
+        let ctx = mock.context();
+        let config = Arc::new(Config {
+            role_arn: Some("arn:aws:iam::123456789012:role/demo".to_string()),
+            role_session_name: "session name".to_string(),
+            web_identity_token_file: Some(token_file.to_string()),
+            ..Default::default()
+        });
+        let loader = AssumeRoleWithWebIdentityLoader::new(config);
+
+        loader.load(&ctx).await?.expect("credential must be loaded");
+        assert_eq!(mock.requests().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_assume_role_with_web_identity_token_loader() -> Result<()> {
+        let token_file = "/var/run/secrets/projected";
+        let mock = mock_sts(MockContext::new(), "projected_token", "reqsign")
+            .with_file(token_file, "projected_token\n");
+        let ctx = mock.context();
+        let config = Arc::new(Config {
+            role_arn: Some("arn:aws:iam::123456789012:role/demo".to_string()),
+            ..Default::default()
+        });
+        let loader = AssumeRoleWithWebIdentityLoader::new(config)
+            .with_token_loader(reqsign_core::TokenFileLoader::new(token_file));
+
+        loader.load(&ctx).await?.expect("credential must be loaded");
+        assert_eq!(mock.requests().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_assume_role_with_web_identity_shared_cache() -> Result<()> {
+        let mock = MockContext::new();
+        let ctx = mock.context();
+        let config = Arc::new(Config {
+            role_arn: Some("arn:aws:iam::123456789012:role/demo".to_string()),
+            web_identity_token_file: Some("not_used".to_string()),
+            ..Default::default()
+        });
+
+        let cache = MemoryCredentialCache::default();
+        let cred = Credential {
+            access_key_id: "cached_access_key_id".to_string(),
+            secret_access_key: "secret_access_key".to_string(),
+            session_token: Some("session_token".to_string()),
+            expires_in: Some(reqsign_core::time::now() + chrono::TimeDelta::try_hours(1).unwrap()),
+        };
+        let key = cache_key(
+            "Action=AssumeRoleWithWebIdentity&RoleArn=arn%3Aaws%3Aiam%3A%3A123456789012%3Arole%2Fdemo&Version=2011-06-15&RoleSessionName=reqsign",
+        );
+        cache.set(&ctx, &key, &cred).await;
+
+        for _ in 0..2 {
+            let loader =
+                AssumeRoleWithWebIdentityLoader::new(config.clone()).with_cache(cache.clone());
+            let cred = loader.load(&ctx).await?.expect("credential must be loaded");
+            assert_eq!(cred.access_key_id, "cached_access_key_id");
+        }
+        assert!(mock.requests().is_empty());
+
+        // Another session name misses the cache.
+        let config = Arc::new(Config {
+            role_session_name: "other".to_string(),
+            ..(*config).clone()
+        });
+        let mock = mock_sts(mock, "token", "other").with_file("not_used", "token");
+        let cred = AssumeRoleWithWebIdentityLoader::new(config)
+            .with_cache(cache.clone())
+            .load(&mock.context())
+            .await?
+            .expect("credential must be loaded");
+        assert_ne!(cred.access_key_id, "cached_access_key_id");
+        assert_eq!(mock.requests().len(), 1);
+        Ok(())
+    }
 
     #[test]
     fn test_parse_assume_role_with_web_identity_response() -> Result<()> {
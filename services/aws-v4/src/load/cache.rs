@@ -0,0 +1,289 @@
+use crate::Credential;
+use async_trait::async_trait;
+use log::{debug, warn};
+use reqsign_core::hash::{hex_sha1, hex_sha256};
+use reqsign_core::time::{format_rfc3339, parse_rfc3339};
+use reqsign_core::{Context, Key};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// CredentialCache stores credentials fetched by loaders like
+/// [`AssumeRoleLoader`](crate::AssumeRoleLoader), so that they can be shared
+/// by loaders in the same process or survive restarts.
+///
+/// `key` identifies the request that fetched the credential: the sha256 of
+/// every STS request parameter except secrets like the web identity token,
+/// so entries are never shared by loaders with different roles, session
+/// policies or tags.
+/// Errors should be logged and ignored since the cache is only an
+/// optimization, loaders will fall back to fetch new credentials.
+#[async_trait]
+pub trait CredentialCache: Debug + Send + Sync + 'static {
+    /// Get the credential of `key`, returns `None` on miss or expiry.
+    async fn get(&self, ctx: &Context, key: &str) -> Option<Credential>;
+
+    /// Store the credential of `key`.
+    async fn set(&self, ctx: &Context, key: &str, cred: &Credential);
+}
+
+/// Build the [`CredentialCache`] key of the STS request with `params`.
+pub(crate) fn cache_key(params: &str) -> String {
+    hex_sha256(params.as_bytes())
+}
+
+/// In-memory credential cache, the default used by loaders.
+///
+/// Clones share the same entries.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryCredentialCache {
+    entries: Arc<Mutex<HashMap<String, Credential>>>,
+}
+
+#[async_trait]
+impl CredentialCache for MemoryCredentialCache {
+    async fn get(&self, ctx: &Context, key: &str) -> Option<Credential> {
+        let mut entries = self.entries.lock().expect("lock poisoned");
+        match entries.get(key) {
+            Some(cred) if cred.is_valid_at(ctx.now()) => Some(cred.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    async fn set(&self, _: &Context, key: &str, cred: &Credential) {
+        self.entries
+            .lock()
+            .expect("lock poisoned")
+            .insert(key.to_string(), cred.clone());
+    }
+}
+
+/// On-disk cache for assumed credentials, like `~/.aws/cli/cache` used by
+/// the AWS CLI.
+///
+/// Credentials are stored in `<dir>/<sha1(key)>.json` via
+/// [`Context::file_write`], which should make them readable by the current
+/// user only. A leading `~` in `dir` is expanded to the home directory.
+#[derive(Debug, Clone)]
+pub struct FileCredentialCache {
+    dir: PathBuf,
+}
+
+impl FileCredentialCache {
+    /// Create a new cache that stores credentials in `dir`.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, ctx: &Context, key: &str) -> Option<String> {
+        let dir = ctx.expand_home_dir(&self.dir.to_string_lossy())?;
+        let path = Path::new(&dir).join(format!("{}.json", hex_sha1(key.as_bytes())));
+        Some(path.to_string_lossy().to_string())
+    }
+}
+
+#[async_trait]
+impl CredentialCache for FileCredentialCache {
+    async fn get(&self, ctx: &Context, key: &str) -> Option<Credential> {
+        let path = self.path(ctx, key)?;
+        let content = ctx.file_read(&path).await.ok()?;
+
+        let file: CacheFile = match serde_json::from_slice(&content) {
+            Ok(v) => v,
+            Err(err) => {
+                warn!("ignore invalid credential cache {}: {err}", path);
+                return None;
+            }
+        };
+        let cred = Credential {
+            access_key_id: file.credentials.access_key_id,
+            secret_access_key: file.credentials.secret_access_key,
+            session_token: Some(file.credentials.session_token),
+            expires_in: Some(parse_rfc3339(&file.credentials.expiration).ok()?),
+        };
+        if !cred.is_valid_at(ctx.now()) {
+            debug!("credential cache {} is expired", path);
+            return None;
+        }
+
+        debug!("load credential from cache {}", path);
+        Some(cred)
+    }
+
+    /// Write credential into cache atomically.
+    ///
+    /// Only temporary credentials with session token and expiration are
+    /// written.
+    async fn set(&self, ctx: &Context, key: &str, cred: &Credential) {
+        let (Some(session_token), Some(expires_in)) = (&cred.session_token, cred.expires_in) else {
+            return;
+        };
+
+        let file = CacheFile {
+            credentials: CacheCredentials {
+                access_key_id: cred.access_key_id.clone(),
+                secret_access_key: cred.secret_access_key.clone(),
+                session_token: session_token.clone(),
+                expiration: format_rfc3339(expires_in),
+            },
+        };
+
+        let Some(path) = self.path(ctx, key) else {
+            warn!(
+                "skip credential cache: home dir of {:?} not found",
+                self.dir
+            );
+            return;
+        };
+        let content = match serde_json::to_vec(&file) {
+            Ok(v) => v,
+            Err(err) => {
+                warn!("serialize credential cache {path} failed: {err}");
+                return;
+            }
+        };
+        if let Err(err) = ctx.file_write(&path, &content).await {
+            warn!("write credential cache {path} failed: {err:#}");
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CacheFile {
+    credentials: CacheCredentials,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CacheCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: String,
+    expiration: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqsign_core::time::now;
+    use reqsign_core::{FixedClock, MockContext, StaticEnv};
+
+    #[tokio::test]
+    async fn test_credential_cache() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let caches: Vec<Box<dyn CredentialCache>> = vec![
+            Box::new(MemoryCredentialCache::default()),
+            Box::new(FileCredentialCache::new(dir.path().join("cache"))),
+        ];
+        let ctx = Context::new(reqsign_file_read_tokio::TokioFileRead, MockContext::new());
+
+        for cache in caches {
+            let key = "arn:aws:iam::123456789012:role/test:reqsign";
+            assert!(cache.get(&ctx, key).await.is_none(), "{cache:?}");
+
+            let cred = Credential {
+                access_key_id: "access_key_id".to_string(),
+                secret_access_key: "secret_access_key".to_string(),
+                session_token: Some("session_token".to_string()),
+                expires_in: Some(now() + chrono::TimeDelta::try_hours(1).unwrap()),
+            };
+            cache.set(&ctx, key, &cred).await;
+
+            let cached = cache.get(&ctx, key).await.unwrap();
+            assert_eq!(cached.access_key_id, "access_key_id", "{cache:?}");
+            assert_eq!(cached.session_token.as_deref(), Some("session_token"));
+            assert!(cache.get(&ctx, "other").await.is_none(), "{cache:?}");
+
+            // Expired credentials should be ignored.
+            let expired = Credential {
+                expires_in: Some(now() - chrono::TimeDelta::try_hours(1).unwrap()),
+                ..cred
+            };
+            cache.set(&ctx, key, &expired).await;
+            assert!(cache.get(&ctx, key).await.is_none(), "{cache:?}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_credential_cache_expiry_follows_context_clock() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let expires_in = parse_rfc3339("2024-01-01T00:00:00Z")?;
+        let hour = chrono::TimeDelta::try_hours(1).unwrap();
+        let cred = Credential {
+            access_key_id: "access_key_id".to_string(),
+            secret_access_key: "secret_access_key".to_string(),
+            session_token: Some("session_token".to_string()),
+            expires_in: Some(expires_in),
+        };
+        let caches: Vec<Box<dyn CredentialCache>> = vec![
+            Box::new(MemoryCredentialCache::default()),
+            Box::new(FileCredentialCache::new(dir.path().join("cache"))),
+        ];
+
+        for cache in caches {
+            let ctx = Context::new(reqsign_file_read_tokio::TokioFileRead, MockContext::new())
+                .with_clock(FixedClock(expires_in - hour));
+            cache.set(&ctx, "key", &cred).await;
+            assert!(cache.get(&ctx, "key").await.is_some(), "{cache:?}");
+
+            let ctx = ctx.with_clock(FixedClock(expires_in + hour));
+            assert!(cache.get(&ctx, "key").await.is_none(), "{cache:?}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_file_credential_cache_expand_home_dir() -> anyhow::Result<()> {
+        let home = tempfile::tempdir()?;
+        let ctx = Context::new(reqsign_file_read_tokio::TokioFileRead, MockContext::new())
+            .with_env(StaticEnv {
+                home_dir: Some(home.path().to_path_buf()),
+                envs: HashMap::new(),
+            });
+        let cache = FileCredentialCache::new("~/.aws/cli/cache");
+        let cred = Credential {
+            access_key_id: "access_key_id".to_string(),
+            secret_access_key: "secret_access_key".to_string(),
+            session_token: Some("session_token".to_string()),
+            expires_in: Some(now() + chrono::TimeDelta::try_hours(1).unwrap()),
+        };
+        cache.set(&ctx, "key", &cred).await;
+        assert!(cache.get(&ctx, "key").await.is_some());
+
+        let path = home
+            .path()
+            .join(".aws/cli/cache")
+            .join(format!("{}.json", hex_sha1(b"key")));
+        assert!(path.exists(), "{path:?}");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = std::fs::metadata(&path)?.permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_credential_cache_shared_by_clones() {
+        let ctx = Context::new(reqsign_file_read_tokio::TokioFileRead, MockContext::new());
+        let cache = MemoryCredentialCache::default();
+        let cred = Credential {
+            access_key_id: "access_key_id".to_string(),
+            secret_access_key: "secret_access_key".to_string(),
+            session_token: None,
+            expires_in: None,
+        };
+        cache.clone().set(&ctx, "key", &cred).await;
+        assert!(cache.get(&ctx, "key").await.is_some());
+    }
+}
@@ -0,0 +1,189 @@
+use crate::load::default::{is_skippable, load_source};
+use crate::Credential;
+use async_trait::async_trait;
+use log::debug;
+use reqsign_core::{Context, Load};
+
+/// ChainLoader tries the given loaders in order and returns the first
+/// credential loaded.
+///
+/// Unlike [`DefaultLoader`](crate::DefaultLoader), the chain is built from
+/// any loaders, so users can reorder them, add their own or disable some
+/// of them via [`ChainLoader::with_enabled`].
+///
+/// Loaders failing with [`Error::Timeout`](reqsign_core::Error::Timeout) or
+/// [`Error::CredentialNotFound`](reqsign_core::Error::CredentialNotFound)
+/// are skipped, other errors stop the chain.
+///
+/// ```
+/// use reqsign_aws_v4::{ChainLoader, Config, ConfigLoader, IMDSv2Loader};
+/// use std::sync::Arc;
+///
+/// let config = Arc::new(Config::default());
+/// let loader = ChainLoader::new(vec![
+///     Box::new(ConfigLoader::new(config.clone())),
+///     Box::new(IMDSv2Loader::new(config)),
+/// ])
+/// // Disable IMDS.
+/// .with_enabled(1, false);
+/// ```
+#[derive(Debug, Default)]
+pub struct ChainLoader {
+    loaders: Vec<(bool, Box<dyn Load<Key = Credential>>)>,
+}
+
+impl ChainLoader {
+    /// Create a new `ChainLoader` with all loaders enabled.
+    pub fn new(loaders: Vec<Box<dyn Load<Key = Credential>>>) -> Self {
+        Self {
+            loaders: loaders.into_iter().map(|v| (true, v)).collect(),
+        }
+    }
+
+    /// Append a loader to the end of the chain.
+    pub fn with_loader(mut self, loader: impl Load<Key = Credential>) -> Self {
+        self.loaders.push((true, Box::new(loader)));
+        self
+    }
+
+    /// Enable or disable the loader at `index`, disabled loaders are skipped.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn with_enabled(mut self, index: usize, enabled: bool) -> Self {
+        self.loaders[index].0 = enabled;
+        self
+    }
+}
+
+#[async_trait]
+impl Load for ChainLoader {
+    type Key = Credential;
+
+    async fn load(&self, ctx: &Context) -> anyhow::Result<Option<Self::Key>> {
+        for (idx, (enabled, loader)) in self.loaders.iter().enumerate() {
+            if !enabled {
+                continue;
+            }
+            let source = format!("chain[{idx}]");
+            match load_source(&source, loader.as_ref(), ctx).await {
+                Ok(Some(cred)) => return Ok(Some(cred)),
+                Ok(None) => {}
+                Err(err) if is_skippable(&err) => {
+                    debug!("skip credential source {source}: {err:#}");
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::constants::{AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY};
+    use crate::{Config, ConfigLoader, IMDSv2Loader};
+    use http::{Method, StatusCode};
+    use reqsign_core::{MockContext, StaticEnv};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_chain_loader_with_env_and_imds() -> anyhow::Result<()> {
+        let mock = MockContext::new()
+            .with_response(
+                Method::PUT,
+                "http://169.254.169.254/latest/api/token",
+                StatusCode::OK,
+                "token",
+            )
+            .with_response(
+                Method::GET,
+                "http://169.254.169.254/latest/meta-data/iam/security-credentials/",
+                StatusCode::OK,
+                "demo",
+            )
+            .with_response(
+                Method::GET,
+                "http://169.254.169.254/latest/meta-data/iam/security-credentials/demo",
+                StatusCode::OK,
+                r#"{"Code":"Success","AccessKeyId":"imds_access_key_id","SecretAccessKey":"secret_access_key","Token":"session_token","Expiration":"2024-01-01T00:00:00Z"}"#,
+            );
+        let ctx = mock.context().with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([
+                (
+                    AWS_ACCESS_KEY_ID.to_string(),
+                    "env_access_key_id".to_string(),
+                ),
+                (
+                    AWS_SECRET_ACCESS_KEY.to_string(),
+                    "secret_access_key".to_string(),
+                ),
+            ]),
+        });
+        let config = Arc::new(Config::default().from_env(&ctx));
+        let chain = || {
+            ChainLoader::new(vec![Box::new(ConfigLoader::new(config.clone()))])
+                .with_loader(IMDSv2Loader::new(config.clone()))
+        };
+
+        let cred = chain()
+            .load(&ctx)
+            .await?
+            .expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "env_access_key_id");
+        assert!(mock.requests().is_empty());
+
+        let cred = chain()
+            .with_enabled(0, false)
+            .load(&ctx)
+            .await?
+            .expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "imds_access_key_id");
+        assert_eq!(mock.requests().len(), 3);
+
+        let cred = chain()
+            .with_enabled(0, false)
+            .with_enabled(1, false)
+            .load(&ctx)
+            .await?;
+        assert!(cred.is_none());
+        assert_eq!(mock.requests().len(), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_chain_loader_skip_imds_timeout() -> anyhow::Result<()> {
+        let mock =
+            MockContext::new().with_timeout(Method::PUT, "http://169.254.169.254/latest/api/token");
+        let ctx = mock.context().with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([
+                (
+                    AWS_ACCESS_KEY_ID.to_string(),
+                    "env_access_key_id".to_string(),
+                ),
+                (
+                    AWS_SECRET_ACCESS_KEY.to_string(),
+                    "secret_access_key".to_string(),
+                ),
+            ]),
+        });
+        let config = Arc::new(Config::default().from_env(&ctx));
+
+        let cred = ChainLoader::new(vec![
+            Box::new(IMDSv2Loader::new(config.clone())),
+            Box::new(ConfigLoader::new(config)),
+        ])
+        .load(&ctx)
+        .await?
+        .expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "env_access_key_id");
+        assert_eq!(mock.requests().len(), 1);
+        Ok(())
+    }
+}
@@ -1,18 +1,103 @@
 use crate::{Config, Credential};
 use async_trait::async_trait;
-use reqsign_core::{Context, Load};
-use std::sync::Arc;
+use reqsign_core::{Context, Error, Load};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
 
-/// TODO: we should support refresh from config file.
+/// Where to get the base credentials for assuming a role, set via
+/// `credential_source` in the profile.
+///
+/// - [Credential source](https://docs.aws.amazon.com/cli/latest/userguide/cli-configure-role.html#cli-configure-role-ec2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CredentialSource {
+    /// Use the EC2 instance metadata service.
+    Ec2InstanceMetadata,
+    /// Use the ECS container credentials provider.
+    EcsContainer,
+    /// Use credentials from environment variables.
+    Environment,
+}
+
+impl FromStr for CredentialSource {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Ec2InstanceMetadata" => Ok(Self::Ec2InstanceMetadata),
+            "EcsContainer" => Ok(Self::EcsContainer),
+            "Environment" => Ok(Self::Environment),
+            v => Err(Error::Config(format!("unknown credential_source: {v}"))),
+        }
+    }
+}
+
+/// ConfigLoader loads the static credential resolved by [`Config`].
+///
+/// The profile files are only read when building the config via
+/// [`Config::from_profile`], call [`ConfigLoader::with_profile_reload`] to
+/// pick up changes of them.
 #[derive(Debug)]
 pub struct ConfigLoader {
     config: Arc<Config>,
+    profile_cache: Option<Mutex<ProfileCache>>,
+}
+
+/// The config resolved from profile files with their modification times.
+#[derive(Debug)]
+struct ProfileCache {
+    modified: Option<[Option<SystemTime>; 2]>,
+    config: Arc<Config>,
 }
 
 impl ConfigLoader {
     /// Create a new `ConfigLoader` instance.
     pub fn new(cfg: Arc<Config>) -> Self {
-        Self { config: cfg }
+        Self {
+            config: cfg,
+            profile_cache: None,
+        }
+    }
+
+    /// Reload the profile of `config_file` and `shared_credentials_file`
+    /// when they have changed.
+    ///
+    /// The modification times of both files are checked on every load, and
+    /// the profile is parsed again on top of the given config only if they
+    /// differ from the last load. Files are only parsed on the first load if
+    /// the [`reqsign_core::FileRead`] doesn't report modification times.
+    pub fn with_profile_reload(mut self) -> Self {
+        self.profile_cache = Some(Mutex::new(ProfileCache {
+            modified: None,
+            config: self.config.clone(),
+        }));
+        self
+    }
+
+    /// Get the region resolved by config.
+    ///
+    /// It follows the same precedence as credentials: this field, env value
+    /// `AWS_REGION` and then profile config `region`.
+    pub fn region(&self) -> Option<String> {
+        self.config.region.clone()
+    }
+
+    /// Get the [`CredentialSource`] of the profile.
+    ///
+    /// Returns an error if the value is unknown or `source_profile` is set
+    /// at the same time.
+    pub fn credential_source(&self) -> anyhow::Result<Option<CredentialSource>> {
+        let Some(source) = &self.config.credential_source else {
+            return Ok(None);
+        };
+        if self.config.source_profile.is_some() {
+            return Err(Error::Config(
+                "source_profile and credential_source must not be set at the same time".to_string(),
+            )
+            .into());
+        }
+
+        Ok(Some(source.parse()?))
     }
 }
 
@@ -20,17 +105,288 @@ impl ConfigLoader {
 impl Load for ConfigLoader {
     type Key = Credential;
 
-    async fn load(&self, _: &Context) -> anyhow::Result<Option<Self::Key>> {
-        let (Some(ak), Some(sk)) = (&self.config.access_key_id, &self.config.secret_access_key)
-        else {
+    async fn load(&self, ctx: &Context) -> anyhow::Result<Option<Self::Key>> {
+        let config = self.current_config(ctx).await?;
+
+        let (Some(ak), Some(sk)) = (&config.access_key_id, &config.secret_access_key) else {
             return Ok(None);
         };
 
-        Ok(Some(Credential {
-            access_key_id: ak.clone(),
-            secret_access_key: sk.clone(),
-            session_token: self.config.session_token.clone(),
-            expires_in: None,
-        }))
+        let cred = Credential {
+            access_key_id: normalize_value(ak).to_string(),
+            secret_access_key: normalize_value(sk).to_string(),
+            session_token: config
+                .session_token
+                .as_deref()
+                .map(|v| normalize_value(v).to_string()),
+            expires_in: config.credential_expiration,
+        };
+        cred.check()?;
+        Ok(Some(cred))
+    }
+}
+
+impl ConfigLoader {
+    /// Get the config used on load, with the latest profile if reloading is
+    /// enabled.
+    pub(crate) async fn current_config(&self, ctx: &Context) -> anyhow::Result<Arc<Config>> {
+        match &self.profile_cache {
+            Some(cache) => self.reload_profile(ctx, cache).await,
+            None => Ok(self.config.clone()),
+        }
+    }
+
+    /// Get the config with the latest profile, files are parsed only if
+    /// their modification times have changed.
+    async fn reload_profile(
+        &self,
+        ctx: &Context,
+        cache: &Mutex<ProfileCache>,
+    ) -> anyhow::Result<Arc<Config>> {
+        let modified = [
+            file_modified(ctx, &self.config.config_file).await,
+            file_modified(ctx, &self.config.shared_credentials_file).await,
+        ];
+        {
+            let cache = cache.lock().expect("lock poisoned");
+            if cache.modified.is_some()
+                && (modified == [None, None] || cache.modified == Some(modified))
+            {
+                return Ok(cache.config.clone());
+            }
+        }
+
+        let config = Arc::new(self.config.as_ref().clone().from_profile(ctx).await?);
+        let mut cache = cache.lock().expect("lock poisoned");
+        *cache = ProfileCache {
+            modified: Some(modified),
+            config: config.clone(),
+        };
+        Ok(config)
+    }
+}
+
+/// Get the modification time of the file, returns `None` if it's unknown.
+async fn file_modified(ctx: &Context, path: &str) -> Option<SystemTime> {
+    let path = ctx.expand_home_dir(path)?;
+    ctx.file_modified(&path).await.ok().flatten()
+}
+
+/// Trim surrounding whitespace and strip one pair of matched quotes.
+///
+/// Values copied from shell exports like `export AWS_SECRET_ACCESS_KEY="secret"`
+/// often carry them, which leads to invalid signatures. Characters inside the
+/// value are kept as is.
+fn normalize_value(v: &str) -> &str {
+    let v = v.trim();
+    for quote in ['"', '\''] {
+        if let Some(inner) = v.strip_prefix(quote).and_then(|v| v.strip_suffix(quote)) {
+            return inner.trim();
+        }
+    }
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credential_source() -> anyhow::Result<()> {
+        let cases = vec![
+            ("not set", None, None, Some(None)),
+            (
+                "ec2",
+                Some("Ec2InstanceMetadata"),
+                None,
+                Some(Some(CredentialSource::Ec2InstanceMetadata)),
+            ),
+            (
+                "ecs",
+                Some("EcsContainer"),
+                None,
+                Some(Some(CredentialSource::EcsContainer)),
+            ),
+            (
+                "env",
+                Some("Environment"),
+                None,
+                Some(Some(CredentialSource::Environment)),
+            ),
+            ("unknown", Some("Unknown"), None, None),
+            (
+                "with source_profile",
+                Some("Environment"),
+                Some("default"),
+                None,
+            ),
+        ];
+
+        for (name, credential_source, source_profile, expected) in cases {
+            let loader = ConfigLoader::new(Arc::new(Config {
+                credential_source: credential_source.map(|v| v.to_string()),
+                source_profile: source_profile.map(|v| v.to_string()),
+                ..Default::default()
+            }));
+
+            match expected {
+                Some(expected) => assert_eq!(loader.credential_source()?, expected, "case {name}"),
+                None => {
+                    let err = loader.credential_source().expect_err("must fail");
+                    assert!(
+                        matches!(err.downcast_ref::<Error>(), Some(Error::Config(_))),
+                        "case {name}"
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_value() {
+        let cases = [
+            ("plain", "secret", "secret"),
+            ("trailing newline", "secret\n", "secret"),
+            ("surrounding spaces", "  secret ", "secret"),
+            ("double quoted", "\"secret\"", "secret"),
+            ("single quoted", "'secret'", "secret"),
+            ("quoted with newline", "\"secret\"\n", "secret"),
+            ("unmatched quotes", "\"secret'", "\"secret'"),
+            ("single quote char", "\"", "\""),
+            ("internal chars", "se\"c r/e+t=", "se\"c r/e+t="),
+        ];
+        for (name, input, expected) in cases {
+            assert_eq!(normalize_value(input), expected, "case {name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_config_loader_normalize() -> anyhow::Result<()> {
+        let loader = ConfigLoader::new(Arc::new(Config {
+            access_key_id: Some("'access_key_id'".to_string()),
+            secret_access_key: Some("secret\n".to_string()),
+            session_token: Some(" \"session_token\" ".to_string()),
+            ..Default::default()
+        }));
+        let ctx = Context::new(
+            reqsign_file_read_tokio::TokioFileRead,
+            reqsign_http_send_reqwest::ReqwestHttpSend::default(),
+        );
+
+        let cred = loader.load(&ctx).await?.expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "access_key_id");
+        assert_eq!(cred.secret_access_key, "secret");
+        assert_eq!(cred.session_token.as_deref(), Some("session_token"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_config_loader_with_profile_reload() -> anyhow::Result<()> {
+        use crate::constants::AWS_SHARED_CREDENTIALS_FILE;
+        use reqsign_core::StaticEnv;
+        use std::collections::HashMap;
+        use std::time::Duration;
+
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("credentials");
+        let write = |ak: &str, modified: SystemTime| -> anyhow::Result<()> {
+            std::fs::write(
+                &path,
+                format!("[default]\naws_access_key_id = {ak}\naws_secret_access_key = secret\n"),
+            )?;
+            std::fs::File::options()
+                .write(true)
+                .open(&path)?
+                .set_modified(modified)?;
+            Ok(())
+        };
+        let now = SystemTime::now();
+        write("access_key_id", now)?;
+
+        let ctx = Context::new(
+            reqsign_file_read_tokio::TokioFileRead,
+            reqsign_http_send_reqwest::ReqwestHttpSend::default(),
+        )
+        .with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([(
+                AWS_SHARED_CREDENTIALS_FILE.to_string(),
+                path.to_string_lossy().to_string(),
+            )]),
+        });
+        let config = Arc::new(Config::default().from_env(&ctx).from_profile(&ctx).await?);
+        let static_loader = ConfigLoader::new(config.clone());
+        let loader = ConfigLoader::new(config).with_profile_reload();
+
+        let cred = loader.load(&ctx).await?.expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "access_key_id");
+
+        write("rotated_access_key_id", now + Duration::from_secs(60))?;
+        let cred = loader.load(&ctx).await?.expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "rotated_access_key_id");
+        let cred = static_loader
+            .load(&ctx)
+            .await?
+            .expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "access_key_id");
+
+        // Files are not parsed again if the modification time is unchanged.
+        std::fs::write(
+            &path,
+            "[default]\naws_access_key_id = unseen\naws_secret_access_key = secret\n",
+        )?;
+        std::fs::File::options()
+            .write(true)
+            .open(&path)?
+            .set_modified(now + Duration::from_secs(60))?;
+        let cred = loader.load(&ctx).await?.expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "rotated_access_key_id");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_config_loader_region() -> anyhow::Result<()> {
+        use crate::constants::{AWS_CONFIG_FILE, AWS_PROFILE, AWS_REGION};
+        use reqsign_core::StaticEnv;
+        use std::collections::HashMap;
+
+        let testdata = format!("{}/testdata", env!("CARGO_MANIFEST_DIR"));
+        let envs = HashMap::from_iter([
+            (AWS_PROFILE.to_string(), "dev".to_string()),
+            (
+                AWS_CONFIG_FILE.to_string(),
+                format!("{testdata}/profile_config"),
+            ),
+        ]);
+        let ctx = Context::new(
+            reqsign_file_read_tokio::TokioFileRead,
+            reqsign_http_send_reqwest::ReqwestHttpSend::default(),
+        );
+
+        // Profile region is used if env is not set.
+        let ctx = ctx.with_env(StaticEnv {
+            home_dir: None,
+            envs: envs.clone(),
+        });
+        let config = Config::default().from_env(&ctx).from_profile(&ctx).await?;
+        assert_eq!(
+            ConfigLoader::new(Arc::new(config)).region().as_deref(),
+            Some("us-west-2")
+        );
+
+        // Env region takes precedence over profile.
+        let mut envs = envs;
+        envs.insert(AWS_REGION.to_string(), "eu-west-1".to_string());
+        let ctx = ctx.with_env(StaticEnv {
+            home_dir: None,
+            envs,
+        });
+        let config = Config::default().from_env(&ctx).from_profile(&ctx).await?;
+        assert_eq!(
+            ConfigLoader::new(Arc::new(config)).region().as_deref(),
+            Some("eu-west-1")
+        );
+        Ok(())
     }
 }
@@ -1,70 +1,395 @@
 use crate::load::config::ConfigLoader;
-use crate::load::{AssumeRoleWithWebIdentityLoader, IMDSv2Loader};
+use crate::load::{AssumeRoleLoader, AssumeRoleWithWebIdentityLoader, ECSLoader, IMDSv2Loader};
 use crate::{Config, Credential};
+use anyhow::Context as _;
 use async_trait::async_trait;
-use reqsign_core::{Context, Load};
-use std::sync::Arc;
+use log::{debug, warn};
+use reqsign_core::{Context, Error, Load};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// A credential source of [`DefaultLoader`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChainSource {
+    /// Static credentials of the given [`Config`], including env values
+    /// loaded via [`Config::from_env`] and profile values loaded via
+    /// [`Config::from_profile`].
+    Env,
+    /// The profile of `config_file` and `shared_credentials_file`, parsed
+    /// on load and again whenever they change.
+    ///
+    /// It's not part of the default chain, list it in
+    /// [`DefaultLoader::with_sources`] to read the profile files even if
+    /// the config is not built via [`Config::from_profile`].
+    Profile,
+    /// `AssumeRole` for configs with `role_arn`, using the base credentials
+    /// of `source_profile` or `credential_source`.
+    AssumeRole,
+    /// `AssumeRoleWithWebIdentity` with `web_identity_token_file`.
+    WebIdentity,
+    /// The ECS container credentials provider.
+    Ecs,
+    /// The EC2 instance metadata service.
+    Imds,
+}
+
+impl ChainSource {
+    /// Get the name of the source used in logs, like `imds_v2`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChainSource::Env => "env",
+            ChainSource::Profile => "profile",
+            ChainSource::AssumeRole => "assume_role",
+            ChainSource::WebIdentity => "web_identity",
+            ChainSource::Ecs => "ecs",
+            ChainSource::Imds => "imds_v2",
+        }
+    }
+
+    /// The sources of [`DefaultLoader`] by default, in order.
+    const DEFAULT: [ChainSource; 5] = [
+        ChainSource::Env,
+        ChainSource::AssumeRole,
+        ChainSource::WebIdentity,
+        ChainSource::Ecs,
+        ChainSource::Imds,
+    ];
+
+    /// All sources known by [`DefaultLoader`].
+    const ALL: [ChainSource; 6] = [
+        ChainSource::Env,
+        ChainSource::Profile,
+        ChainSource::AssumeRole,
+        ChainSource::WebIdentity,
+        ChainSource::Ecs,
+        ChainSource::Imds,
+    ];
+}
 
 /// DefaultLoader is a loader that will try to load credential via default chains.
 ///
 /// Resolution order:
 ///
 /// 1. Environment variables
-/// 2. Shared config (`~/.aws/config`, `~/.aws/credentials`)
-/// 3. Web Identity Tokens
-/// 4. ECS (IAM Roles for Tasks) & General HTTP credentials (TODO)
-/// 5. EC2 IMDSv2
+/// 2. Shared config (`~/.aws/config`, `~/.aws/credentials`), if the config
+///    is built via [`Config::from_profile`]
+/// 3. Assume role with `source_profile` or `credential_source`
+/// 4. Web Identity Tokens
+/// 5. ECS container credentials
+/// 6. EC2 IMDSv2
+///
+/// The profile files are not read by the default chain otherwise. Use
+/// [`DefaultLoader::with_sources`] to define another chain, for example,
+/// to read the profile files via [`ChainSource::Profile`] and let them win
+/// over env, or to disable IMDS.
 #[derive(Debug)]
 pub struct DefaultLoader {
+    config: Arc<Config>,
+    sources: Vec<ChainSource>,
     config_loader: ConfigLoader,
+    profile_loader: ConfigLoader,
     assume_role_with_web_identity_loader: AssumeRoleWithWebIdentityLoader,
+    ecs_loader: ECSLoader,
     imds_v2_loader: IMDSv2Loader,
+    /// Built on first use since it needs the context to sign STS requests.
+    assume_role_loader: Mutex<Option<Arc<AssumeRoleLoader>>>,
+    /// Whether the summary of an empty chain has been logged at WARN.
+    warned: AtomicBool,
 }
 
 impl DefaultLoader {
     /// Create a new `DefaultLoader` instance.
     pub fn new(config: Arc<Config>) -> Self {
         let config_loader = ConfigLoader::new(config.clone());
+        let profile_loader = ConfigLoader::new(Arc::new(Config {
+            profile: config.profile.clone(),
+            config_file: config.config_file.clone(),
+            shared_credentials_file: config.shared_credentials_file.clone(),
+            ..Default::default()
+        }))
+        .with_profile_reload();
         let assume_role_with_web_identity_loader =
             AssumeRoleWithWebIdentityLoader::new(config.clone());
         let imds_v2_loader = IMDSv2Loader::new(config.clone());
 
         Self {
+            sources: ChainSource::DEFAULT.to_vec(),
             config_loader,
+            profile_loader,
             assume_role_with_web_identity_loader,
+            ecs_loader: ECSLoader::new(),
             imds_v2_loader,
+            assume_role_loader: Mutex::new(None),
+            warned: AtomicBool::new(false),
+            config,
+        }
+    }
+
+    /// Set the sources to try in order, sources not listed are disabled.
+    ///
+    /// For example, `[ChainSource::Imds]` only loads credentials from the
+    /// instance metadata.
+    pub fn with_sources(mut self, sources: impl IntoIterator<Item = ChainSource>) -> Self {
+        self.sources = sources.into_iter().collect();
+        self
+    }
+
+    /// Reload the profile files when they have changed, see
+    /// [`ConfigLoader::with_profile_reload`].
+    pub fn with_profile_reload(mut self) -> Self {
+        self.config_loader = self.config_loader.with_profile_reload();
+        self
+    }
+
+    /// Get the region resolved with the same precedence as credentials.
+    ///
+    /// The region of the first source in the chain that has one is
+    /// returned: [`ChainSource::Env`] uses the given config, see
+    /// [`ConfigLoader::region`], and [`ChainSource::Profile`] reads `region`
+    /// from the profile files. Other sources don't provide a region.
+    pub async fn region(&self, ctx: &Context) -> anyhow::Result<Option<String>> {
+        for source in &self.sources {
+            let loader = match source {
+                ChainSource::Env => &self.config_loader,
+                ChainSource::Profile => &self.profile_loader,
+                _ => continue,
+            };
+            if let Some(region) = &loader.current_config(ctx).await?.region {
+                return Ok(Some(region.clone()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Load credential along with the source that provided it.
+    ///
+    /// This is the same as [`Load::load`], but tells whether the credential
+    /// comes from env, profile or IMDS, which is useful for debugging and
+    /// metrics.
+    ///
+    /// Sources failing with [`Error::Timeout`] or
+    /// [`Error::CredentialNotFound`], like IMDS outside of EC2, are skipped
+    /// so that the next source is tried, other errors are returned as is.
+    ///
+    /// If no source provides a credential, a summary of every source and
+    /// why it was skipped (disabled, not configured, or the skipped error)
+    /// is logged at WARN the first time and at DEBUG afterwards.
+    pub async fn load_with_source(
+        &self,
+        ctx: &Context,
+    ) -> anyhow::Result<Option<LoadedCredential>> {
+        let outcomes = match self.try_sources(ctx).await? {
+            Ok(loaded) => return Ok(Some(loaded)),
+            Err(outcomes) => outcomes,
+        };
+
+        let summary = summary(&outcomes);
+        if self.warned.swap(true, Ordering::Relaxed) {
+            debug!("no credential loaded from default chain: {summary}");
+        } else {
+            warn!("no credential loaded from default chain: {summary}");
+        }
+        Ok(None)
+    }
+
+    /// Try the sources in order, returns the outcome of every source if
+    /// none of them provides a credential.
+    async fn try_sources(
+        &self,
+        ctx: &Context,
+    ) -> anyhow::Result<Result<LoadedCredential, Vec<(ChainSource, Outcome)>>> {
+        let mut outcomes = Vec::with_capacity(ChainSource::ALL.len());
+        for &source in &self.sources {
+            let name = source.as_str();
+            let result = match source {
+                ChainSource::Env => load_source(name, &self.config_loader, ctx).await,
+                ChainSource::Profile => load_source(name, &self.profile_loader, ctx).await,
+                ChainSource::AssumeRole => match self.assume_role_loader(ctx) {
+                    Ok(Some(loader)) => load_source(name, loader.as_ref(), ctx).await,
+                    Ok(None) => Ok(None),
+                    Err(err) => Err(err),
+                },
+                ChainSource::WebIdentity => {
+                    load_source(name, &self.assume_role_with_web_identity_loader, ctx).await
+                }
+                ChainSource::Ecs => load_source(name, &self.ecs_loader, ctx).await,
+                ChainSource::Imds if self.imds_v2_loader.is_disabled(ctx) => {
+                    outcomes.push((source, Outcome::Disabled));
+                    continue;
+                }
+                ChainSource::Imds => load_source(name, &self.imds_v2_loader, ctx).await,
+            };
+            match result {
+                Ok(Some(credential)) => return Ok(Ok(LoadedCredential { credential, source })),
+                Ok(None) => outcomes.push((source, Outcome::NotConfigured)),
+                Err(err) if is_skippable(&err) => {
+                    debug!("skip credential source {name}: {err:#}");
+                    outcomes.push((source, Outcome::Errored(format!("{err:#}"))));
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        for source in ChainSource::ALL {
+            if !self.sources.contains(&source) {
+                outcomes.push((source, Outcome::Disabled));
+            }
+        }
+        Ok(Err(outcomes))
+    }
+
+    /// Get the assume role loader if the config has `role_arn` along with
+    /// `source_profile` or `credential_source`.
+    ///
+    /// Configs with `role_arn` and `web_identity_token_file` are handled by
+    /// [`ChainSource::WebIdentity`] instead.
+    fn assume_role_loader(&self, ctx: &Context) -> anyhow::Result<Option<Arc<AssumeRoleLoader>>> {
+        if self.config.role_arn.is_none() {
+            return Ok(None);
         }
+
+        let mut loader = self.assume_role_loader.lock().expect("lock poisoned");
+        if let Some(loader) = loader.as_ref() {
+            return Ok(Some(loader.clone()));
+        }
+        let built = if self.config.source_profile.is_some() {
+            AssumeRoleLoader::from_source_profile(ctx.clone(), self.config.clone())?
+        } else if self.config.credential_source.is_some() {
+            AssumeRoleLoader::from_credential_source(ctx.clone(), self.config.clone())?
+        } else {
+            return Ok(None);
+        };
+        Ok(Some(loader.insert(Arc::new(built)).clone()))
     }
 }
 
+/// Why a source of [`DefaultLoader`] yielded no credential.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Outcome {
+    /// The source is not listed in the chain or disabled by config.
+    Disabled,
+    /// The source is tried but not configured in this environment.
+    NotConfigured,
+    /// The source failed with a skippable error, see [`is_skippable`].
+    Errored(String),
+}
+
+/// Describe why every source yielded no credential, like
+/// `env: not configured, imds_v2: errored (request timed out), ecs: disabled`.
+///
+/// Only the error messages of loaders are included, which never carry
+/// secrets.
+fn summary(outcomes: &[(ChainSource, Outcome)]) -> String {
+    outcomes
+        .iter()
+        .map(|(source, outcome)| match outcome {
+            Outcome::Disabled => format!("{}: disabled", source.as_str()),
+            Outcome::NotConfigured => format!("{}: not configured", source.as_str()),
+            Outcome::Errored(err) => format!("{}: errored ({err})", source.as_str()),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Credential loaded by [`DefaultLoader::load_with_source`].
+#[derive(Clone, Debug)]
+pub struct LoadedCredential {
+    /// The loaded credential.
+    pub credential: Credential,
+    /// The source that provided the credential.
+    pub source: ChainSource,
+}
+
 #[async_trait]
 impl Load for DefaultLoader {
     type Key = Credential;
 
     async fn load(&self, ctx: &Context) -> anyhow::Result<Option<Self::Key>> {
-        if let Some(cred) = self.config_loader.load(ctx).await? {
-            return Ok(Some(cred));
-        }
+        Ok(self.load_with_source(ctx).await?.map(|v| v.credential))
+    }
+}
 
-        if let Some(cred) = self.assume_role_with_web_identity_loader.load(ctx).await? {
-            return Ok(Some(cred));
-        }
+/// Whether the chain should move on to the next source after `err`.
+///
+/// Timeouts and missing credentials mean the source is not available in
+/// this environment, other errors like invalid config are reported.
+pub(crate) fn is_skippable(err: &anyhow::Error) -> bool {
+    matches!(
+        err.downcast_ref::<Error>(),
+        Some(Error::Timeout(_) | Error::CredentialNotFound(_))
+    )
+}
 
-        if let Some(cred) = self.imds_v2_loader.load(ctx).await? {
-            return Ok(Some(cred));
-        }
+/// Load credential from one source of the chain.
+///
+/// With `tracing` enabled, every attempt runs in a `load_credential` span
+/// and reports its outcome (`hit`, `miss` or `error`) and duration. Only
+/// metadata like the source name and expiry are emitted, never the secrets.
+pub(crate) async fn load_source(
+    source: &str,
+    loader: &(impl Load<Key = Credential> + ?Sized),
+    ctx: &Context,
+) -> anyhow::Result<Option<Credential>> {
+    #[cfg(feature = "tracing")]
+    {
+        use tracing::Instrument;
 
-        Ok(None)
+        let span = tracing::debug_span!("load_credential", source);
+        let start = std::time::Instant::now();
+        let result = load_checked(source, loader, ctx)
+            .instrument(span.clone())
+            .await;
+        let elapsed_ms = start.elapsed().as_millis() as u64;
+        span.in_scope(|| match &result {
+            Ok(Some(cred)) => tracing::debug!(
+                outcome = "hit",
+                elapsed_ms,
+                expires_in = ?cred.expires_in,
+                "credential loaded"
+            ),
+            Ok(None) => tracing::debug!(outcome = "miss", elapsed_ms, "credential not found"),
+            Err(err) => tracing::debug!(
+                outcome = "error",
+                elapsed_ms,
+                error = %err,
+                "credential load failed"
+            ),
+        });
+        result
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    {
+        load_checked(source, loader, ctx).await
+    }
+}
+
+/// Load credential and check it, so that misconfigurations are reported
+/// along with the source they come from.
+async fn load_checked(
+    source: &str,
+    loader: &(impl Load<Key = Credential> + ?Sized),
+    ctx: &Context,
+) -> anyhow::Result<Option<Credential>> {
+    let cred = loader.load(ctx).await?;
+    if let Some(cred) = &cred {
+        cred.check()
+            .with_context(|| format!("credential loaded from {source} is invalid"))?;
     }
+    Ok(cred)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::constants::{
-        AWS_ACCESS_KEY_ID, AWS_CONFIG_FILE, AWS_SECRET_ACCESS_KEY, AWS_SHARED_CREDENTIALS_FILE,
+        AWS_ACCESS_KEY_ID, AWS_CONFIG_FILE, AWS_CREDENTIAL_EXPIRATION, AWS_EC2_METADATA_DISABLED,
+        AWS_REGION, AWS_SECRET_ACCESS_KEY, AWS_SHARED_CREDENTIALS_FILE,
     };
-    use reqsign_core::StaticEnv;
+    use http::Method;
+    use reqsign_core::time::parse_rfc3339;
+    use reqsign_core::{MockContext, StaticEnv};
     use reqsign_file_read_tokio::TokioFileRead;
     use reqsign_http_send_reqwest::ReqwestHttpSend;
     use std::collections::HashMap;
@@ -90,6 +415,56 @@ mod tests {
         assert!(x.is_none());
     }
 
+    #[tokio::test]
+    async fn test_default_loader_summary() -> anyhow::Result<()> {
+        let mock =
+            MockContext::new().with_timeout(Method::PUT, "http://169.254.169.254/latest/api/token");
+        let loader = DefaultLoader::new(Arc::new(Config::default()))
+            .with_sources([ChainSource::Env, ChainSource::Imds]);
+
+        let outcomes = loader
+            .try_sources(&mock.context())
+            .await?
+            .expect_err("no credential must be loaded");
+        let summary = summary(&outcomes);
+        assert!(
+            summary.starts_with(
+                "env: not configured, imds_v2: errored (request timed out: mock timeout"
+            ),
+            "{summary}"
+        );
+        assert!(
+            summary.ends_with(
+                "profile: disabled, assume_role: disabled, web_identity: disabled, ecs: disabled"
+            ),
+            "{summary}"
+        );
+
+        // IMDS disabled by env is reported as disabled and not requested.
+        let ctx = mock.context().with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([(AWS_EC2_METADATA_DISABLED.to_string(), "true".to_string())]),
+        });
+        let outcomes = loader
+            .try_sources(&ctx)
+            .await?
+            .expect_err("no credential must be loaded");
+        assert_eq!(outcomes[1], (ChainSource::Imds, Outcome::Disabled));
+        assert_eq!(mock.requests().len(), 1);
+
+        // Other errors are returned as is.
+        let ctx = mock.context().with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([
+                (AWS_ACCESS_KEY_ID.to_string(), "access_key_id".to_string()),
+                (AWS_SECRET_ACCESS_KEY.to_string(), "\"\"".to_string()),
+            ]),
+        });
+        let loader = DefaultLoader::new(Arc::new(Config::default().from_env(&ctx)));
+        loader.load(&ctx).await.expect_err("load must fail");
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_credential_env_loader_with_env() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -114,6 +489,80 @@ mod tests {
         assert_eq!("secret_access_key", x.secret_access_key);
     }
 
+    #[tokio::test]
+    async fn test_credential_env_loader_with_untrimmed_env() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let ctx = Context::new(TokioFileRead, ReqwestHttpSend::default());
+        let ctx = ctx.with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([
+                (AWS_ACCESS_KEY_ID.to_string(), "access_key_id".to_string()),
+                (AWS_SECRET_ACCESS_KEY.to_string(), "secret\n".to_string()),
+            ]),
+        });
+
+        let l = DefaultLoader::new(Arc::new(Config::default().from_env(&ctx)));
+        let x = l.load(&ctx).await.expect("load must succeed");
+
+        let x = x.expect("must load succeed");
+        assert_eq!("access_key_id", x.access_key_id);
+        assert_eq!("secret", x.secret_access_key);
+    }
+
+    #[tokio::test]
+    async fn test_credential_env_loader_with_invalid_env() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let ctx = Context::new(TokioFileRead, ReqwestHttpSend::default());
+        let ctx = ctx.with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([
+                (AWS_ACCESS_KEY_ID.to_string(), "access_key_id".to_string()),
+                (AWS_SECRET_ACCESS_KEY.to_string(), "\"\"".to_string()),
+            ]),
+        });
+
+        let l = DefaultLoader::new(Arc::new(Config::default().from_env(&ctx)));
+        let err = l.load(&ctx).await.expect_err("load must fail");
+        assert!(matches!(
+            err.downcast_ref::<reqsign_core::Error>(),
+            Some(reqsign_core::Error::Config(_))
+        ));
+        assert!(format!("{err:#}").contains("secret_access_key is empty"));
+    }
+
+    #[tokio::test]
+    async fn test_credential_env_loader_with_expiration() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let ctx = Context::new(TokioFileRead, ReqwestHttpSend::default());
+        let ctx = ctx.with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([
+                (AWS_ACCESS_KEY_ID.to_string(), "access_key_id".to_string()),
+                (
+                    AWS_SECRET_ACCESS_KEY.to_string(),
+                    "secret_access_key".to_string(),
+                ),
+                (
+                    AWS_CREDENTIAL_EXPIRATION.to_string(),
+                    "2024-01-01T08:00:00+08:00".to_string(),
+                ),
+            ]),
+        });
+
+        let l = DefaultLoader::new(Arc::new(Config::default().from_env(&ctx)));
+        let x = l.load(&ctx).await.expect("load must succeed");
+
+        let x = x.expect("must load succeed");
+        assert_eq!("access_key_id", x.access_key_id);
+        assert_eq!(
+            x.expires_in,
+            Some(parse_rfc3339("2024-01-01T00:00:00Z").expect("must be valid"))
+        );
+    }
+
     #[tokio::test]
     async fn test_credential_profile_loader_from_config() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -148,6 +597,7 @@ mod tests {
                 .from_env(&ctx)
                 .from_profile(&ctx)
                 .await
+                .expect("load profile must succeed")
                 .into(),
         );
         let x = l.load(&ctx).await.unwrap().unwrap();
@@ -155,6 +605,48 @@ mod tests {
         assert_eq!("config_secret_access_key", x.secret_access_key);
     }
 
+    #[tokio::test]
+    async fn test_credential_profile_loader_with_quoted_value() {
+        let _ = env_logger::builder().is_test(true).try_init();
+
+        let ctx = Context::new(TokioFileRead, ReqwestHttpSend::default());
+        let ctx = ctx.with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([
+                (
+                    AWS_CONFIG_FILE.to_string(),
+                    format!(
+                        "{}/testdata/quoted_config",
+                        env::current_dir()
+                            .expect("current_dir must exist")
+                            .to_string_lossy()
+                    ),
+                ),
+                (
+                    AWS_SHARED_CREDENTIALS_FILE.to_string(),
+                    format!(
+                        "{}/testdata/not_exist",
+                        env::current_dir()
+                            .expect("current_dir must exist")
+                            .to_string_lossy()
+                    ),
+                ),
+            ]),
+        });
+
+        let l = DefaultLoader::new(
+            Config::default()
+                .from_env(&ctx)
+                .from_profile(&ctx)
+                .await
+                .expect("load profile must succeed")
+                .into(),
+        );
+        let x = l.load(&ctx).await.unwrap().unwrap();
+        assert_eq!("quoted_access_key_id", x.access_key_id);
+        assert_eq!("quoted_secret_access_key", x.secret_access_key);
+    }
+
     #[tokio::test]
     async fn test_credential_profile_loader_from_shared() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -189,6 +681,7 @@ mod tests {
                 .from_env(&ctx)
                 .from_profile(&ctx)
                 .await
+                .expect("load profile must succeed")
                 .into(),
         );
         let x = l.load(&ctx).await.unwrap().unwrap();
@@ -231,10 +724,283 @@ mod tests {
                 .from_env(&ctx)
                 .from_profile(&ctx)
                 .await
+                .expect("load profile must succeed")
                 .into(),
         );
         let x = l.load(&ctx).await.expect("load must success").unwrap();
         assert_eq!("shared_access_key_id", x.access_key_id);
         assert_eq!("shared_secret_access_key", x.secret_access_key);
     }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn test_default_loader_tracing() {
+        use std::sync::Mutex;
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        /// Subscriber that records all fields of spans and events as text.
+        #[derive(Default, Clone)]
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+
+        impl Visit for Recorder {
+            fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+                self.0
+                    .lock()
+                    .unwrap()
+                    .push(format!("{}={value:?}", field.name()));
+            }
+        }
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, span: &Attributes<'_>) -> Id {
+                span.record(&mut self.clone());
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, values: &Record<'_>) {
+                values.record(&mut self.clone());
+            }
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, event: &Event<'_>) {
+                event.record(&mut self.clone());
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        let ctx = Context::new(TokioFileRead, ReqwestHttpSend::default()).with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([
+                (AWS_ACCESS_KEY_ID.to_string(), "access_key_id".to_string()),
+                (
+                    AWS_SECRET_ACCESS_KEY.to_string(),
+                    "secret_access_key".to_string(),
+                ),
+            ]),
+        });
+        let l = DefaultLoader::new(Arc::new(Config::default().from_env(&ctx)));
+
+        let recorder = Recorder::default();
+        let _guard = tracing::subscriber::set_default(recorder.clone());
+        l.load(&ctx).await.unwrap().expect("must load succeed");
+
+        let fields = recorder.0.lock().unwrap().join(" ");
+        assert!(fields.contains("source=\"env\""), "{fields}");
+        assert!(fields.contains("outcome=\"hit\""), "{fields}");
+        assert!(!fields.contains("secret_access_key"), "{fields}");
+    }
+
+    #[tokio::test]
+    async fn test_default_loader_with_sources() -> anyhow::Result<()> {
+        let ctx = Context::new(TokioFileRead, ReqwestHttpSend::default());
+        let ctx = ctx.with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([
+                (AWS_ACCESS_KEY_ID.to_string(), "access_key_id".to_string()),
+                (
+                    AWS_SECRET_ACCESS_KEY.to_string(),
+                    "secret_access_key".to_string(),
+                ),
+                (
+                    AWS_SHARED_CREDENTIALS_FILE.to_string(),
+                    format!("{}/testdata/default_credential", env!("CARGO_MANIFEST_DIR")),
+                ),
+                (AWS_EC2_METADATA_DISABLED.to_string(), "true".to_string()),
+            ]),
+        });
+        let config = Arc::new(Config::default().from_env(&ctx));
+
+        // Env goes before profile.
+        let loaded = DefaultLoader::new(config.clone())
+            .with_sources([ChainSource::Env, ChainSource::Profile])
+            .load_with_source(&ctx)
+            .await?
+            .expect("credential must be loaded");
+        assert_eq!(loaded.credential.access_key_id, "access_key_id");
+        assert_eq!(loaded.source, ChainSource::Env);
+
+        let loaded = DefaultLoader::new(config.clone())
+            .with_sources([ChainSource::Profile, ChainSource::Env])
+            .load_with_source(&ctx)
+            .await?
+            .expect("credential must be loaded");
+        assert_eq!(loaded.credential.access_key_id, "shared_access_key_id");
+        assert_eq!(loaded.source, ChainSource::Profile);
+
+        // The default chain doesn't read the profile files if the config is
+        // not built by `from_profile`.
+        let cred = DefaultLoader::new(Arc::new(Config::default()))
+            .load(&ctx)
+            .await?;
+        assert!(cred.is_none());
+
+        // Unless the profile source is listed.
+        let cred = DefaultLoader::new(Arc::new(Config::default()))
+            .with_sources([ChainSource::Profile])
+            .load(&ctx)
+            .await?;
+        assert_eq!(cred.unwrap().access_key_id, "shared_access_key_id");
+
+        let cred = DefaultLoader::new(config)
+            .with_sources([ChainSource::Imds])
+            .load(&ctx)
+            .await?;
+        assert!(cred.is_none());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_default_loader_region() -> anyhow::Result<()> {
+        let envs = HashMap::from_iter([
+            (
+                AWS_CONFIG_FILE.to_string(),
+                format!("{}/testdata/default_config", env!("CARGO_MANIFEST_DIR")),
+            ),
+            (
+                AWS_SHARED_CREDENTIALS_FILE.to_string(),
+                format!("{}/testdata/not_exist", env!("CARGO_MANIFEST_DIR")),
+            ),
+        ]);
+        let ctx = Context::new(TokioFileRead, ReqwestHttpSend::default()).with_env(StaticEnv {
+            home_dir: None,
+            envs: envs.clone(),
+        });
+        let config = Arc::new(Config::default().from_env(&ctx));
+
+        // Only the profile sets the region.
+        let loader = DefaultLoader::new(config.clone());
+        assert_eq!(loader.region(&ctx).await?, None);
+        let loader = loader.with_sources([ChainSource::Env, ChainSource::Profile]);
+        assert_eq!(loader.region(&ctx).await?.as_deref(), Some("test"));
+
+        // Env goes before profile unless the chain says otherwise.
+        let ctx = ctx.with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter(
+                envs.into_iter()
+                    .chain([(AWS_REGION.to_string(), "us-east-1".to_string())]),
+            ),
+        });
+        let config = Arc::new(Config::default().from_env(&ctx));
+        let loader = DefaultLoader::new(config.clone())
+            .with_sources([ChainSource::Env, ChainSource::Profile]);
+        assert_eq!(loader.region(&ctx).await?.as_deref(), Some("us-east-1"));
+        let loader =
+            DefaultLoader::new(config).with_sources([ChainSource::Profile, ChainSource::Env]);
+        assert_eq!(loader.region(&ctx).await?.as_deref(), Some("test"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_default_loader_skip_imds_timeout() -> anyhow::Result<()> {
+        let mock =
+            MockContext::new().with_timeout(Method::PUT, "http://169.254.169.254/latest/api/token");
+        let ctx = mock.context().with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([
+                (AWS_ACCESS_KEY_ID.to_string(), "access_key_id".to_string()),
+                (
+                    AWS_SECRET_ACCESS_KEY.to_string(),
+                    "secret_access_key".to_string(),
+                ),
+            ]),
+        });
+        let config = Arc::new(Config::default().from_env(&ctx));
+
+        let loaded = DefaultLoader::new(config)
+            .with_sources([ChainSource::Imds, ChainSource::Env])
+            .load_with_source(&ctx)
+            .await?
+            .expect("credential must be loaded");
+        assert_eq!(loaded.credential.access_key_id, "access_key_id");
+        assert_eq!(loaded.source, ChainSource::Env);
+        assert_eq!(mock.requests().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_default_loader_skip_unreachable_imds() -> anyhow::Result<()> {
+        // Without a mock response IMDS fails with an untyped error, like a
+        // connection refused outside EC2.
+        let mock = MockContext::new();
+        let ctx = mock.context().with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([
+                (AWS_ACCESS_KEY_ID.to_string(), "access_key_id".to_string()),
+                (
+                    AWS_SECRET_ACCESS_KEY.to_string(),
+                    "secret_access_key".to_string(),
+                ),
+            ]),
+        });
+        let config = Arc::new(Config::default().from_env(&ctx));
+
+        let loaded = DefaultLoader::new(config)
+            .with_sources([ChainSource::Imds, ChainSource::Env])
+            .load_with_source(&ctx)
+            .await?
+            .expect("credential must be loaded");
+        assert_eq!(loaded.credential.access_key_id, "access_key_id");
+        assert_eq!(loaded.source, ChainSource::Env);
+        assert_eq!(mock.requests().len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_default_loader_assume_role_with_source_profile() -> anyhow::Result<()> {
+        let mock = MockContext::new()
+            .with_file(
+                "/mock/config",
+                "[profile base]\n\
+                 aws_access_key_id = base_access_key_id\n\
+                 aws_secret_access_key = base_secret_access_key\n\
+                 \n\
+                 [profile role]\n\
+                 role_arn = arn:aws:iam::123456789012:role/demo\n\
+                 source_profile = base\n",
+            )
+            .with_response(
+                Method::GET,
+                "https://sts.amazonaws.com/?Action=AssumeRole&DurationSeconds=3600&RoleArn=arn%3Aaws%3Aiam%3A%3A123456789012%3Arole%2Fdemo&RoleSessionName=reqsign&Version=2011-06-15",
+                http::StatusCode::OK,
+                r#"<AssumeRoleResponse><AssumeRoleResult><Credentials>
+                <AccessKeyId>assumed_access_key_id</AccessKeyId>
+                <SecretAccessKey>assumed_secret_access_key</SecretAccessKey>
+                <SessionToken>assumed_session_token</SessionToken>
+                <Expiration>2124-01-01T00:00:00Z</Expiration>
+                </Credentials></AssumeRoleResult></AssumeRoleResponse>"#,
+            );
+        let ctx = mock.context();
+        let config = Config {
+            profile: "role".to_string(),
+            config_file: "/mock/config".to_string(),
+            shared_credentials_file: "/mock/credentials".to_string(),
+            ..Default::default()
+        }
+        .from_profile(&ctx)
+        .await?;
+
+        let loader = DefaultLoader::new(Arc::new(config));
+        let loaded = loader
+            .load_with_source(&ctx)
+            .await?
+            .expect("credential must be loaded");
+        assert_eq!(loaded.source, ChainSource::AssumeRole);
+        assert_eq!(loaded.credential.access_key_id, "assumed_access_key_id");
+
+        // The request to STS is signed with the source profile.
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        let auth = requests[0].headers()[http::header::AUTHORIZATION].to_str()?;
+        assert!(auth.contains("Credential=base_access_key_id/"), "{auth}");
+
+        // The assumed credential is cached.
+        loader.load(&ctx).await?.expect("credential must be loaded");
+        assert_eq!(mock.requests().len(), 1);
+        Ok(())
+    }
 }
@@ -0,0 +1,224 @@
+use crate::constants::{
+    AWS_CONTAINER_AUTHORIZATION_TOKEN, AWS_CONTAINER_CREDENTIALS_FULL_URI,
+    AWS_CONTAINER_CREDENTIALS_RELATIVE_URI,
+};
+use crate::Credential;
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::header::AUTHORIZATION;
+use http::Method;
+use reqsign_core::time::parse_rfc3339;
+use reqsign_core::{Context, Error, HttpTimeout, Load};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Endpoint of the ECS container credentials provider.
+const ECS_CONTAINER_ENDPOINT: &str = "http://169.254.170.2";
+
+/// ECSLoader will load credential from the ECS container credentials provider.
+///
+/// The endpoint is resolved from:
+///
+/// - env value: [`AWS_CONTAINER_CREDENTIALS_RELATIVE_URI`], relative to `http://169.254.170.2`
+/// - env value: [`AWS_CONTAINER_CREDENTIALS_FULL_URI`]
+///
+/// [`AWS_CONTAINER_AUTHORIZATION_TOKEN`] will be sent as `authorization` if set.
+///
+/// - [IAM roles for tasks](https://docs.aws.amazon.com/AmazonECS/latest/developerguide/task-iam-roles.html)
+#[derive(Debug)]
+pub struct ECSLoader {
+    timeout: HttpTimeout,
+}
+
+impl Default for ECSLoader {
+    fn default() -> Self {
+        Self {
+            timeout: HttpTimeout::new(Duration::from_secs(1), Duration::from_secs(1)),
+        }
+    }
+}
+
+impl ECSLoader {
+    /// Create a new `ECSLoader` instance.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the connect and read timeouts for requests to the credentials
+    /// provider.
+    ///
+    /// Both default to 1s so that the credential chain can move on quickly
+    /// outside ECS. `HttpSend` implementations without a per-request connect
+    /// timeout, like `ReqwestHttpSend`, apply their sum as the total timeout,
+    /// so every request is given 2s by default.
+    pub fn with_timeout(mut self, connect: Duration, read: Duration) -> Self {
+        self.timeout = HttpTimeout::new(connect, read);
+        self
+    }
+}
+
+#[async_trait]
+impl Load for ECSLoader {
+    type Key = Credential;
+
+    async fn load(&self, ctx: &Context) -> Result<Option<Self::Key>> {
+        let url = if let Some(uri) = ctx.env_var(AWS_CONTAINER_CREDENTIALS_RELATIVE_URI) {
+            format!("{ECS_CONTAINER_ENDPOINT}{uri}")
+        } else if let Some(uri) = ctx.env_var(AWS_CONTAINER_CREDENTIALS_FULL_URI) {
+            uri
+        } else {
+            return Ok(None);
+        };
+
+        let mut req = http::Request::builder()
+            .uri(url)
+            .method(Method::GET)
+            .extension(self.timeout);
+        if let Some(token) = ctx.env_var(AWS_CONTAINER_AUTHORIZATION_TOKEN) {
+            req = req.header(AUTHORIZATION, token);
+        }
+        let req = req.body(Bytes::new())?;
+
+        let resp = ctx.http_send_as_string(req).await?;
+        if resp.status() != http::StatusCode::OK {
+            return Err(Error::Service {
+                status: resp.status(),
+                code: None,
+                message: resp.into_body(),
+                request_id: None,
+            }
+            .into());
+        }
+
+        let resp: EcsContainerCredentials = serde_json::from_str(resp.body())
+            .map_err(|e| Error::Parse(format!("invalid ECS credentials response: {e}")))?;
+
+        let cred = Credential {
+            access_key_id: resp.access_key_id,
+            secret_access_key: resp.secret_access_key,
+            session_token: Some(resp.token),
+            expires_in: Some(parse_rfc3339(&resp.expiration)?),
+        };
+
+        Ok(Some(cred))
+    }
+}
+
+#[derive(Default, Debug, Deserialize)]
+#[serde(default, rename_all = "PascalCase")]
+struct EcsContainerCredentials {
+    access_key_id: String,
+    secret_access_key: String,
+    token: String,
+    expiration: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::{Method, StatusCode};
+    use reqsign_core::{MockContext, StaticEnv};
+    use std::collections::HashMap;
+
+    const ECS_RESPONSE: &str = r#"{"AccessKeyId":"access_key_id","SecretAccessKey":"secret_access_key","Token":"session_token","Expiration":"2024-01-01T00:00:00Z"}"#;
+
+    #[tokio::test]
+    async fn test_ecs_loader() -> Result<()> {
+        let cases = vec![
+            ("without env", vec![], None),
+            (
+                "relative uri",
+                vec![(
+                    AWS_CONTAINER_CREDENTIALS_RELATIVE_URI,
+                    "/v2/credentials/demo",
+                )],
+                Some(("http://169.254.170.2/v2/credentials/demo", None)),
+            ),
+            (
+                "full uri with token",
+                vec![
+                    (
+                        AWS_CONTAINER_CREDENTIALS_FULL_URI,
+                        "http://127.0.0.1:8080/creds",
+                    ),
+                    (AWS_CONTAINER_AUTHORIZATION_TOKEN, "token"),
+                ],
+                Some(("http://127.0.0.1:8080/creds", Some("token"))),
+            ),
+        ];
+
+        for (name, envs, expected) in cases {
+            let mock = MockContext::new()
+                .with_response(
+                    Method::GET,
+                    "http://169.254.170.2/v2/credentials/demo",
+                    StatusCode::OK,
+                    ECS_RESPONSE,
+                )
+                .with_response(
+                    Method::GET,
+                    "http://127.0.0.1:8080/creds",
+                    StatusCode::OK,
+                    ECS_RESPONSE,
+                );
+            let ctx = mock.context().with_env(StaticEnv {
+                home_dir: None,
+                envs: HashMap::from_iter(envs.into_iter().map(|(k, v)| (k.into(), v.into()))),
+            });
+
+            let cred = ECSLoader::new().load(&ctx).await?;
+            let requests = mock.requests();
+            match expected {
+                None => {
+                    assert!(cred.is_none(), "case {name}");
+                    assert!(requests.is_empty(), "case {name}");
+                }
+                Some((uri, token)) => {
+                    let cred = cred.expect("credential must be loaded");
+                    assert_eq!(cred.access_key_id, "access_key_id", "case {name}");
+                    assert_eq!(cred.session_token.as_deref(), Some("session_token"));
+                    assert_eq!(requests.len(), 1, "case {name}");
+                    assert_eq!(requests[0].uri(), uri, "case {name}");
+                    assert_eq!(
+                        requests[0]
+                            .headers()
+                            .get(AUTHORIZATION)
+                            .map(|v| v.to_str().unwrap()),
+                        token,
+                        "case {name}"
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_ecs_loader_service_error() -> Result<()> {
+        let mock = MockContext::new().with_response(
+            Method::GET,
+            "http://169.254.170.2/v2/credentials/demo",
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "internal error",
+        );
+        let ctx = mock.context().with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([(
+                AWS_CONTAINER_CREDENTIALS_RELATIVE_URI.to_string(),
+                "/v2/credentials/demo".to_string(),
+            )]),
+        });
+
+        let err = ECSLoader::new()
+            .load(&ctx)
+            .await
+            .expect_err("load must fail");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Service { status: StatusCode::INTERNAL_SERVER_ERROR, message, .. })
+                if message == "internal error"
+        ));
+        Ok(())
+    }
+}
@@ -1,58 +1,238 @@
+use crate::constants::AWS_EC2_METADATA_DISABLED;
+use crate::load::utils::{http_send, RetryPolicy};
 use crate::{Config, Credential};
-use anyhow::{anyhow, Result};
+use anyhow::Result;
 use async_trait::async_trait;
 use bytes::Bytes;
 use http::header::CONTENT_LENGTH;
-use http::Method;
-use reqsign_core::time::{now, parse_rfc3339, DateTime};
-use reqsign_core::{Context, Load};
+use http::{HeaderMap, Method, Uri};
+use reqsign_core::time::{parse_rfc3339, DateTime};
+use reqsign_core::{Context, Error, HttpTimeout, Load};
 use serde::Deserialize;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+/// The max time spent sleeping between retries of a single IMDS request, so
+/// callers starting at boot time are not blocked for long.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+/// IMDSv2Loader loads credentials from the EC2 instance metadata service.
+///
+/// Only IMDSv2 is supported: every metadata request carries the session
+/// token, and loading fails if the token can't be fetched. It never falls
+/// back to IMDSv1, so it works with `http-tokens: required` instances.
 #[derive(Debug, Clone)]
 pub struct IMDSv2Loader {
     config: Arc<Config>,
     token: Arc<Mutex<(String, DateTime)>>,
+    retry: RetryPolicy,
+    timeout: HttpTimeout,
+    token_ttl: Duration,
+    expiry_margin: Duration,
+    role_name: Option<String>,
+    endpoint: String,
+    headers: HeaderMap,
 }
 
 impl IMDSv2Loader {
     /// Create a new `IMDSv2Loader` instance.
     pub fn new(cfg: Arc<Config>) -> Self {
         Self {
+            retry: RetryPolicy::from_config(&cfg).with_total_delay(MAX_RETRY_DELAY),
             config: cfg,
             token: Arc::new(Mutex::new((String::new(), DateTime::default()))),
+            timeout: HttpTimeout::new(Duration::from_secs(1), Duration::from_secs(1)),
+            // 21600s (6h) is recommended by AWS.
+            token_ttl: Duration::from_secs(21600),
+            expiry_margin: Duration::from_secs(300),
+            role_name: None,
+            endpoint: "http://169.254.169.254".to_string(),
+            headers: HeaderMap::new(),
+        }
+    }
+
+    /// Set the retry policy for requests to IMDS.
+    ///
+    /// IMDS could return 503 while the instance is starting, and 404 before
+    /// the instance profile is attached, so failed requests are retried 3
+    /// times with jitter by default. Set `max_times` to `0` to disable
+    /// retries. The total delay between retries is capped at 5s.
+    ///
+    /// This overrides `max_attempts` from config.
+    pub fn with_retry(mut self, max_times: usize, jitter: bool) -> Self {
+        self.retry = RetryPolicy::new(max_times, jitter).with_total_delay(MAX_RETRY_DELAY);
+        self
+    }
+
+    /// Set the connect and read timeouts for requests to IMDS.
+    ///
+    /// Both default to 1s so that the credential chain can move on quickly
+    /// outside EC2, timeouts are not retried. `HttpSend` implementations
+    /// without a per-request connect timeout, like `ReqwestHttpSend`, apply
+    /// their sum as the total timeout, so every request is given 2s by
+    /// default.
+    pub fn with_timeout(mut self, connect: Duration, read: Duration) -> Self {
+        self.timeout = HttpTimeout::new(connect, read);
+        self
+    }
+
+    /// Set the TTL of IMDS session tokens.
+    ///
+    /// Must be between 1s and 21600s (6h), defaults to 21600s. Tokens are
+    /// refreshed once 90% of the TTL has elapsed.
+    pub fn with_token_ttl(mut self, ttl: Duration) -> Self {
+        self.token_ttl = ttl;
+        self
+    }
+
+    /// Set the safety margin subtracted from the expiration reported by IMDS,
+    /// defaults to 5 minutes.
+    ///
+    /// IMDS could serve credentials that are about to rotate, the margin
+    /// makes sure they are refreshed before hard expiry. It's capped at half
+    /// of the remaining lifetime so short credentials are not treated as
+    /// expired right after issued.
+    pub fn with_expiry_margin(mut self, margin: Duration) -> Self {
+        self.expiry_margin = margin;
+        self
+    }
+
+    /// Set the name of the IAM role to fetch credentials for.
+    ///
+    /// The role listing request is skipped if set. Otherwise, the first role
+    /// listed by IMDS is used.
+    pub fn with_role_name(mut self, name: &str) -> Self {
+        self.role_name = Some(name.to_string());
+        self
+    }
+
+    /// Set the base URL of IMDS, defaults to `http://169.254.169.254`.
+    ///
+    /// This is useful for sidecars that relay an IMDS compatible endpoint on
+    /// another host and port, like `http://127.0.0.1:8080/imds`. The URL must
+    /// be http or https with a host, it's checked on load.
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = endpoint.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Set the extra headers sent with all requests to IMDS, like the auth
+    /// header required by a credential relay.
+    ///
+    /// The values are marked as sensitive so they are not leaked in logs.
+    pub fn with_headers(mut self, headers: HeaderMap) -> Self {
+        self.headers = headers;
+        for value in self.headers.values_mut() {
+            value.set_sensitive(true);
         }
+        self
     }
 }
 
 impl IMDSv2Loader {
+    /// Build the url of `path` under the endpoint.
+    fn url(&self, path: &str) -> Result<String> {
+        let endpoint: Uri = self
+            .endpoint
+            .parse()
+            .map_err(|e| Error::Config(format!("invalid IMDS endpoint {}: {e}", self.endpoint)))?;
+        if !matches!(endpoint.scheme_str(), Some("http" | "https")) || endpoint.host().is_none() {
+            return Err(Error::Config(format!(
+                "IMDS endpoint must be an http or https url with host, got {}",
+                self.endpoint
+            ))
+            .into());
+        }
+        Ok(format!("{}{path}", self.endpoint))
+    }
+
+    /// Build a request to IMDS with the timeout and extra headers.
+    fn request(&self, method: Method, path: &str) -> Result<http::request::Builder> {
+        let mut req = http::Request::builder()
+            .extension(self.timeout)
+            .uri(self.url(path)?)
+            .method(method);
+        if let Some(headers) = req.headers_mut() {
+            headers.extend(self.headers.clone());
+        }
+        Ok(req)
+    }
+
+    /// Send the request to IMDS.
+    ///
+    /// Transport failures like connection refused or network unreachable
+    /// mean we are not running on EC2, they are reported as
+    /// [`Error::CredentialNotFound`] so that the credential chain moves on.
+    async fn send(
+        &self,
+        ctx: &Context,
+        req: http::Request<Bytes>,
+    ) -> Result<http::Response<String>> {
+        http_send(ctx, req)
+            .await
+            .map_err(|err| match err.downcast_ref::<Error>() {
+                Some(Error::Http(msg)) => Error::CredentialNotFound(format!(
+                    "IMDS is unreachable at {}: {msg}",
+                    self.endpoint
+                ))
+                .into(),
+                _ => err,
+            })
+    }
+
+    /// Load the IMDS session token, reusing the cached one until it's about
+    /// to expire.
     async fn load_ec2_metadata_token(&self, ctx: &Context) -> Result<String> {
         {
             let (token, expires_in) = self.token.lock().expect("lock poisoned").clone();
-            if expires_in > now() {
+            if expires_in > ctx.now() {
                 return Ok(token);
             }
         }
 
-        let url = "http://169.254.169.254/latest/api/token";
-        let req = http::Request::builder()
-            .uri(url)
-            .method(Method::PUT)
-            .header(CONTENT_LENGTH, "0")
-            // 21600s (6h) is recommended by AWS.
-            .header("x-aws-ec2-metadata-token-ttl-seconds", "21600")
-            .body(Bytes::new())?;
-        let resp = ctx.http_send_as_string(req).await?;
-        if resp.status() != http::StatusCode::OK {
-            return Err(anyhow!(
-                "request to AWS EC2 Metadata Services failed: {}",
-                resp.body()
-            ));
-        }
-        let ec2_token = resp.into_body();
-        // Set expires_in to 10 minutes to enforce re-read.
-        let expires_in = now() + chrono::TimeDelta::try_seconds(21600).expect("in bounds")
-            - chrono::TimeDelta::try_seconds(600).expect("in bounds");
+        let ttl = self.token_ttl.as_secs();
+        if !(1..=21600).contains(&ttl) {
+            return Err(Error::Config(format!(
+                "IMDS token ttl must be between 1s and 21600s, got {ttl}s"
+            ))
+            .into());
+        }
+
+        // 401 and 403 mean IMDSv2 is disabled or the hop limit is reached,
+        // only 5xx are retried.
+        let ec2_token = self
+            .retry
+            .run_when(
+                || async {
+                    let req = self
+                        .request(Method::PUT, "/latest/api/token")?
+                        .header(CONTENT_LENGTH, "0")
+                        .header("x-aws-ec2-metadata-token-ttl-seconds", ttl.to_string())
+                        .body(Bytes::new())?;
+                    let resp = self.send(ctx, req).await?;
+                    if resp.status() != http::StatusCode::OK {
+                        return Err(imds_error(resp).into());
+                    }
+                    Ok(resp.into_body())
+                },
+                |err| is_server_error(err, false),
+            )
+            .await
+            .map_err(|err| match err.downcast_ref::<Error>() {
+                // The token response is dropped after one hop by default,
+                // so it never reaches containers with their own network.
+                Some(Error::Timeout(msg)) => Error::Timeout(format!(
+                    "{msg}. Hint: if running inside a container, the instance's \
+                     IMDS hop limit may need to be raised to 2 via \
+                     `aws ec2 modify-instance-metadata-options --http-put-response-hop-limit 2`"
+                ))
+                .into(),
+                _ => err,
+            })?;
+        // Cache the token for its full ttl minus a refresh margin of 10%, so
+        // that it's refetched before IMDS starts to reject it.
+        let expires_in = ctx.now()
+            + chrono::TimeDelta::from_std(self.token_ttl - self.token_ttl / 10).expect("in bounds");
 
         {
             *self.token.lock().expect("lock poisoned") = (ec2_token.clone(), expires_in);
@@ -60,6 +240,95 @@ impl IMDSv2Loader {
 
         Ok(ec2_token)
     }
+
+    /// Get the metadata at `path`, retrying 404 and 5xx responses.
+    ///
+    /// Returns `None` if IMDS still returns 404 after all retries.
+    async fn get_metadata(&self, ctx: &Context, token: &str, path: &str) -> Result<Option<String>> {
+        let resp = self
+            .retry
+            .run_when(
+                || async {
+                    let req = self
+                        .request(Method::GET, path)?
+                        .header("x-aws-ec2-metadata-token", token)
+                        .body(Bytes::new())?;
+                    let resp = self.send(ctx, req).await?;
+                    if resp.status() != http::StatusCode::OK {
+                        return Err(imds_error(resp).into());
+                    }
+                    Ok(resp.into_body())
+                },
+                // The role could be listed before its credentials are ready,
+                // for example, right after the instance profile is attached.
+                |err| is_server_error(err, true),
+            )
+            .await;
+        match resp {
+            Err(err)
+                if matches!(
+                    err.downcast_ref::<Error>(),
+                    Some(Error::Service {
+                        status: http::StatusCode::NOT_FOUND,
+                        ..
+                    })
+                ) =>
+            {
+                Ok(None)
+            }
+            resp => Ok(Some(resp?)),
+        }
+    }
+
+    /// Whether IMDS is disabled by `ec2_metadata_disabled`. The env is
+    /// checked via ctx as well so it applies to configs not built by
+    /// `from_env`.
+    pub(crate) fn is_disabled(&self, ctx: &Context) -> bool {
+        self.config.ec2_metadata_disabled
+            || ctx.env_var(AWS_EC2_METADATA_DISABLED).as_deref() == Some("true")
+    }
+
+    /// Load the name of the IAM role attached to this instance.
+    async fn load_role_name(&self, ctx: &Context, token: &str) -> Result<String> {
+        // List all credentials that node has.
+        let content = self
+            .get_metadata(ctx, token, "/latest/meta-data/iam/security-credentials/")
+            .await?
+            .unwrap_or_default();
+
+        // Instance profiles contain only one role for now, take the first
+        // non-empty entry in case more are listed.
+        let profile_name = content
+            .lines()
+            .map(str::trim)
+            .find(|v| !v.is_empty())
+            .ok_or_else(|| {
+                Error::CredentialNotFound("no IAM role is attached to this instance".to_string())
+            })?
+            .to_string();
+        Ok(profile_name)
+    }
+}
+
+/// Whether the error is a 5xx response of IMDS, or a 404 if `not_found` is
+/// set.
+fn is_server_error(err: &anyhow::Error, not_found: bool) -> bool {
+    match err.downcast_ref::<Error>() {
+        Some(Error::Service { status, .. }) => {
+            status.is_server_error() || (not_found && *status == http::StatusCode::NOT_FOUND)
+        }
+        _ => false,
+    }
+}
+
+/// Build the error for a failed IMDS response.
+fn imds_error(resp: http::Response<String>) -> Error {
+    Error::Service {
+        status: resp.status(),
+        code: None,
+        message: resp.into_body(),
+        request_id: None,
+    }
 }
 
 #[async_trait]
@@ -67,73 +336,59 @@ impl Load for IMDSv2Loader {
     type Key = Credential;
 
     async fn load(&self, ctx: &Context) -> Result<Option<Self::Key>> {
-        // If ec2_metadata_disabled is set, return None.
-        if self.config.ec2_metadata_disabled {
+        if self.is_disabled(ctx) {
             return Ok(None);
         }
 
         let token = self.load_ec2_metadata_token(ctx).await?;
 
-        // List all credentials that node has.
-        let url = "http://169.254.169.254/latest/meta-data/iam/security-credentials/";
-        let req = http::Request::builder()
-            .uri(url)
-            .method(Method::GET)
-            // 21600s (6h) is recommended by AWS.
-            .header("x-aws-ec2-metadata-token", &token)
-            .body(Bytes::new())?;
-        let resp = ctx.http_send_as_string(req).await?;
-        if resp.status() != http::StatusCode::OK {
-            return Err(anyhow!(
-                "request to AWS EC2 Metadata Services failed: {}",
-                resp.body()
-            ));
-        }
-
-        let profile_name = resp.into_body();
+        let profile_name = match &self.role_name {
+            Some(name) => name.clone(),
+            None => self.load_role_name(ctx, &token).await?,
+        };
 
         // Get the credentials via role_name.
-        let url = format!(
-            "http://169.254.169.254/latest/meta-data/iam/security-credentials/{profile_name}"
-        );
-        let req = http::Request::builder()
-            .uri(url)
-            .method(Method::GET)
-            // 21600s (6h) is recommended by AWS.
-            .header("x-aws-ec2-metadata-token", &token)
-            .body(Bytes::new())?;
-
-        let resp = ctx.http_send_as_string(req).await?;
-        if resp.status() != http::StatusCode::OK {
-            return Err(anyhow!(
-                "request to AWS EC2 Metadata Services failed: {}",
-                resp.body()
-            ));
-        }
-
-        let content = resp.into_body();
-        let resp: Ec2MetadataIamSecurityCredentials = serde_json::from_str(&content)?;
+        let content = self
+            .get_metadata(
+                ctx,
+                &token,
+                &format!("/latest/meta-data/iam/security-credentials/{profile_name}"),
+            )
+            .await?
+            .ok_or_else(|| {
+                Error::CredentialNotFound(format!(
+                    "IAM role {profile_name} is not attached to this instance"
+                ))
+            })?;
+        let resp: Ec2MetadataIamSecurityCredentials = serde_json::from_str(&content)
+            .map_err(|e| Error::Parse(format!("invalid IMDS credentials response: {e}")))?;
         if resp.code == "AssumeRoleUnauthorizedAccess" {
-            return Err(anyhow!(
+            return Err(Error::Config(format!(
                 "Incorrect IMDS/IAM configuration: [{}] {}. \
                         Hint: Does this role have a trust relationship with EC2?",
-                resp.code,
-                resp.message
-            ));
+                resp.code, resp.message
+            ))
+            .into());
         }
         if resp.code != "Success" {
-            return Err(anyhow!(
+            return Err(Error::CredentialNotFound(format!(
                 "Error retrieving credentials from IMDS: {} {}",
-                resp.code,
-                resp.message
-            ));
+                resp.code, resp.message
+            ))
+            .into());
         }
 
+        let expiration = parse_rfc3339(&resp.expiration)?;
+        let margin = chrono::TimeDelta::from_std(self.expiry_margin)
+            .unwrap_or(chrono::TimeDelta::MAX)
+            .min((expiration - ctx.now()) / 2)
+            .max(chrono::TimeDelta::zero());
+
         let cred = Credential {
             access_key_id: resp.access_key_id,
             secret_access_key: resp.secret_access_key,
             session_token: Some(resp.token),
-            expires_in: Some(parse_rfc3339(&resp.expiration)?),
+            expires_in: Some(expiration - margin),
         };
 
         Ok(Some(cred))
@@ -151,3 +406,525 @@ struct Ec2MetadataIamSecurityCredentials {
     code: String,
     message: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::StatusCode;
+    use reqsign_core::{MockContext, StaticEnv};
+    use std::collections::HashMap;
+
+    /// Mock IMDS to fail the first `token_failures` token requests and
+    /// `credential_failures` credentials requests.
+    fn mock_imds(token_failures: usize, credential_failures: usize) -> MockContext {
+        let mut mock = MockContext::new();
+        for _ in 0..token_failures {
+            mock = mock.with_response(
+                Method::PUT,
+                "http://169.254.169.254/latest/api/token",
+                StatusCode::SERVICE_UNAVAILABLE,
+                "",
+            );
+        }
+        for _ in 0..credential_failures {
+            mock = mock.with_response(
+                Method::GET,
+                "http://169.254.169.254/latest/meta-data/iam/security-credentials/demo",
+                StatusCode::SERVICE_UNAVAILABLE,
+                "",
+            );
+        }
+        mock.with_response(
+            Method::PUT,
+            "http://169.254.169.254/latest/api/token",
+            StatusCode::OK,
+            "token",
+        )
+        .with_response(
+            Method::GET,
+            "http://169.254.169.254/latest/meta-data/iam/security-credentials/",
+            StatusCode::OK,
+            "demo",
+        )
+        .with_response(
+            Method::GET,
+            "http://169.254.169.254/latest/meta-data/iam/security-credentials/demo",
+            StatusCode::OK,
+            r#"{"Code":"Success","AccessKeyId":"access_key_id","SecretAccessKey":"secret_access_key","Token":"session_token","Expiration":"2024-01-01T00:00:00Z"}"#,
+        )
+        .with_response(
+            Method::GET,
+            "http://169.254.169.254/latest/meta-data/iam/security-credentials/missing",
+            StatusCode::NOT_FOUND,
+            "",
+        )
+    }
+
+    /// Count the token requests sent to the mocked IMDS.
+    fn token_requests(mock: &MockContext) -> usize {
+        mock.requests()
+            .iter()
+            .filter(|req| req.method() == Method::PUT)
+            .count()
+    }
+
+    #[tokio::test]
+    async fn test_imds_loader_with_mock_context() -> Result<()> {
+        let mock = MockContext::new()
+            .with_response(
+                Method::PUT,
+                "http://169.254.169.254/latest/api/token",
+                StatusCode::OK,
+                "token",
+            )
+            .with_response(
+                Method::GET,
+                "http://169.254.169.254/latest/meta-data/iam/security-credentials/",
+                StatusCode::OK,
+                "demo",
+            )
+            .with_response(
+                Method::GET,
+                "http://169.254.169.254/latest/meta-data/iam/security-credentials/demo",
+                StatusCode::OK,
+                r#"{"Code":"Success","AccessKeyId":"access_key_id","SecretAccessKey":"secret_access_key","Token":"session_token","Expiration":"2024-01-01T00:00:00Z"}"#,
+            );
+        let ctx = mock.context();
+        let loader = IMDSv2Loader::new(Arc::new(Config::default()));
+
+        let cred = loader.load(&ctx).await?.expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "access_key_id");
+        assert_eq!(cred.secret_access_key, "secret_access_key");
+        assert_eq!(cred.session_token.as_deref(), Some("session_token"));
+        assert_eq!(
+            cred.expires_in,
+            Some(parse_rfc3339("2024-01-01T00:00:00Z")?)
+        );
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].method(), Method::PUT);
+        assert_eq!(
+            requests[0].headers()["x-aws-ec2-metadata-token-ttl-seconds"],
+            "21600"
+        );
+        for req in &requests[1..] {
+            assert_eq!(req.headers()["x-aws-ec2-metadata-token"], "token");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_imds_loader_with_endpoint_and_headers() -> Result<()> {
+        let mock = MockContext::new()
+            .with_response(
+                Method::PUT,
+                "http://127.0.0.1:8080/imds/latest/api/token",
+                StatusCode::OK,
+                "token",
+            )
+            .with_response(
+                Method::GET,
+                "http://127.0.0.1:8080/imds/latest/meta-data/iam/security-credentials/",
+                StatusCode::OK,
+                "demo",
+            )
+            .with_response(
+                Method::GET,
+                "http://127.0.0.1:8080/imds/latest/meta-data/iam/security-credentials/demo",
+                StatusCode::OK,
+                r#"{"Code":"Success","AccessKeyId":"access_key_id","SecretAccessKey":"secret_access_key","Token":"session_token","Expiration":"2024-01-01T00:00:00Z"}"#,
+            );
+        let ctx = mock.context();
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer relay-token".parse()?);
+        let loader = IMDSv2Loader::new(Arc::new(Config::default()))
+            .with_endpoint("http://127.0.0.1:8080/imds/")
+            .with_headers(headers);
+
+        let cred = loader.load(&ctx).await?.expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "access_key_id");
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 3);
+        for req in requests {
+            assert_eq!(req.headers()["authorization"], "Bearer relay-token");
+        }
+        assert!(!format!("{loader:?}").contains("relay-token"));
+
+        for endpoint in ["169.254.169.254", "ftp://127.0.0.1", "http://"] {
+            let err = IMDSv2Loader::new(Arc::new(Config::default()))
+                .with_endpoint(endpoint)
+                .load(&ctx)
+                .await
+                .expect_err("endpoint must be invalid");
+            assert!(
+                matches!(err.downcast_ref::<Error>(), Some(Error::Config(_))),
+                "{endpoint}: {err:?}"
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_imds_loader_disabled_by_env() -> Result<()> {
+        let mock = MockContext::new();
+        let ctx = mock.context().with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from([(AWS_EC2_METADATA_DISABLED.to_string(), "true".to_string())]),
+        });
+        let loader = IMDSv2Loader::new(Arc::new(Config::default()));
+
+        assert!(loader.load(&ctx).await?.is_none());
+        assert!(mock.requests().is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_imds_loader_with_token_ttl() -> Result<()> {
+        let now = parse_rfc3339("2024-01-01T00:00:00Z")?;
+        let mock = MockContext::new()
+            .with_response(
+                Method::PUT,
+                "http://169.254.169.254/latest/api/token",
+                StatusCode::OK,
+                "token",
+            )
+            .with_response(
+                Method::GET,
+                "http://169.254.169.254/latest/meta-data/iam/security-credentials/",
+                StatusCode::OK,
+                "demo",
+            )
+            .with_response(
+                Method::GET,
+                "http://169.254.169.254/latest/meta-data/iam/security-credentials/demo",
+                StatusCode::OK,
+                r#"{"Code":"Success","AccessKeyId":"access_key_id","SecretAccessKey":"secret_access_key","Token":"session_token","Expiration":"2024-01-01T06:00:00Z"}"#,
+            );
+        let ctx = mock.context().with_clock(reqsign_core::FixedClock(now));
+        let loader =
+            IMDSv2Loader::new(Arc::new(Config::default())).with_token_ttl(Duration::from_secs(600));
+
+        loader.load(&ctx).await?.expect("credential must be loaded");
+        let requests = mock.requests();
+        assert_eq!(
+            requests[0].headers()["x-aws-ec2-metadata-token-ttl-seconds"],
+            "600"
+        );
+        // Token is reused until 90% of the ttl has elapsed.
+        let (_, expires_in) = loader.token.lock().unwrap().clone();
+        assert_eq!(expires_in, parse_rfc3339("2024-01-01T00:09:00Z")?);
+
+        let err = IMDSv2Loader::new(Arc::new(Config::default()))
+            .with_token_ttl(Duration::from_secs(21601))
+            .load(&ctx)
+            .await
+            .expect_err("ttl is too large");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Config(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_imds_loader_with_multiple_roles() -> Result<()> {
+        let credentials = |key: &str| {
+            format!(
+                r#"{{"Code":"Success","AccessKeyId":"{key}","SecretAccessKey":"secret_access_key","Token":"session_token","Expiration":"2024-01-01T00:00:00Z"}}"#
+            )
+        };
+        let mock = MockContext::new()
+            .with_response(
+                Method::PUT,
+                "http://169.254.169.254/latest/api/token",
+                StatusCode::OK,
+                "token",
+            )
+            .with_response(
+                Method::GET,
+                "http://169.254.169.254/latest/meta-data/iam/security-credentials/",
+                StatusCode::OK,
+                "\nfirst\nsecond\n",
+            )
+            .with_response(
+                Method::GET,
+                "http://169.254.169.254/latest/meta-data/iam/security-credentials/first",
+                StatusCode::OK,
+                credentials("first_access_key_id"),
+            )
+            .with_response(
+                Method::GET,
+                "http://169.254.169.254/latest/meta-data/iam/security-credentials/second",
+                StatusCode::OK,
+                credentials("second_access_key_id"),
+            );
+        let ctx = mock.context();
+
+        let loader = IMDSv2Loader::new(Arc::new(Config::default()));
+        let cred = loader.load(&ctx).await?.expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "first_access_key_id");
+        assert_eq!(mock.requests().len(), 3);
+
+        let loader = IMDSv2Loader::new(Arc::new(Config::default())).with_role_name("second");
+        let cred = loader.load(&ctx).await?.expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "second_access_key_id");
+        // The listing request is skipped.
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 5);
+        assert_eq!(
+            requests[4].uri(),
+            "http://169.254.169.254/latest/meta-data/iam/security-credentials/second"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_imds_loader_expiry_margin() -> Result<()> {
+        let now = parse_rfc3339("2024-01-01T00:00:00Z")?;
+        let cases = [
+            // The default margin is 5 minutes.
+            (None, "2024-01-01T06:00:00Z", "2024-01-01T05:55:00Z"),
+            (
+                Some(Duration::from_secs(60)),
+                "2024-01-01T06:00:00Z",
+                "2024-01-01T05:59:00Z",
+            ),
+            // Short credentials keep half of the remaining lifetime.
+            (None, "2024-01-01T00:04:00Z", "2024-01-01T00:02:00Z"),
+        ];
+        for (margin, expiration, expected) in cases {
+            let mock = MockContext::new()
+                .with_response(
+                    Method::PUT,
+                    "http://169.254.169.254/latest/api/token",
+                    StatusCode::OK,
+                    "token",
+                )
+                .with_response(
+                    Method::GET,
+                    "http://169.254.169.254/latest/meta-data/iam/security-credentials/demo",
+                    StatusCode::OK,
+                    format!(
+                        r#"{{"Code":"Success","AccessKeyId":"access_key_id","SecretAccessKey":"secret_access_key","Token":"session_token","Expiration":"{expiration}"}}"#
+                    ),
+                );
+            let ctx = mock.context().with_clock(reqsign_core::FixedClock(now));
+            let mut loader = IMDSv2Loader::new(Arc::new(Config::default())).with_role_name("demo");
+            if let Some(margin) = margin {
+                loader = loader.with_expiry_margin(margin);
+            }
+
+            let cred = loader.load(&ctx).await?.expect("credential must be loaded");
+            assert_eq!(
+                cred.expires_in,
+                Some(parse_rfc3339(expected)?),
+                "{expiration}"
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_imds_loader_token_cache() -> Result<()> {
+        let now = parse_rfc3339("2024-01-01T00:00:00Z")?;
+        let mock = MockContext::new()
+            .with_response(
+                Method::PUT,
+                "http://169.254.169.254/latest/api/token",
+                StatusCode::OK,
+                "token",
+            )
+            .with_response(
+                Method::GET,
+                "http://169.254.169.254/latest/meta-data/iam/security-credentials/",
+                StatusCode::OK,
+                "demo",
+            )
+            .with_response(
+                Method::GET,
+                "http://169.254.169.254/latest/meta-data/iam/security-credentials/demo",
+                StatusCode::OK,
+                r#"{"Code":"Success","AccessKeyId":"access_key_id","SecretAccessKey":"secret_access_key","Token":"session_token","Expiration":"2024-01-02T00:00:00Z"}"#,
+            );
+        let at = |offset: i64| {
+            mock.context().with_clock(reqsign_core::FixedClock(
+                now + chrono::TimeDelta::try_minutes(offset).unwrap(),
+            ))
+        };
+        let loader = IMDSv2Loader::new(Arc::new(Config::default()));
+
+        loader.load(&at(0)).await?;
+        assert_eq!(token_requests(&mock), 1);
+        // The default ttl is 6h, so the token is reused for 5h24m.
+        loader.load(&at(5 * 60)).await?;
+        assert_eq!(token_requests(&mock), 1);
+        loader.load(&at(5 * 60 + 25)).await?;
+        assert_eq!(token_requests(&mock), 2);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_imds_loader_retry_token() -> Result<()> {
+        let mock = mock_imds(2, 0);
+        let ctx = mock.context();
+        let loader = IMDSv2Loader::new(Arc::new(Config::default())).with_retry(2, false);
+
+        let cred = loader.load(&ctx).await?.expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "access_key_id");
+        assert_eq!(token_requests(&mock), 3);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_imds_loader_retry_credentials() -> Result<()> {
+        let mock = mock_imds(0, 1);
+        let ctx = mock.context();
+        let loader = IMDSv2Loader::new(Arc::new(Config::default())).with_retry(2, false);
+
+        let cred = loader.load(&ctx).await?.expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "access_key_id");
+        let credential_requests = mock
+            .requests()
+            .iter()
+            .filter(|req| req.uri().path() == "/latest/meta-data/iam/security-credentials/demo")
+            .count();
+        assert_eq!(credential_requests, 2);
+
+        // Persistent 404 means the role is not attached.
+        let err = IMDSv2Loader::new(Arc::new(Config::default()))
+            .with_retry(1, false)
+            .with_role_name("missing")
+            .load(&ctx)
+            .await
+            .expect_err("load must fail");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::CredentialNotFound(_))
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_imds_loader_without_retry() -> Result<()> {
+        let mock = mock_imds(1, 0);
+        let ctx = mock.context();
+        let loader = IMDSv2Loader::new(Arc::new(Config::default())).with_retry(0, false);
+
+        let err = loader.load(&ctx).await.expect_err("load must fail");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Service {
+                status: StatusCode::SERVICE_UNAVAILABLE,
+                ..
+            })
+        ));
+        assert_eq!(token_requests(&mock), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_imds_loader_never_falls_back_to_v1() -> Result<()> {
+        // Token requests are rejected like IMDS with v2 disabled, while v1
+        // metadata requests would succeed.
+        let mock = MockContext::new()
+            .with_response(
+                Method::PUT,
+                "http://169.254.169.254/latest/api/token",
+                StatusCode::FORBIDDEN,
+                "",
+            )
+            .with_response(
+                Method::GET,
+                "http://169.254.169.254/latest/meta-data/iam/security-credentials/",
+                StatusCode::OK,
+                "demo",
+            );
+        let ctx = mock.context();
+        let loader = IMDSv2Loader::new(Arc::new(Config::default())).with_retry(0, false);
+
+        loader.load(&ctx).await.expect_err("load must fail");
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method(), Method::PUT);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_imds_loader_not_retry_rejected_token() -> Result<()> {
+        for status in [StatusCode::UNAUTHORIZED, StatusCode::FORBIDDEN] {
+            let mock = MockContext::new().with_response(
+                Method::PUT,
+                "http://169.254.169.254/latest/api/token",
+                status,
+                "",
+            );
+            let loader = IMDSv2Loader::new(Arc::new(Config::default())).with_retry(2, false);
+
+            let err = loader
+                .load(&mock.context())
+                .await
+                .expect_err("load must fail");
+            assert!(
+                matches!(err.downcast_ref::<Error>(), Some(Error::Service { status: v, .. }) if *v == status),
+                "case {status}"
+            );
+            assert_eq!(mock.requests().len(), 1, "case {status}");
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_imds_loader_connection_refused() -> Result<()> {
+        // Bind and drop a listener to get a local port nobody listens on.
+        let addr = std::net::TcpListener::bind("127.0.0.1:0")?.local_addr()?;
+        let ctx = Context::new(
+            reqsign_file_read_tokio::TokioFileRead,
+            reqsign_http_send_reqwest::ReqwestHttpSend::default(),
+        );
+
+        let err = IMDSv2Loader::new(Arc::new(Config::default()))
+            .with_endpoint(&format!("http://{addr}"))
+            .load(&ctx)
+            .await
+            .expect_err("load must fail");
+        assert!(
+            matches!(
+                err.downcast_ref::<Error>(),
+                Some(Error::CredentialNotFound(_))
+            ),
+            "{err:?}"
+        );
+        assert!(super::super::default::is_skippable(&err));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_imds_loader_timeout() -> Result<()> {
+        let mock =
+            MockContext::new().with_timeout(Method::PUT, "http://169.254.169.254/latest/api/token");
+        let ctx = mock.context();
+
+        let err = IMDSv2Loader::new(Arc::new(Config::default()))
+            .load(&ctx)
+            .await
+            .expect_err("load must time out");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Timeout(_))
+        ));
+        assert!(err.to_string().contains("hop limit"));
+
+        // Timeouts are not retried, and default to 1s.
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].extensions().get::<HttpTimeout>(),
+            Some(&HttpTimeout::new(
+                Duration::from_secs(1),
+                Duration::from_secs(1)
+            ))
+        );
+        Ok(())
+    }
+}
@@ -1,16 +1,39 @@
 mod assume_role;
 pub use assume_role::AssumeRoleLoader;
 
+mod assume_role_with_saml;
+pub use assume_role_with_saml::AssumeRoleWithSAMLLoader;
+
 mod assume_role_with_web_identity;
 pub use assume_role_with_web_identity::AssumeRoleWithWebIdentityLoader;
 
+mod chain;
+pub use chain::ChainLoader;
+
+mod cache;
+pub use cache::CredentialCache;
+pub use cache::FileCredentialCache;
+pub use cache::MemoryCredentialCache;
+
 mod config;
 pub use config::ConfigLoader;
+pub use config::CredentialSource;
 
 mod default;
+pub use default::ChainSource;
 pub use default::DefaultLoader;
+pub use default::LoadedCredential;
+
+mod ecs;
+pub use ecs::ECSLoader;
+
+mod get_session_token;
+pub use get_session_token::GetSessionTokenLoader;
 
 mod imds;
 pub use imds::IMDSv2Loader;
 
+mod vault;
+pub use vault::VaultLoader;
+
 mod utils;
@@ -1,34 +1,496 @@
-use crate::Config;
-use anyhow::anyhow;
+use crate::error::ErrorBody;
+use crate::partition::check_region;
+use crate::{Config, Partition};
+#[cfg(not(target_arch = "wasm32"))]
+use backon::{ExponentialBuilder, Retryable};
+use bytes::Bytes;
+#[cfg(not(target_arch = "wasm32"))]
+use log::debug;
+use reqsign_core::{Context, Error};
+use std::future::Future;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+
+/// Retry policy used by loaders that send requests to AWS services.
+///
+/// The default policy retries 3 times with exponential backoff starting at
+/// 100ms and jitter enabled. Set `max_times` to `0` to disable retries.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RetryPolicy {
+    max_times: usize,
+    jitter: bool,
+    #[cfg(not(target_arch = "wasm32"))]
+    total_delay: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(3, true)
+    }
+}
+
+impl RetryPolicy {
+    pub(crate) fn new(max_times: usize, jitter: bool) -> Self {
+        Self {
+            max_times,
+            jitter,
+            #[cfg(not(target_arch = "wasm32"))]
+            total_delay: None,
+        }
+    }
+
+    /// Cap the total time spent sleeping between retries, retries stop once
+    /// the next delay would exceed it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn with_total_delay(mut self, total_delay: Duration) -> Self {
+        self.total_delay = Some(total_delay);
+        self
+    }
+
+    /// Retries are not supported on wasm32, see [`RetryPolicy::run`].
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) fn with_total_delay(self, _: std::time::Duration) -> Self {
+        self
+    }
+
+    /// Build the default policy with `max_attempts` from config.
+    ///
+    /// `max_attempts` counts the first request, so `1` disables retries.
+    pub(crate) fn from_config(config: &Config) -> Self {
+        match config.max_attempts {
+            Some(max_attempts) => Self {
+                max_times: max_attempts.saturating_sub(1),
+                ..Self::default()
+            },
+            None => Self::default(),
+        }
+    }
+
+    /// Run `f` until it succeeds, returns an error that can't be retried or
+    /// runs out of retries.
+    ///
+    /// Only transient errors are retried, see [`is_retryable`].
+    pub(crate) async fn run<T, F, Fut>(&self, f: F) -> anyhow::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        self.run_when(f, is_retryable).await
+    }
+
+    /// Run `f` like [`RetryPolicy::run`], but only retry errors accepted by
+    /// `when`.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) async fn run_when<T, F, Fut>(
+        &self,
+        f: F,
+        when: fn(&anyhow::Error) -> bool,
+    ) -> anyhow::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let mut backoff = ExponentialBuilder::default()
+            .with_min_delay(Duration::from_millis(100))
+            .with_max_times(self.max_times)
+            .with_total_delay(self.total_delay);
+        if self.jitter {
+            backoff = backoff.with_jitter();
+        }
+
+        f.retry(backoff)
+            .when(when)
+            .notify(|err, dur| debug!("request failed, retrying after {dur:?}: {err:?}"))
+            .await
+    }
+
+    /// Run `f` once without retries.
+    ///
+    /// backon sleeps with gloo timers on wasm32 which are not `Send`, so the
+    /// loader futures couldn't be `Send` with retries.
+    #[cfg(target_arch = "wasm32")]
+    pub(crate) async fn run_when<T, F, Fut>(
+        &self,
+        mut f: F,
+        _: fn(&anyhow::Error) -> bool,
+    ) -> anyhow::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = anyhow::Result<T>>,
+    {
+        let _ = (self.max_times, self.jitter);
+        f().await
+    }
+}
+
+/// Error codes returned by AWS services when requests are throttled.
+///
+/// ref: <https://github.com/smithy-lang/smithy-rs/blob/main/rust-runtime/aws-smithy-runtime/src/client/retries/classifiers.rs>
+const THROTTLING_CODES: [&str; 8] = [
+    "Throttling",
+    "ThrottlingException",
+    "ThrottledException",
+    "RequestThrottledException",
+    "TooManyRequestsException",
+    "RequestLimitExceeded",
+    "SlowDown",
+    "PriorRequestNotComplete",
+];
+
+/// Whether the error is transient and worth a retry.
+///
+/// Transport failures reported as [`Error::Http`], 5xx responses and
+/// throttling are retried. Other errors like `AccessDenied`, `ExpiredToken`
+/// or a missing token file fail immediately.
+pub(crate) fn is_retryable(err: &anyhow::Error) -> bool {
+    match err.downcast_ref::<Error>() {
+        Some(Error::Http(_)) => true,
+        Some(Error::Service { status, code, .. }) => {
+            status.is_server_error()
+                || *status == http::StatusCode::TOO_MANY_REQUESTS
+                || code
+                    .as_deref()
+                    .is_some_and(|code| THROTTLING_CODES.contains(&code))
+        }
+        _ => false,
+    }
+}
+
+/// Send the request via `ctx` and read the body as string.
+///
+/// Untyped errors of the underlying `HttpSend` are transport failures like
+/// connection resets, they are reported as [`Error::Http`] so that
+/// [`RetryPolicy`] retries them.
+pub(crate) async fn http_send(
+    ctx: &Context,
+    req: http::Request<Bytes>,
+) -> anyhow::Result<http::Response<String>> {
+    ctx.http_send_as_string(req)
+        .await
+        .map_err(|err| match err.downcast_ref::<Error>() {
+            Some(_) => err,
+            None => Error::Http(format!("{err:#}")).into(),
+        })
+}
+
+/// Build the error for a failed request to AWS STS.
+///
+/// The `Code`, `Message` and `RequestId` of the STS `<ErrorResponse>` are
+/// kept in [`Error::Service`] so that errors like `AccessDenied` and
+/// `ExpiredTokenException` can be told apart. The raw body is kept as the
+/// message if it's not an STS error.
+pub(crate) fn sts_error(status: http::StatusCode, body: &str) -> Error {
+    match ErrorBody::parse(body) {
+        Some(resp) => Error::Service {
+            status,
+            code: Some(resp.code),
+            message: resp.message,
+            request_id: (!resp.request_id.is_empty()).then_some(resp.request_id),
+        },
+        None => Error::Service {
+            status,
+            code: None,
+            message: body.to_string(),
+            request_id: None,
+        },
+    }
+}
 
 /// Get the sts endpoint.
 ///
-/// The returning format may look like `sts.{region}.amazonaws.com`
+/// The returning format may look like `https://sts.{region}.amazonaws.com`
+///
+/// `endpoint_url_sts` and `endpoint_url` take precedence over the default
+/// endpoints, in that order. `sts_region` takes precedence over `region`,
+/// which is validated unless `skip_region_validation` is enabled.
 ///
 /// # Notes
 ///
-/// AWS could have different sts endpoint based on it's region.
-/// We can check them by region name.
+/// AWS could have different sts endpoint based on it's [`Partition`].
+/// FIPS endpoints will be used if `use_fips_endpoint` is enabled.
 ///
 /// ref: https://github.com/awslabs/aws-sdk-rust/blob/31cfae2cf23be0c68a47357070dea1aee9227e3a/sdk/sts/src/aws_endpoint.rs
 pub fn sts_endpoint(config: &Config) -> anyhow::Result<String> {
-    // use regional sts if sts_regional_endpoints has been set.
-    if config.sts_regional_endpoints == "regional" {
-        let region = config.region.clone().ok_or_else(|| {
-            anyhow!("sts_regional_endpoints set to regional, but region is not set")
-        })?;
-        if region.starts_with("cn-") {
-            Ok(format!("sts.{region}.amazonaws.com.cn"))
-        } else {
-            Ok(format!("sts.{region}.amazonaws.com"))
-        }
-    } else {
-        let region = config.region.clone().unwrap_or_default();
-        if region.starts_with("cn") {
-            // TODO: seems aws china doesn't support global sts?
-            Ok("sts.amazonaws.com.cn".to_string())
-        } else {
-            Ok("sts.amazonaws.com".to_string())
+    if let Some(endpoint) = config
+        .endpoint_url_sts
+        .as_ref()
+        .or(config.endpoint_url.as_ref())
+    {
+        return Ok(endpoint.trim_end_matches('/').to_string());
+    }
+
+    let regional = config.sts_regional_endpoints == "regional" || config.sts_region.is_some();
+    let Some(region) = config.sts_region.as_deref().or(config.region.as_deref()) else {
+        if regional {
+            return Err(Error::Config(
+                "sts_regional_endpoints set to regional, but region is not set".to_string(),
+            )
+            .into());
+        }
+        if config.use_fips_endpoint {
+            return Ok(Partition::Aws.endpoint("sts", "us-east-1", true));
+        }
+        return Ok("https://sts.amazonaws.com".to_string());
+    };
+
+    if !config.skip_region_validation {
+        check_region(region)?;
+    }
+
+    // FIPS endpoints are always regional.
+    let partition = Partition::from_region(region);
+    let regional = regional || config.use_fips_endpoint;
+    match partition {
+        Partition::Aws if !regional => Ok("https://sts.amazonaws.com".to_string()),
+        // TODO: seems aws china doesn't support global sts?
+        Partition::AwsCn if !regional => Ok("https://sts.amazonaws.com.cn".to_string()),
+        // GovCloud doesn't have global sts.
+        _ => Ok(partition.endpoint("sts", region, config.use_fips_endpoint)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_sts_error() {
+        let body = r#"<ErrorResponse xmlns="https://sts.amazonaws.com/doc/2011-06-15/">
+  <Error>
+    <Type>Sender</Type>
+    <Code>AccessDenied</Code>
+    <Message>User: arn:aws:iam::123456789012:user/demo is not authorized to perform: sts:AssumeRole on resource: arn:aws:iam::123456789012:role/demo</Message>
+  </Error>
+  <RequestId>c6104cbe-af31-11e0-8154-cbc7ccf896c7</RequestId>
+</ErrorResponse>"#;
+        let err = sts_error(http::StatusCode::FORBIDDEN, body);
+        let Error::Service {
+            status,
+            code,
+            message,
+            request_id,
+        } = &err
+        else {
+            panic!("sts error must be a service error: {err:?}");
+        };
+        assert_eq!(*status, http::StatusCode::FORBIDDEN);
+        assert_eq!(code.as_deref(), Some("AccessDenied"));
+        assert_eq!(
+            message,
+            "User: arn:aws:iam::123456789012:user/demo is not authorized to perform: \
+             sts:AssumeRole on resource: arn:aws:iam::123456789012:role/demo"
+        );
+        assert_eq!(
+            request_id.as_deref(),
+            Some("c6104cbe-af31-11e0-8154-cbc7ccf896c7")
+        );
+
+        let err = sts_error(http::StatusCode::BAD_GATEWAY, "Bad Gateway");
+        assert!(matches!(
+            err,
+            Error::Service {
+                status: http::StatusCode::BAD_GATEWAY,
+                code: None,
+                ref message,
+                request_id: None,
+            } if message == "Bad Gateway"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_retry_policy() {
+        fn service_error(status: http::StatusCode, code: &str) -> Error {
+            Error::Service {
+                status,
+                code: Some(code.to_string()),
+                message: String::new(),
+                request_id: None,
+            }
+        }
+
+        let cases = vec![
+            (
+                "retry http errors",
+                RetryPolicy::new(2, false),
+                (|| Error::Http("connection reset".to_string())) as fn() -> Error,
+                3,
+            ),
+            (
+                "zero retries",
+                RetryPolicy::new(0, false),
+                || Error::Http("connection reset".to_string()),
+                1,
+            ),
+            (
+                "max attempts from config",
+                RetryPolicy::from_config(&Config {
+                    max_attempts: Some(2),
+                    ..Default::default()
+                }),
+                || Error::Http("connection reset".to_string()),
+                2,
+            ),
+            (
+                "retry 5xx",
+                RetryPolicy::new(2, false),
+                || service_error(http::StatusCode::SERVICE_UNAVAILABLE, "ServiceUnavailable"),
+                3,
+            ),
+            (
+                "retry throttling",
+                RetryPolicy::new(2, false),
+                || service_error(http::StatusCode::BAD_REQUEST, "Throttling"),
+                3,
+            ),
+            (
+                "not retry access denied",
+                RetryPolicy::new(2, false),
+                || service_error(http::StatusCode::FORBIDDEN, "AccessDenied"),
+                1,
+            ),
+            (
+                "not retry expired token",
+                RetryPolicy::new(2, false),
+                || service_error(http::StatusCode::BAD_REQUEST, "ExpiredToken"),
+                1,
+            ),
+            (
+                "not retry config errors",
+                RetryPolicy::new(2, false),
+                || Error::Config("invalid".to_string()),
+                1,
+            ),
+        ];
+
+        for (name, policy, err, expected) in cases {
+            let calls = AtomicUsize::new(0);
+            let result: anyhow::Result<()> = policy
+                .run(|| async {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    Err(err().into())
+                })
+                .await;
+            assert!(result.is_err(), "case {name}");
+            assert_eq!(calls.load(Ordering::SeqCst), expected, "case {name}");
         }
+
+        // Untyped errors, like a missing token file, are not retried.
+        let calls = AtomicUsize::new(0);
+        let result: anyhow::Result<()> = RetryPolicy::new(2, false)
+            .run(|| async {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Err(anyhow::anyhow!("no such file or directory"))
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_sts_endpoint() -> anyhow::Result<()> {
+        let cases = vec![
+            ("default", Config::default(), "https://sts.amazonaws.com"),
+            (
+                "regional",
+                Config {
+                    region: Some("us-west-2".to_string()),
+                    sts_regional_endpoints: "regional".to_string(),
+                    ..Default::default()
+                },
+                "https://sts.us-west-2.amazonaws.com",
+            ),
+            (
+                "sts_region",
+                Config {
+                    region: Some("eu-west-1".to_string()),
+                    sts_region: Some("us-east-1".to_string()),
+                    ..Default::default()
+                },
+                "https://sts.us-east-1.amazonaws.com",
+            ),
+            (
+                "endpoint_url",
+                Config {
+                    endpoint_url: Some("http://127.0.0.1:4566/".to_string()),
+                    ..Default::default()
+                },
+                "http://127.0.0.1:4566",
+            ),
+            (
+                "endpoint_url_sts",
+                Config {
+                    region: Some("us-west-2".to_string()),
+                    sts_regional_endpoints: "regional".to_string(),
+                    endpoint_url: Some("http://127.0.0.1:4566".to_string()),
+                    endpoint_url_sts: Some("https://sts.vpce.example.com".to_string()),
+                    ..Default::default()
+                },
+                "https://sts.vpce.example.com",
+            ),
+            (
+                "cn regional",
+                Config {
+                    region: Some("cn-north-1".to_string()),
+                    sts_regional_endpoints: "regional".to_string(),
+                    ..Default::default()
+                },
+                "https://sts.cn-north-1.amazonaws.com.cn",
+            ),
+            (
+                "us-gov legacy",
+                Config {
+                    region: Some("us-gov-west-1".to_string()),
+                    ..Default::default()
+                },
+                "https://sts.us-gov-west-1.amazonaws.com",
+            ),
+            (
+                "fips legacy",
+                Config {
+                    region: Some("us-west-2".to_string()),
+                    use_fips_endpoint: true,
+                    ..Default::default()
+                },
+                "https://sts-fips.us-west-2.amazonaws.com",
+            ),
+            (
+                "fips regional us-gov",
+                Config {
+                    region: Some("us-gov-east-1".to_string()),
+                    sts_regional_endpoints: "regional".to_string(),
+                    use_fips_endpoint: true,
+                    ..Default::default()
+                },
+                "https://sts-fips.us-gov-east-1.amazonaws.com",
+            ),
+        ];
+
+        for (name, config, expected) in cases {
+            assert_eq!(sts_endpoint(&config)?, expected, "case {name}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_sts_endpoint_with_invalid_region() -> anyhow::Result<()> {
+        let config = Config {
+            region: Some("us-east-1".to_string()),
+            sts_region: Some("us-east1".to_string()),
+            ..Default::default()
+        };
+        let err = sts_endpoint(&config).expect_err("region must be invalid");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Config(_))
+        ));
+        assert!(err.to_string().contains("\"us-east1\""));
+
+        let config = Config {
+            skip_region_validation: true,
+            ..config
+        };
+        assert_eq!(sts_endpoint(&config)?, "https://sts.us-east1.amazonaws.com");
+        Ok(())
     }
 }
@@ -0,0 +1,236 @@
+use crate::constants::{VAULT_ADDR, VAULT_NAMESPACE, VAULT_TOKEN};
+use crate::Credential;
+use anyhow::Result;
+use async_trait::async_trait;
+use bytes::Bytes;
+use http::Method;
+use reqsign_core::utils::Redact;
+use reqsign_core::{Context, Error, Load};
+use serde::Deserialize;
+use std::fmt::{Debug, Formatter};
+
+/// VaultLoader will load credential from the [AWS secrets engine](https://developer.hashicorp.com/vault/docs/secrets/aws)
+/// of HashiCorp Vault.
+///
+/// The loader reads `<mount>/creds/<role>`, the expiration of the loaded
+/// credential is derived from the lease duration.
+///
+/// The vault address, token and namespace are resolved from:
+///
+/// - [`VaultLoader::with_address`], [`VaultLoader::with_token`] and
+///   [`VaultLoader::with_namespace`]
+/// - env value: [`VAULT_ADDR`], [`VAULT_TOKEN`] and [`VAULT_NAMESPACE`]
+///
+/// The loader returns `None` if address or token is missing.
+pub struct VaultLoader {
+    role: String,
+    mount: String,
+    address: Option<String>,
+    token: Option<String>,
+    namespace: Option<String>,
+}
+
+impl Debug for VaultLoader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("VaultLoader")
+            .field("role", &self.role)
+            .field("mount", &self.mount)
+            .field("address", &self.address)
+            .field("token", &Redact::from(&self.token))
+            .field("namespace", &self.namespace)
+            .finish()
+    }
+}
+
+impl VaultLoader {
+    /// Create a new `VaultLoader` for the given role.
+    pub fn new(role: &str) -> Self {
+        Self {
+            role: role.to_string(),
+            mount: "aws".to_string(),
+            address: None,
+            token: None,
+            namespace: None,
+        }
+    }
+
+    /// Set the path the AWS secrets engine is mounted at, `aws` by default.
+    pub fn with_mount(mut self, mount: &str) -> Self {
+        self.mount = mount.trim_matches('/').to_string();
+        self
+    }
+
+    /// Set the address of vault, for example, `https://vault.example.com:8200`.
+    pub fn with_address(mut self, address: &str) -> Self {
+        self.address = Some(address.to_string());
+        self
+    }
+
+    /// Set the token used to authenticate with vault.
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    /// Set the vault enterprise namespace, sent as `X-Vault-Namespace`.
+    pub fn with_namespace(mut self, namespace: &str) -> Self {
+        self.namespace = Some(namespace.to_string());
+        self
+    }
+}
+
+#[async_trait]
+impl Load for VaultLoader {
+    type Key = Credential;
+
+    async fn load(&self, ctx: &Context) -> Result<Option<Self::Key>> {
+        let (Some(address), Some(token)) = (
+            self.address.clone().or_else(|| ctx.env_var(VAULT_ADDR)),
+            self.token.clone().or_else(|| ctx.env_var(VAULT_TOKEN)),
+        ) else {
+            return Ok(None);
+        };
+        let namespace = self
+            .namespace
+            .clone()
+            .or_else(|| ctx.env_var(VAULT_NAMESPACE));
+
+        let url = format!(
+            "{}/v1/{}/creds/{}",
+            address.trim_end_matches('/'),
+            self.mount,
+            self.role
+        );
+        let mut req = http::Request::builder()
+            .uri(url)
+            .method(Method::GET)
+            .header("x-vault-token", token);
+        if let Some(namespace) = namespace {
+            req = req.header("x-vault-namespace", namespace);
+        }
+        let req = req.body(Bytes::new())?;
+
+        let resp = ctx.http_send_as_string(req).await?;
+        if resp.status() != http::StatusCode::OK {
+            return Err(Error::Service {
+                status: resp.status(),
+                code: None,
+                message: resp.into_body(),
+                request_id: None,
+            }
+            .into());
+        }
+
+        let resp: VaultSecret = serde_json::from_str(resp.body())
+            .map_err(|e| Error::Parse(format!("invalid vault AWS secret: {e}")))?;
+
+        // Lease duration `0` means the secret is not renewable and never expires.
+        let expires_in = match resp.lease_duration {
+            0 => None,
+            secs => Some(ctx.now() + chrono::TimeDelta::try_seconds(secs).unwrap_or_default()),
+        };
+        let cred = Credential {
+            access_key_id: resp.data.access_key,
+            secret_access_key: resp.data.secret_key,
+            session_token: resp.data.security_token,
+            expires_in,
+        };
+
+        Ok(Some(cred))
+    }
+}
+
+#[derive(Default, Debug, Deserialize)]
+#[serde(default)]
+struct VaultSecret {
+    lease_duration: i64,
+    data: VaultAwsCredentials,
+}
+
+#[derive(Default, Debug, Deserialize)]
+#[serde(default)]
+struct VaultAwsCredentials {
+    access_key: String,
+    secret_key: String,
+    security_token: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::{Method, StatusCode};
+    use reqsign_core::{FixedClock, MockContext, StaticEnv};
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_vault_loader() -> Result<()> {
+        let now = chrono::DateTime::parse_from_rfc3339("2024-01-01T00:00:00Z")?
+            .with_timezone(&chrono::Utc);
+
+        let mock = MockContext::new().with_response(
+            Method::GET,
+            "http://127.0.0.1:8200/v1/aws/creds/demo",
+            StatusCode::OK,
+            r#"{"request_id":"demo","lease_id":"aws/creds/demo/abcd","renewable":true,"lease_duration":3600,"data":{"access_key":"access_key_id","secret_key":"secret_access_key","security_token":"session_token"}}"#,
+        );
+        let ctx = mock.context();
+        assert!(VaultLoader::new("demo").load(&ctx).await?.is_none());
+        assert!(mock.requests().is_empty());
+
+        let ctx = ctx.with_clock(FixedClock(now)).with_env(StaticEnv {
+            home_dir: None,
+            envs: HashMap::from_iter([
+                (VAULT_ADDR.to_string(), "http://127.0.0.1:8200/".to_string()),
+                (VAULT_TOKEN.to_string(), "vault_token".to_string()),
+            ]),
+        });
+        let cred = VaultLoader::new("demo")
+            .with_namespace("team")
+            .load(&ctx)
+            .await?
+            .expect("credential must be loaded");
+        assert_eq!(cred.access_key_id, "access_key_id");
+        assert_eq!(cred.secret_access_key, "secret_access_key");
+        assert_eq!(cred.session_token.as_deref(), Some("session_token"));
+        assert_eq!(
+            cred.expires_in,
+            Some(now + chrono::TimeDelta::try_hours(1).unwrap())
+        );
+
+        let requests = mock.requests();
+        assert_eq!(requests[0].uri(), "http://127.0.0.1:8200/v1/aws/creds/demo");
+        assert_eq!(requests[0].headers()["x-vault-token"], "vault_token");
+        assert_eq!(requests[0].headers()["x-vault-namespace"], "team");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_vault_loader_permission_denied() -> Result<()> {
+        let mock = MockContext::new().with_response(
+            Method::GET,
+            "http://127.0.0.1:8200/v1/aws/creds/demo",
+            StatusCode::FORBIDDEN,
+            r#"{"errors":["permission denied"]}"#,
+        );
+        let err = VaultLoader::new("demo")
+            .with_address("http://127.0.0.1:8200")
+            .with_token("vault_token")
+            .load(&mock.context())
+            .await
+            .expect_err("load must fail");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Service { status: StatusCode::FORBIDDEN, message, .. })
+                if message.contains("permission denied")
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_vault_loader_debug_redacted() {
+        let loader = VaultLoader::new("demo").with_token("hvs.CAESIJlongvaulttoken");
+        let debug = format!("{loader:?}");
+        assert!(!debug.contains("hvs.CAESIJlongvaulttoken"), "{debug}");
+        assert!(debug.contains("role: \"demo\""), "{debug}");
+    }
+}
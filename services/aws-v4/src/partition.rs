@@ -0,0 +1,175 @@
+#[cfg(feature = "load")]
+use reqsign_core::Error;
+
+/// AWS partition that a region belongs to.
+///
+/// Endpoints of the same service differ between partitions, for example
+/// `sts.cn-north-1.amazonaws.com.cn` in `aws-cn`.
+///
+/// ref: <https://github.com/aws/aws-sdk-rust/blob/main/sdk/aws-config/src/endpoint/partitions.json>
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Partition {
+    /// AWS Standard, like `us-east-1`.
+    Aws,
+    /// AWS China, like `cn-north-1`.
+    AwsCn,
+    /// AWS GovCloud (US), like `us-gov-west-1`.
+    AwsUsGov,
+}
+
+impl Partition {
+    /// Resolve the partition of given region.
+    ///
+    /// Unknown regions fall back to [`Partition::Aws`].
+    pub fn from_region(region: &str) -> Self {
+        if region.starts_with("cn-") {
+            Partition::AwsCn
+        } else if region.starts_with("us-gov-") {
+            Partition::AwsUsGov
+        } else {
+            Partition::Aws
+        }
+    }
+
+    /// Get the partition id, like `aws-cn`.
+    pub fn id(&self) -> &'static str {
+        match self {
+            Partition::Aws => "aws",
+            Partition::AwsCn => "aws-cn",
+            Partition::AwsUsGov => "aws-us-gov",
+        }
+    }
+
+    /// Get the DNS suffix of this partition.
+    pub fn dns_suffix(&self) -> &'static str {
+        match self {
+            Partition::Aws | Partition::AwsUsGov => "amazonaws.com",
+            Partition::AwsCn => "amazonaws.com.cn",
+        }
+    }
+
+    /// Build the endpoint of `service` in `region`, like
+    /// `https://sts.us-west-2.amazonaws.com`.
+    ///
+    /// FIPS variant like `https://sts-fips.us-west-2.amazonaws.com` will be
+    /// used if `use_fips` is enabled.
+    pub fn endpoint(&self, service: &str, region: &str, use_fips: bool) -> String {
+        let fips = if use_fips { "-fips" } else { "" };
+        format!("https://{service}{fips}.{region}.{}", self.dns_suffix())
+    }
+}
+
+/// Pseudo regions accepted besides the regular ones.
+#[cfg(feature = "load")]
+const PSEUDO_REGIONS: [&str; 2] = ["aws-global", "auto"];
+
+/// Check that `region` looks like `us-east-1` or `us-gov-west-1`, so that
+/// typos like `us-east1` are reported before building an endpoint that
+/// fails with a DNS error.
+#[cfg(feature = "load")]
+pub(crate) fn check_region(region: &str) -> anyhow::Result<()> {
+    if PSEUDO_REGIONS.contains(&region) {
+        return Ok(());
+    }
+
+    let parts: Vec<_> = region.split('-').collect();
+    let valid = parts.len() >= 3
+        && parts[0].len() == 2
+        && parts[..parts.len() - 1]
+            .iter()
+            .all(|v| !v.is_empty() && v.bytes().all(|b| b.is_ascii_lowercase()))
+        && parts[parts.len() - 1].bytes().all(|b| b.is_ascii_digit())
+        && !parts[parts.len() - 1].is_empty();
+    if !valid {
+        return Err(Error::Config(format!(
+            "invalid region {region:?}, expected a region like us-east-1, \
+             enable skip_region_validation for custom regions"
+        ))
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "load")]
+    fn test_check_region() {
+        for region in [
+            "us-east-1",
+            "eu-central-2",
+            "cn-north-1",
+            "us-gov-west-1",
+            "us-isob-east-1",
+            "aws-global",
+            "auto",
+        ] {
+            assert!(check_region(region).is_ok(), "case {region}");
+        }
+
+        for region in [
+            "",
+            "us-east1",
+            "us-east-",
+            "use-east-1",
+            "US-EAST-1",
+            "us--east-1",
+            "us-east-1a",
+            "https://us-east-1",
+        ] {
+            let err = check_region(region).expect_err(region);
+            assert!(matches!(
+                err.downcast_ref::<Error>(),
+                Some(Error::Config(_))
+            ));
+            assert!(err.to_string().contains(&format!("{region:?}")));
+        }
+    }
+
+    #[test]
+    fn test_partition() {
+        let cases = vec![
+            (
+                "us-east-1",
+                false,
+                Partition::Aws,
+                "https://sts.us-east-1.amazonaws.com",
+            ),
+            (
+                "cn-north-1",
+                false,
+                Partition::AwsCn,
+                "https://sts.cn-north-1.amazonaws.com.cn",
+            ),
+            (
+                "us-gov-west-1",
+                false,
+                Partition::AwsUsGov,
+                "https://sts.us-gov-west-1.amazonaws.com",
+            ),
+            (
+                "us-west-2",
+                true,
+                Partition::Aws,
+                "https://sts-fips.us-west-2.amazonaws.com",
+            ),
+            (
+                "us-gov-east-1",
+                true,
+                Partition::AwsUsGov,
+                "https://sts-fips.us-gov-east-1.amazonaws.com",
+            ),
+        ];
+
+        for (region, use_fips, partition, endpoint) in cases {
+            assert_eq!(Partition::from_region(region), partition, "case {region}");
+            assert_eq!(
+                partition.endpoint("sts", region, use_fips),
+                endpoint,
+                "case {region}"
+            );
+        }
+    }
+}
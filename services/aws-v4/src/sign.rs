@@ -0,0 +1,100 @@
+//! Pure SigV4 signing primitives.
+//!
+//! Functions here only do the signing math and never touch the network,
+//! filesystem or env, so they are available with `default-features = false`
+//! for WASM and embedded users that obtain credentials elsewhere. Use
+//! [`Builder::sign_request`](crate::Builder::sign_request) to sign a whole
+//! `http::request::Parts` with a given time.
+//!
+//! - [Create a signed AWS API request](https://docs.aws.amazon.com/IAM/latest/UserGuide/create-signed-request.html)
+
+use reqsign_core::hash::{hex_hmac_sha256, hex_sha256, hmac_sha256};
+use reqsign_core::time::{format_date, format_iso8601, DateTime};
+
+/// Build the credential scope like `20220313/<region>/<service>/aws4_request`.
+pub fn scope(time: DateTime, region: &str, service: &str) -> String {
+    format!("{}/{region}/{service}/aws4_request", format_date(time))
+}
+
+/// Derive the signing key from the secret access key.
+///
+/// The key only depends on the date, region and service, so it could be
+/// reused for all requests signed in the same day.
+pub fn signing_key(secret: &str, time: DateTime, region: &str, service: &str) -> Vec<u8> {
+    // Sign secret
+    let secret = format!("AWS4{secret}");
+    // Sign date
+    let sign_date = hmac_sha256(secret.as_bytes(), format_date(time).as_bytes());
+    // Sign region
+    let sign_region = hmac_sha256(sign_date.as_slice(), region.as_bytes());
+    // Sign service
+    let sign_service = hmac_sha256(sign_region.as_slice(), service.as_bytes());
+    // Sign request
+    hmac_sha256(sign_service.as_slice(), "aws4_request".as_bytes())
+}
+
+/// Build the string to sign of the canonical request.
+///
+/// ```text
+/// AWS4-HMAC-SHA256
+/// 20220313T072004Z
+/// 20220313/<region>/<service>/aws4_request
+/// <hashed_canonical_request>
+/// ```
+pub fn string_to_sign(time: DateTime, scope: &str, canonical_request: &str) -> String {
+    format!(
+        "AWS4-HMAC-SHA256\n{}\n{scope}\n{}",
+        format_iso8601(time),
+        hex_sha256(canonical_request.as_bytes())
+    )
+}
+
+/// Calculate the hex encoded signature of the string to sign.
+pub fn signature(signing_key: &[u8], string_to_sign: &str) -> String {
+    hex_hmac_sha256(signing_key, string_to_sign.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqsign_core::time::parse_rfc3339;
+
+    /// Example from [Examples of the complete Version 4 signing process](https://docs.aws.amazon.com/IAM/latest/UserGuide/signature-v4-examples.html)
+    #[test]
+    fn test_sign_iam_example() -> anyhow::Result<()> {
+        let time = parse_rfc3339("2015-08-30T12:36:00Z")?;
+        let creq = "GET
+/
+Action=ListUsers&Version=2010-05-08
+content-type:application/x-www-form-urlencoded; charset=utf-8
+host:iam.amazonaws.com
+x-amz-date:20150830T123600Z
+
+content-type;host;x-amz-date
+e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+        let scope = scope(time, "us-east-1", "iam");
+        assert_eq!(scope, "20150830/us-east-1/iam/aws4_request");
+
+        let string_to_sign = string_to_sign(time, &scope, creq);
+        assert_eq!(
+            string_to_sign,
+            "AWS4-HMAC-SHA256
+20150830T123600Z
+20150830/us-east-1/iam/aws4_request
+f536975d06c0309214f805bb90ccff089219ecd68b2577efef23edd43b7e1a59"
+        );
+
+        let key = signing_key(
+            "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            time,
+            "us-east-1",
+            "iam",
+        );
+        assert_eq!(
+            signature(&key, &string_to_sign),
+            "5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,356 @@
+use crate::build::{
+    canonical_request_string, canonicalize_header, canonicalize_query, default_uri_options,
+    redact_canonical_request, take_unsigned_headers, MAX_EXPIRES,
+};
+use crate::{Credential, SignedHeaders, SigningScope, UnsignedPayload};
+use anyhow::{anyhow, bail};
+use async_trait::async_trait;
+use http::request::Parts;
+use http::{header, HeaderValue};
+use log::debug;
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{DerSignature, SigningKey};
+use reqsign_core::hash::{hex_sha256, hmac_sha256};
+use reqsign_core::time::{format_date, format_iso8601};
+use reqsign_core::{Build, Context, SigningRequest};
+use std::fmt::Write;
+use std::time::Duration;
+
+/// Algorithm used by SigV4A.
+const ALGORITHM: &str = "AWS4-ECDSA-P256-SHA256";
+
+/// Header used to specify the region set of SigV4A.
+const X_AMZ_REGION_SET: &str = "x-amz-region-set";
+
+/// The order of the P-256 curve minus 2 in big-endian.
+const N_MINUS_2: [u8; 32] = [
+    0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+    0xbc, 0xe6, 0xfa, 0xad, 0xa7, 0x17, 0x9e, 0x84, 0xf3, 0xb9, 0xca, 0xc2, 0xfc, 0x63, 0x25, 0x4f,
+];
+
+/// Builder that implement AWS SigV4A.
+///
+/// SigV4A signs with an ECDSA P-256 key derived from the secret access key,
+/// so the same signature is valid in every region of the region set. S3
+/// Multi-Region Access Points require it, use `*` as the region set.
+///
+/// - [Signing AWS API requests](https://docs.aws.amazon.com/IAM/latest/UserGuide/reference_aws-signing.html)
+#[derive(Debug)]
+pub struct V4aBuilder {
+    service: String,
+    region_set: String,
+    double_uri_encode: bool,
+    normalize_uri_path: bool,
+}
+
+impl V4aBuilder {
+    /// Create a new builder for AWS SigV4A signer.
+    pub fn new(service: &str, region_set: &str) -> Self {
+        Self {
+            service: service.into(),
+            region_set: region_set.into(),
+            double_uri_encode: default_uri_options(service),
+            normalize_uri_path: default_uri_options(service),
+        }
+    }
+
+    /// Set whether to URI encode the path twice in the canonical request,
+    /// see [`crate::Builder::with_double_uri_encode`].
+    pub fn with_double_uri_encode(mut self, enabled: bool) -> Self {
+        self.double_uri_encode = enabled;
+        self
+    }
+
+    /// Set whether to normalize the path in the canonical request,
+    /// see [`crate::Builder::with_normalize_uri_path`].
+    pub fn with_normalize_uri_path(mut self, enabled: bool) -> Self {
+        self.normalize_uri_path = enabled;
+        self
+    }
+}
+
+#[async_trait]
+impl Build for V4aBuilder {
+    type Key = Credential;
+
+    async fn build(
+        &self,
+        ctx: &Context,
+        req: &mut Parts,
+        key: Option<&Self::Key>,
+        expires_in: Option<Duration>,
+    ) -> anyhow::Result<()> {
+        if let Some(expire) = expires_in {
+            if expire > MAX_EXPIRES {
+                bail!(
+                    "expires_in must not exceed 7 days, got {}s",
+                    expire.as_secs()
+                );
+            }
+        }
+
+        let Some(cred) = key else {
+            return Ok(());
+        };
+
+        let now = ctx.now();
+        let unsigned_payload = req.extensions.get::<UnsignedPayload>().is_some();
+        let service = req
+            .extensions
+            .get::<SigningScope>()
+            .and_then(|v| v.service.clone())
+            .unwrap_or_else(|| self.service.clone());
+        let mut signed_req = SigningRequest::build(req)?;
+
+        // Scope: "20220313/<service>/aws4_request"
+        let scope = format!("{}/{service}/aws4_request", format_date(now));
+        debug!("calculated scope: {scope}");
+
+        // The region set must be signed as well.
+        if expires_in.is_some() {
+            signed_req
+                .query
+                .push(("X-Amz-Region-Set".into(), self.region_set.clone()));
+        } else {
+            signed_req
+                .headers
+                .insert(X_AMZ_REGION_SET, HeaderValue::from_str(&self.region_set)?);
+        }
+
+        canonicalize_header(&mut signed_req, cred, expires_in, now, unsigned_payload)?;
+        let unsigned_headers = take_unsigned_headers(
+            req.extensions.get::<SignedHeaders>(),
+            &mut signed_req.headers,
+        );
+        canonicalize_query(&mut signed_req, cred, expires_in, now, ALGORITHM, &scope)?;
+
+        // build canonical request and string to sign.
+        let creq = canonical_request_string(
+            &mut signed_req,
+            self.double_uri_encode,
+            self.normalize_uri_path,
+        )?;
+        debug!(
+            "calculated canonical request: {}",
+            redact_canonical_request(&creq, cred)
+        );
+        let encoded_req = hex_sha256(creq.as_bytes());
+
+        // StringToSign:
+        //
+        // AWS4-ECDSA-P256-SHA256
+        // 20220313T072004Z
+        // 20220313/<service>/aws4_request
+        // <hashed_canonical_request>
+        let string_to_sign = {
+            let mut f = String::new();
+            writeln!(f, "{ALGORITHM}")?;
+            writeln!(f, "{}", format_iso8601(now))?;
+            writeln!(f, "{}", &scope)?;
+            write!(f, "{}", &encoded_req)?;
+            f
+        };
+        debug!("calculated string to sign: {string_to_sign}");
+
+        let signing_key = generate_signing_key(&cred.access_key_id, &cred.secret_access_key)?;
+        let signature: DerSignature = signing_key.sign(string_to_sign.as_bytes());
+        let signature = hex::encode(signature.as_bytes());
+
+        if expires_in.is_some() {
+            signed_req.query.push(("X-Amz-Signature".into(), signature));
+        } else {
+            let mut authorization = HeaderValue::from_str(&format!(
+                "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+                ALGORITHM,
+                cred.access_key_id,
+                scope,
+                signed_req.header_name_to_vec_sorted().join(";"),
+                signature
+            ))?;
+            authorization.set_sensitive(true);
+
+            signed_req
+                .headers
+                .insert(header::AUTHORIZATION, authorization);
+        }
+
+        // Apply to the request.
+        signed_req.apply(req)?;
+        req.headers.extend(unsigned_headers);
+        Ok(())
+    }
+}
+
+/// Derive the ECDSA P-256 signing key from the credential.
+///
+/// This follows the NIST SP 800-108 KDF in counter mode with HMAC-SHA256
+/// that AWS uses, retrying with the next counter until the candidate falls
+/// into `[1, n-1]`.
+fn generate_signing_key(
+    access_key_id: &str,
+    secret_access_key: &str,
+) -> anyhow::Result<SigningKey> {
+    let input_key = format!("AWS4A{secret_access_key}");
+
+    for counter in 1..=u8::MAX {
+        // FixedInputString: "1" || Label || 0x00 || Context || Length
+        let mut fis = Vec::with_capacity(4 + ALGORITHM.len() + 1 + access_key_id.len() + 1 + 4);
+        fis.extend_from_slice(&1u32.to_be_bytes());
+        fis.extend_from_slice(ALGORITHM.as_bytes());
+        fis.push(0);
+        fis.extend_from_slice(access_key_id.as_bytes());
+        fis.push(counter);
+        fis.extend_from_slice(&256u32.to_be_bytes());
+
+        let k0 = hmac_sha256(input_key.as_bytes(), &fis);
+        let mut k0: [u8; 32] = k0
+            .try_into()
+            .map_err(|_| anyhow!("hmac-sha256 output must be 32 bytes"))?;
+
+        // Big-endian byte arrays compare the same as the numbers they encode.
+        if k0 > N_MINUS_2 {
+            continue;
+        }
+
+        // Private key is k0 + 1, which will never overflow since k0 <= n-2.
+        for b in k0.iter_mut().rev() {
+            let (v, overflow) = b.overflowing_add(1);
+            *b = v;
+            if !overflow {
+                break;
+            }
+        }
+
+        return Ok(SigningKey::from_slice(&k0)?);
+    }
+
+    bail!("failed to derive sigv4a signing key")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use http::Request;
+    use p256::ecdsa::signature::Verifier;
+    use p256::ecdsa::VerifyingKey;
+    use p256::EncodedPoint;
+    use reqsign_core::time::DateTime;
+    use reqsign_core::FixedClock;
+    use reqsign_file_read_tokio::TokioFileRead;
+    use reqsign_http_send_reqwest::ReqwestHttpSend;
+
+    // Vectors from the AWS signing test suite `v4a/get-vanilla`.
+    const ACCESS_KEY_ID: &str = "AKIDEXAMPLE";
+    const SECRET_ACCESS_KEY: &str = "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY";
+    const PUBLIC_KEY_X: &str = "b6618f6a65740a99e650b33b6b4b5bd0d43b176d721a3edfea7e7d2d56d936b1";
+    const PUBLIC_KEY_Y: &str = "865ed22a7eadc9c5cb9d2cbaca1b3699139fedc5043dc6661864218330c8e518";
+
+    fn verifying_key() -> VerifyingKey {
+        let x = hex::decode(PUBLIC_KEY_X).unwrap();
+        let y = hex::decode(PUBLIC_KEY_Y).unwrap();
+        let point =
+            EncodedPoint::from_affine_coordinates(x.as_slice().into(), y.as_slice().into(), false);
+        VerifyingKey::from_encoded_point(&point).unwrap()
+    }
+
+    fn credential() -> Credential {
+        Credential {
+            access_key_id: ACCESS_KEY_ID.to_string(),
+            secret_access_key: SECRET_ACCESS_KEY.to_string(),
+            session_token: None,
+            expires_in: None,
+        }
+    }
+
+    fn time() -> DateTime {
+        chrono::DateTime::parse_from_rfc3339("2015-08-30T12:36:00Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+    }
+
+    fn verify(string_to_sign: &str, signature: &str) {
+        let signature = DerSignature::try_from(hex::decode(signature).unwrap().as_slice()).unwrap();
+        verifying_key()
+            .verify(string_to_sign.as_bytes(), &signature)
+            .expect("signature must be valid");
+    }
+
+    #[test]
+    fn test_generate_signing_key() -> Result<()> {
+        let key = generate_signing_key(ACCESS_KEY_ID, SECRET_ACCESS_KEY)?;
+        assert_eq!(key.verifying_key(), &verifying_key());
+
+        // The published signature must be verified by the derived key.
+        verify(
+            "AWS4-ECDSA-P256-SHA256\n20150830T123600Z\n20150830/service/aws4_request\n\
+             cf59db423e841c8b7e3444158185aa261b724a5c27cbe762676f3eed19f4dc02",
+            "3045022018b4e277d0281864beb51d3600e23f88510ea5031d68ddfbb68614b82a5eb7d2022100effb9c5f22ed9ef3ae0ab243d21f06bce82365bbb79529a07b6888c343ae5f8c",
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_in_header() -> Result<()> {
+        let ctx =
+            Context::new(TokioFileRead, ReqwestHttpSend::default()).with_clock(FixedClock(time()));
+        let req = Request::get("https://example.amazonaws.com/").body(())?;
+        let (mut parts, _) = req.into_parts();
+
+        let builder = V4aBuilder::new("service", "us-east-1");
+        builder
+            .build(&ctx, &mut parts, Some(&credential()), None)
+            .await?;
+
+        assert_eq!(parts.headers[X_AMZ_REGION_SET], "us-east-1");
+        let authorization = parts.headers[header::AUTHORIZATION].to_str()?;
+        let (prefix, signature) = authorization.split_once(", Signature=").unwrap();
+        assert_eq!(
+            prefix,
+            "AWS4-ECDSA-P256-SHA256 Credential=AKIDEXAMPLE/20150830/service/aws4_request, SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-region-set"
+        );
+
+        let creq = "GET\n/\n\nhost:example.amazonaws.com\nx-amz-content-sha256:UNSIGNED-PAYLOAD\n\
+                    x-amz-date:20150830T123600Z\nx-amz-region-set:us-east-1\n\n\
+                    host;x-amz-content-sha256;x-amz-date;x-amz-region-set\nUNSIGNED-PAYLOAD";
+        let string_to_sign = format!(
+            "AWS4-ECDSA-P256-SHA256\n20150830T123600Z\n20150830/service/aws4_request\n{}",
+            hex_sha256(creq.as_bytes())
+        );
+        verify(&string_to_sign, signature);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_build_in_query() -> Result<()> {
+        let ctx =
+            Context::new(TokioFileRead, ReqwestHttpSend::default()).with_clock(FixedClock(time()));
+        let req = Request::get("https://example.amazonaws.com/").body(())?;
+        let (mut parts, _) = req.into_parts();
+
+        let builder = V4aBuilder::new("service", "us-east-1");
+        builder
+            .build(
+                &ctx,
+                &mut parts,
+                Some(&credential()),
+                Some(Duration::from_secs(3600)),
+            )
+            .await?;
+
+        let query = parts.uri.query().unwrap();
+        let (query, signature) = query.split_once("&X-Amz-Signature=").unwrap();
+        assert_eq!(
+            query,
+            "X-Amz-Algorithm=AWS4-ECDSA-P256-SHA256&X-Amz-Credential=AKIDEXAMPLE%2F20150830%2Fservice%2Faws4_request&X-Amz-Date=20150830T123600Z&X-Amz-Expires=3600&X-Amz-Region-Set=us-east-1&X-Amz-SignedHeaders=host"
+        );
+
+        let creq = format!("GET\n/\n{query}\nhost:example.amazonaws.com\n\nhost\nUNSIGNED-PAYLOAD");
+        let string_to_sign = format!(
+            "AWS4-ECDSA-P256-SHA256\n20150830T123600Z\n20150830/service/aws4_request\n{}",
+            hex_sha256(creq.as_bytes())
+        );
+        verify(&string_to_sign, signature);
+        Ok(())
+    }
+}
@@ -47,7 +47,8 @@ async fn init_default_loader() -> Option<(Context, DefaultLoader, Builder)> {
     }
     .from_env(&context)
     .from_profile(&context)
-    .await;
+    .await
+    .expect("load profile must succeed");
 
     let region = config.region.as_deref().unwrap().to_string();
 
@@ -1,24 +1,25 @@
 use crate::Config;
 
+use bytes::Bytes;
 use http::HeaderValue;
 use http::Method;
 use http::Request;
-use reqwest::Client;
+use reqsign_core::Context;
 use serde::Deserialize;
 use std::str;
 
-pub async fn get_client_secret_token(config: &Config) -> anyhow::Result<Option<LoginResponse>> {
-    let (secret, tenant_id, client_id, authority_host) = match (
-        &config.client_secret,
-        &config.tenant_id,
-        &config.client_id,
-        &config.authority_host,
-    ) {
-        (Some(client_secret), Some(tenant_id), Some(client_id), Some(authority_host)) => {
-            (client_secret, tenant_id, client_id, authority_host)
-        }
-        _ => return Ok(None),
-    };
+pub async fn get_client_secret_token(
+    ctx: &Context,
+    config: &Config,
+) -> anyhow::Result<Option<LoginResponse>> {
+    let (secret, tenant_id, client_id) =
+        match (&config.client_secret, &config.tenant_id, &config.client_id) {
+            (Some(client_secret), Some(tenant_id), Some(client_id)) => {
+                (client_secret, tenant_id, client_id)
+            }
+            _ => return Ok(None),
+        };
+    let authority_host = config.authority_host();
     let url = &format!("{authority_host}/{tenant_id}/oauth2/v2.0/token");
     let scopes: &[&str] = &[STORAGE_TOKEN_SCOPE];
     let encoded_body: String = form_urlencoded::Serializer::new(String::new())
@@ -31,7 +32,7 @@ pub async fn get_client_secret_token(config: &Config) -> anyhow::Result<Option<L
     let mut req = Request::builder()
         .method(Method::POST)
         .uri(url.to_string())
-        .body(encoded_body)?;
+        .body(Bytes::from(encoded_body))?;
     req.headers_mut().insert(
         http::header::CONTENT_TYPE.as_str(),
         HeaderValue::from_static("application/x-www-form-urlencoded"),
@@ -40,9 +41,9 @@ pub async fn get_client_secret_token(config: &Config) -> anyhow::Result<Option<L
     req.headers_mut()
         .insert(API_VERSION, HeaderValue::from_static("2019-06-01"));
 
-    let res = Client::new().execute(req.try_into()?).await?;
+    let res = ctx.http_send_as_string(req).await?;
     let rsp_status = res.status();
-    let rsp_body = res.text().await?;
+    let rsp_body = res.into_body();
 
     if !rsp_status.is_success() {
         return Err(anyhow::anyhow!(
@@ -1,6 +1,56 @@
 use std::collections::HashMap;
 use std::env;
 
+use log::warn;
+use reqsign_core::time::{parse_rfc3339, DateTime};
+use reqsign_core::Error;
+
+/// Azure cloud that the storage account lives in.
+///
+/// Sovereign clouds use their own login authority and storage endpoint
+/// suffix, for example, `core.usgovcloudapi.net` for Azure Government.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum AzureCloud {
+    /// Azure public cloud.
+    #[default]
+    Public,
+    /// Azure Government.
+    UsGovernment,
+    /// Azure China operated by 21Vianet.
+    China,
+    /// Other clouds like Azure Stack Hub.
+    Custom {
+        /// Login authority host like `https://login.microsoftonline.com`.
+        authority_host: String,
+        /// Storage endpoint suffix like `core.windows.net`.
+        endpoint_suffix: String,
+    },
+}
+
+impl AzureCloud {
+    /// Login authority host of this cloud.
+    pub fn authority_host(&self) -> &str {
+        match self {
+            AzureCloud::Public => AZURE_PUBLIC_CLOUD,
+            AzureCloud::UsGovernment => "https://login.microsoftonline.us",
+            AzureCloud::China => "https://login.chinacloudapi.cn",
+            AzureCloud::Custom { authority_host, .. } => authority_host,
+        }
+    }
+
+    /// Storage endpoint suffix of this cloud.
+    pub fn endpoint_suffix(&self) -> &str {
+        match self {
+            AzureCloud::Public => "core.windows.net",
+            AzureCloud::UsGovernment => "core.usgovcloudapi.net",
+            AzureCloud::China => "core.chinacloudapi.cn",
+            AzureCloud::Custom {
+                endpoint_suffix, ..
+            } => endpoint_suffix,
+        }
+    }
+}
+
 /// Config carries all the configuration for Azure Storage services.
 #[derive(Clone, Default)]
 #[cfg_attr(test, derive(Debug))]
@@ -17,6 +67,38 @@ pub struct Config {
     ///
     /// - this field if it's `is_some`
     pub sas_token: Option<String>,
+    /// `bearer_token` will be loaded from
+    ///
+    /// - this field if it's `is_some`
+    /// - env value: [`AZURE_STORAGE_TOKEN`]
+    ///
+    /// A pre-fetched AAD access token, for example, minted by an earlier CI
+    /// step. It's used as is without any network call.
+    pub bearer_token: Option<String>,
+    /// `bearer_token_expires_on` will be loaded from
+    ///
+    /// - this field if it's `is_some`
+    /// - env value: [`AZURE_STORAGE_TOKEN_EXPIRES_ON`] in RFC 3339
+    ///
+    /// `bearer_token` never expires if it's not set, loading fails with
+    /// `Error::Expired` once it has passed.
+    pub bearer_token_expires_on: Option<DateTime>,
+    /// `cloud` the storage account lives in, default to [`AzureCloud::Public`].
+    ///
+    /// It decides the default `authority_host` and `endpoint_suffix`.
+    pub cloud: AzureCloud,
+    /// `endpoint_suffix` will be loaded from
+    ///
+    /// - this field if it's `is_some`
+    /// - connection string: `EndpointSuffix`
+    /// - default to the suffix of `cloud`, like `core.windows.net`
+    pub endpoint_suffix: Option<String>,
+    /// `blob_endpoint` will be loaded from
+    ///
+    /// - this field if it's `is_some`
+    /// - connection string: `BlobEndpoint`, or built from
+    ///   `DefaultEndpointsProtocol`, `AccountName` and `EndpointSuffix`
+    pub blob_endpoint: Option<String>,
     /// Specifies the object id associated with a user assigned managed service identity resource
     ///
     /// The values of client_id and msi_res_id are discarded
@@ -65,6 +147,7 @@ pub struct Config {
     /// - this field if it's `is_some`
     /// - env value: [`AZURE_AUTHORITY_HOST`]
     /// - profile config: `authority_host`
+    /// - default to the authority host of `cloud`
     pub authority_host: Option<String>,
 
     /// `client_secret` value will be loaded from:
@@ -79,10 +162,18 @@ pub const AZURE_TENANT_ID: &str = "AZURE_TENANT_ID";
 pub const AZURE_CLIENT_ID: &str = "AZURE_CLIENT_ID";
 pub const AZURE_CLIENT_SECRET: &str = "AZURE_CLIENT_SECRET";
 pub const AZURE_AUTHORITY_HOST: &str = "AZURE_AUTHORITY_HOST";
+pub const AZURE_STORAGE_TOKEN: &str = "AZURE_STORAGE_TOKEN";
+pub const AZURE_STORAGE_TOKEN_EXPIRES_ON: &str = "AZURE_STORAGE_TOKEN_EXPIRES_ON";
 const AZBLOB_ENDPOINT: &str = "AZBLOB_ENDPOINT";
 const AZBLOB_ACCOUNT_KEY: &str = "AZBLOB_ACCOUNT_KEY";
 const AZBLOB_ACCOUNT_NAME: &str = "AZBLOB_ACCOUNT_NAME";
 const AZURE_PUBLIC_CLOUD: &str = "https://login.microsoftonline.com";
+/// Well-known account of the Azurite storage emulator.
+const DEV_ACCOUNT_NAME: &str = "devstoreaccount1";
+/// Well-known key of the Azurite storage emulator.
+const DEV_ACCOUNT_KEY: &str =
+    "Eby8vdM02xNOcqFlqUwJPLlmEtlCDXJ1OUzFT50uSRZ6IFsuFq2UVErCz4I6tq/K1SZFPTOtr/KBHBeksoGMGw==";
+const DEV_BLOB_ENDPOINT: &str = "http://127.0.0.1:10000/devstoreaccount1";
 
 impl Config {
     /// Load config from env.
@@ -114,16 +205,209 @@ impl Config {
             self.account_name = Some(v.to_string());
         }
 
+        // Leave it unset otherwise, so it keeps following `cloud`.
         if let Some(v) = envs.get(AZURE_AUTHORITY_HOST) {
             self.authority_host = Some(v.to_string());
-        } else {
-            self.authority_host = Some(AZURE_PUBLIC_CLOUD.to_string());
         }
 
         if let Some(v) = envs.get(AZURE_CLIENT_SECRET) {
             self.client_secret = Some(v.to_string());
         }
 
+        if let Some(v) = envs.get(AZURE_STORAGE_TOKEN) {
+            self.bearer_token = Some(v.to_string());
+        }
+
+        if let Some(v) = envs.get(AZURE_STORAGE_TOKEN_EXPIRES_ON) {
+            match parse_rfc3339(v) {
+                Ok(v) => self.bearer_token_expires_on = Some(v),
+                Err(err) => warn!("ignore invalid {AZURE_STORAGE_TOKEN_EXPIRES_ON} {v}: {err:?}"),
+            }
+        }
+
         self
     }
+
+    /// Load config from an Azure Storage connection string like
+    /// `DefaultEndpointsProtocol=https;AccountName=..;AccountKey=..;EndpointSuffix=core.windows.net`.
+    ///
+    /// `UseDevelopmentStorage=true` is expanded to the well-known account of
+    /// the Azurite emulator. Values set in the connection string override
+    /// the current ones.
+    ///
+    /// - [Configure Azure Storage connection strings](https://learn.microsoft.com/en-us/azure/storage/common/storage-configure-connection-string)
+    pub fn from_connection_string(mut self, conn: &str) -> anyhow::Result<Self> {
+        let mut protocol = None;
+        for pair in conn.split(';').map(str::trim).filter(|v| !v.is_empty()) {
+            // Values like account keys and SAS tokens could contain `=`.
+            let (key, value) = pair.split_once('=').ok_or_else(|| {
+                Error::Config(format!("invalid connection string segment: {pair}"))
+            })?;
+            let value = value.trim().to_string();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "usedevelopmentstorage" if value.eq_ignore_ascii_case("true") => {
+                    self.account_name = Some(DEV_ACCOUNT_NAME.to_string());
+                    self.account_key = Some(DEV_ACCOUNT_KEY.to_string());
+                    self.blob_endpoint = Some(DEV_BLOB_ENDPOINT.to_string());
+                }
+                "defaultendpointsprotocol" => protocol = Some(value),
+                "accountname" => self.account_name = Some(value),
+                "accountkey" => self.account_key = Some(value),
+                "sharedaccesssignature" => {
+                    self.sas_token = Some(value.trim_start_matches('?').to_string())
+                }
+                "endpointsuffix" => self.endpoint_suffix = Some(value),
+                "blobendpoint" => {
+                    self.blob_endpoint = Some(value.trim_end_matches('/').to_string())
+                }
+                // Endpoints of other services are not used by the signer.
+                _ => {}
+            }
+        }
+
+        if self.blob_endpoint.is_none() {
+            if let Some(account_name) = &self.account_name {
+                let protocol = protocol.as_deref().unwrap_or("https");
+                let suffix = self.endpoint_suffix();
+                self.blob_endpoint = Some(format!("{protocol}://{account_name}.blob.{suffix}"));
+            }
+        }
+
+        Ok(self)
+    }
+
+    /// Get the login authority host, falls back to the one of `cloud`.
+    pub(crate) fn authority_host(&self) -> &str {
+        self.authority_host
+            .as_deref()
+            .unwrap_or_else(|| self.cloud.authority_host())
+    }
+
+    /// Get the storage endpoint suffix, falls back to the one of `cloud`.
+    pub(crate) fn endpoint_suffix(&self) -> &str {
+        self.endpoint_suffix
+            .as_deref()
+            .unwrap_or_else(|| self.cloud.endpoint_suffix())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_connection_string() -> anyhow::Result<()> {
+        let config = Config::default().from_connection_string(
+            "DefaultEndpointsProtocol=https;AccountName=myaccount;AccountKey=bXlrZXk=;EndpointSuffix=core.windows.net",
+        )?;
+        assert_eq!(config.account_name.as_deref(), Some("myaccount"));
+        assert_eq!(config.account_key.as_deref(), Some("bXlrZXk="));
+        assert_eq!(config.sas_token, None);
+        assert_eq!(config.endpoint_suffix.as_deref(), Some("core.windows.net"));
+        assert_eq!(
+            config.blob_endpoint.as_deref(),
+            Some("https://myaccount.blob.core.windows.net")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_connection_string_with_sas() -> anyhow::Result<()> {
+        let config = Config::default().from_connection_string(
+            "BlobEndpoint=https://myaccount.blob.core.windows.net/;\
+             SharedAccessSignature=sv=2021-06-08&ss=b&srt=co&sp=rl&se=2024-01-01T00:00:00Z&sig=c2ln%3D;",
+        )?;
+        assert_eq!(config.account_key, None);
+        assert_eq!(
+            config.sas_token.as_deref(),
+            Some("sv=2021-06-08&ss=b&srt=co&sp=rl&se=2024-01-01T00:00:00Z&sig=c2ln%3D")
+        );
+        assert_eq!(
+            config.blob_endpoint.as_deref(),
+            Some("https://myaccount.blob.core.windows.net")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_connection_string_with_development_storage() -> anyhow::Result<()> {
+        let config = Config::default().from_connection_string("UseDevelopmentStorage=true")?;
+        assert_eq!(config.account_name.as_deref(), Some(DEV_ACCOUNT_NAME));
+        assert_eq!(config.account_key.as_deref(), Some(DEV_ACCOUNT_KEY));
+        assert_eq!(
+            config.blob_endpoint.as_deref(),
+            Some("http://127.0.0.1:10000/devstoreaccount1")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_connection_string_invalid() {
+        let err = Config::default()
+            .from_connection_string("AccountName=myaccount;AccountKey")
+            .expect_err("must fail");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Config(_))
+        ));
+    }
+
+    #[test]
+    fn test_sovereign_cloud() -> anyhow::Result<()> {
+        let config = Config {
+            cloud: AzureCloud::UsGovernment,
+            ..Default::default()
+        }
+        .from_connection_string("AccountName=myaccount;AccountKey=bXlrZXk=")?;
+        assert_eq!(config.authority_host(), "https://login.microsoftonline.us");
+        assert_eq!(
+            config.blob_endpoint.as_deref(),
+            Some("https://myaccount.blob.core.usgovcloudapi.net")
+        );
+
+        let config = Config {
+            cloud: AzureCloud::China,
+            ..Default::default()
+        };
+        assert_eq!(config.authority_host(), "https://login.chinacloudapi.cn");
+        assert_eq!(config.endpoint_suffix(), "core.chinacloudapi.cn");
+
+        let config = Config {
+            cloud: AzureCloud::Custom {
+                authority_host: "https://login.local.azurestack.external".to_string(),
+                endpoint_suffix: "local.azurestack.external".to_string(),
+            },
+            authority_host: Some("https://adfs.local.azurestack.external/adfs".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.authority_host(),
+            "https://adfs.local.azurestack.external/adfs"
+        );
+        assert_eq!(config.endpoint_suffix(), "local.azurestack.external");
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_env_keeps_authority_host_of_cloud() {
+        temp_env::with_var_unset(AZURE_AUTHORITY_HOST, || {
+            let mut config = Config::default().from_env();
+            assert_eq!(config.authority_host, None);
+            assert_eq!(config.authority_host(), "https://login.microsoftonline.com");
+
+            // Cloud set after from_env still decides the authority host.
+            config.cloud = AzureCloud::China;
+            assert_eq!(config.authority_host(), "https://login.chinacloudapi.cn");
+        });
+
+        temp_env::with_var(
+            AZURE_AUTHORITY_HOST,
+            Some("https://login.example.com"),
+            || {
+                let mut config = Config::default().from_env();
+                config.cloud = AzureCloud::UsGovernment;
+                assert_eq!(config.authority_host(), "https://login.example.com");
+            },
+        );
+    }
 }
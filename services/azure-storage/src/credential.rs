@@ -1,8 +1,17 @@
-use reqsign_core::time::DateTime;
+use log::warn;
+use reqsign_core::time::{now, parse_rfc3339, DateTime};
+use reqsign_core::utils::Redact;
+use std::fmt::{Debug, Formatter};
+use std::time::Duration;
+
+/// Tolerance for the clock skew against Azure.
+///
+/// Credentials are refreshed this long before they expire, and SAS tokens
+/// starting within it are accepted.
+pub(crate) const CLOCK_SKEW: Duration = Duration::from_secs(20);
 
 /// Credential that holds the access_key and secret_key.
 #[derive(Clone)]
-#[cfg_attr(test, derive(Debug))]
 pub enum Credential {
     /// Credential via account key
     ///
@@ -21,22 +30,74 @@ pub enum Credential {
     BearerToken(String, DateTime),
 }
 
+impl Debug for Credential {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Credential::SharedKey(account_name, account_key) => f
+                .debug_tuple("SharedKey")
+                .field(account_name)
+                .field(&Redact::from(account_key))
+                .finish(),
+            Credential::SharedAccessSignature(sas_token) => f
+                .debug_tuple("SharedAccessSignature")
+                .field(&Redact::from(sas_token))
+                .finish(),
+            Credential::BearerToken(token, expires_on) => f
+                .debug_tuple("BearerToken")
+                .field(&Redact::from(token))
+                .field(expires_on)
+                .finish(),
+        }
+    }
+}
+
 impl Credential {
     /// is current cred is valid?
     pub fn is_valid(&self) -> bool {
+        self.is_valid_at(now())
+    }
+
+    /// is cred valid at `now`?
+    pub fn is_valid_at(&self, now: DateTime) -> bool {
         if self.is_empty() {
             return false;
         }
-        if let Credential::BearerToken(_, expires_on) = self {
-            let buffer = chrono::TimeDelta::try_seconds(20).expect("in bounds");
-            if expires_on < &(chrono::Utc::now() + buffer) {
+        if let Some(expires_on) = self.expires_in() {
+            if expires_on < now + CLOCK_SKEW {
                 return false;
             }
         };
+        // SAS tokens could be issued with a start time in the future.
+        if let Credential::SharedAccessSignature(sas_token) = self {
+            if sas_time(sas_token, "st").is_some_and(|st| now + CLOCK_SKEW < st) {
+                return false;
+            }
+        }
 
         true
     }
 
+    /// Get the expiration time of this credential.
+    ///
+    /// The `se` param is used for SAS tokens. Returns `None` for shared key
+    /// and SAS tokens without `se`.
+    pub fn expires_in(&self) -> Option<DateTime> {
+        match self {
+            Credential::BearerToken(_, expires_on) => Some(*expires_on),
+            Credential::SharedAccessSignature(sas_token) => sas_time(sas_token, "se"),
+            Credential::SharedKey(_, _) => None,
+        }
+    }
+
+    /// Get the remaining time to live of this credential at `now`.
+    ///
+    /// Returns `None` if this credential doesn't expire, and
+    /// `Duration::ZERO` if it has already expired.
+    pub fn ttl(&self, now: DateTime) -> Option<Duration> {
+        self.expires_in()
+            .map(|v| (v - now).to_std().unwrap_or(Duration::ZERO))
+    }
+
     fn is_empty(&self) -> bool {
         match self {
             Credential::SharedKey(account_name, account_key) => {
@@ -47,3 +108,93 @@ impl Credential {
         }
     }
 }
+
+/// Parse the time param `key` like `se` or `st` of the SAS token.
+///
+/// Azure accepts `YYYY-MM-DD`, `YYYY-MM-DDThh:mmZ` and `YYYY-MM-DDThh:mm:ssZ`
+/// in UTC.
+pub(crate) fn sas_time(sas_token: &str, key: &str) -> Option<DateTime> {
+    let sas_token = sas_token.strip_prefix('?').unwrap_or(sas_token);
+    let (_, v) = form_urlencoded::parse(sas_token.as_bytes()).find(|(k, _)| k == key)?;
+
+    let parsed = parse_rfc3339(&v)
+        .ok()
+        .or_else(|| {
+            chrono::NaiveDateTime::parse_from_str(&v, "%Y-%m-%dT%H:%MZ")
+                .ok()
+                .map(|v| v.and_utc())
+        })
+        .or_else(|| {
+            chrono::NaiveDate::parse_from_str(&v, "%Y-%m-%d")
+                .ok()
+                .map(|v| v.and_time(chrono::NaiveTime::MIN).and_utc())
+        });
+    if parsed.is_none() {
+        warn!("ignore invalid sas token param {key}={v}");
+    }
+    parsed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credential_debug_redacted() {
+        let secret = "c2VjcmV0LWFjY291bnQta2V5LWZvci10ZXN0aW5n";
+
+        let cred = Credential::SharedKey("account".to_string(), secret.to_string());
+        let output = format!("{cred:?}");
+        assert!(output.contains("account"));
+        assert!(!output.contains(secret));
+
+        let cred = Credential::SharedAccessSignature(secret.to_string());
+        assert!(!format!("{cred:?}").contains(secret));
+
+        let cred = Credential::BearerToken(secret.to_string(), DateTime::default());
+        assert!(!format!("{cred:?}").contains(secret));
+    }
+
+    #[test]
+    fn test_sas_token_validity() {
+        let format = |v: DateTime| v.format("%Y-%m-%dT%H:%M:%SZ").to_string();
+        let hour = chrono::TimeDelta::try_hours(1).unwrap();
+
+        let expired = format!("sv=2022-11-02&se={}&sig=c2ln", format(now() - hour));
+        let cred = Credential::SharedAccessSignature(expired);
+        assert!(!cred.is_valid());
+        assert!(cred.expires_in().is_some());
+
+        let valid = format!(
+            "?sv=2022-11-02&st={}&se={}&sig=c2ln",
+            format(now() - hour),
+            format(now() + hour)
+        );
+        assert!(Credential::SharedAccessSignature(valid).is_valid());
+
+        let not_yet_valid = format!("sv=2022-11-02&st={}&sig=c2ln", format(now() + hour));
+        assert!(!Credential::SharedAccessSignature(not_yet_valid).is_valid());
+
+        // Tokens without se never expire.
+        let cred = Credential::SharedAccessSignature("sv=2022-11-02&sig=c2ln".to_string());
+        assert!(cred.is_valid());
+        assert_eq!(cred.expires_in(), None);
+    }
+
+    #[test]
+    fn test_sas_time_formats() {
+        let expected = parse_rfc3339("2024-01-02T03:04:00Z").unwrap();
+        for token in [
+            "se=2024-01-02T03%3A04%3A00Z",
+            "se=2024-01-02T03:04:00Z",
+            "se=2024-01-02T03:04Z",
+        ] {
+            assert_eq!(sas_time(token, "se"), Some(expected), "{token}");
+        }
+        assert_eq!(
+            sas_time("se=2024-01-02", "se"),
+            Some(parse_rfc3339("2024-01-02T00:00:00Z").unwrap())
+        );
+        assert_eq!(sas_time("se=invalid", "se"), None);
+    }
+}
@@ -1,9 +1,10 @@
 use std::str;
 
+use bytes::Bytes;
 use http::HeaderValue;
 use http::Method;
 use http::Request;
-use reqwest::Client;
+use reqsign_core::Context;
 use reqwest::Url;
 use serde::Deserialize;
 
@@ -15,7 +16,11 @@ const MSI_ENDPOINT: &str = "http://169.254.169.254/metadata/identity/oauth2/toke
 /// Gets an access token for the specified resource and configuration.
 ///
 /// See <https://learn.microsoft.com/en-us/azure/app-service/overview-managed-identity?tabs=portal,http#using-the-rest-protocol>
-pub async fn get_access_token(resource: &str, config: &Config) -> anyhow::Result<AccessToken> {
+pub async fn get_access_token(
+    ctx: &Context,
+    resource: &str,
+    config: &Config,
+) -> anyhow::Result<AccessToken> {
     let endpoint = config.endpoint.as_deref().unwrap_or(MSI_ENDPOINT);
     let mut query_items = vec![("api-version", MSI_API_VERSION), ("resource", resource)];
 
@@ -35,7 +40,7 @@ pub async fn get_access_token(resource: &str, config: &Config) -> anyhow::Result
     let mut req = Request::builder()
         .method(Method::GET)
         .uri(url.to_string())
-        .body("")?;
+        .body(Bytes::new())?;
 
     req.headers_mut()
         .insert("metadata", HeaderValue::from_static("true"));
@@ -45,9 +50,9 @@ pub async fn get_access_token(resource: &str, config: &Config) -> anyhow::Result
             .insert("x-identity-header", HeaderValue::from_str(secret)?);
     };
 
-    let res = Client::new().execute(req.try_into()?).await?;
+    let res = ctx.http_send_as_string(req).await?;
     let rsp_status = res.status();
-    let rsp_body = res.text().await?;
+    let rsp_body = res.into_body();
 
     if !rsp_status.is_success() {
         return Err(anyhow::anyhow!("Failed to get token from IMDS endpoint"));
@@ -3,6 +3,8 @@
 //! Use [`azure::storage::Signer`][crate::azure::storage::Signer]
 
 mod account_sas;
+mod blob_sas;
+pub use blob_sas::presign_blob;
 mod client_secret_credential;
 mod constants;
 mod imds_credential;
@@ -12,6 +14,7 @@ mod signer;
 pub use signer::Signer;
 
 mod config;
+pub use config::AzureCloud;
 pub use config::Config;
 
 mod credential;
@@ -1,11 +1,12 @@
 use std::sync::Arc;
-use std::sync::Mutex;
 
 use anyhow::Result;
+use tokio::sync::Mutex;
 
-use reqsign_core::time::{now, parse_rfc3339};
+use reqsign_core::time::{format_rfc3339, now, parse_rfc3339, DateTime};
+use reqsign_core::{Context, Error};
 
-use super::credential::Credential;
+use super::credential::{sas_time, Credential, CLOCK_SKEW};
 use super::imds_credential;
 use super::{config::Config, workload_identity_credential};
 
@@ -28,41 +29,65 @@ impl Loader {
     }
 
     /// Load credential.
-    pub async fn load(&self) -> Result<Option<Credential>> {
+    ///
+    /// Token requests to Azure AD and IMDS are sent via `ctx`.
+    pub async fn load(&self, ctx: &Context) -> Result<Option<Credential>> {
+        // Hold the lock while loading so that only one refresh is in flight.
+        let mut lock = self.credential.lock().await;
+
         // Return cached credential if it's valid.
-        match self.credential.lock().expect("lock poisoned").clone() {
+        match lock.clone() {
             Some(cred) if cred.is_valid() => return Ok(Some(cred)),
             _ => (),
         }
-        let cred = self.load_inner().await?;
 
-        let mut lock = self.credential.lock().expect("lock poisoned");
+        // Keep the cached credential untouched if loading failed.
+        let cred = self.load_inner(ctx).await?;
         lock.clone_from(&cred);
 
         Ok(cred)
     }
 
-    async fn load_inner(&self) -> Result<Option<Credential>> {
+    async fn load_inner(&self, ctx: &Context) -> Result<Option<Credential>> {
         if let Some(cred) = self.load_via_config().await? {
             return Ok(Some(cred));
         }
 
-        if let Some(cred) = self.load_via_client_secret().await? {
+        if let Some(cred) = self.load_via_client_secret(ctx).await? {
             return Ok(Some(cred));
         }
 
-        if let Some(cred) = self.load_via_workload_identity().await? {
+        if let Some(cred) = self.load_via_workload_identity(ctx).await? {
             return Ok(Some(cred));
         }
 
         // try to load credential using AAD(Azure Active Directory) authenticate on Azure VM
         // we may get an error if not running on Azure VM
         // see https://learn.microsoft.com/en-us/azure/app-service/overview-managed-identity?tabs=portal,http#using-the-rest-protocol
-        self.load_via_imds().await
+        self.load_via_imds(ctx).await
     }
 
     async fn load_via_config(&self) -> Result<Option<Credential>> {
         if let Some(token) = &self.config.sas_token {
+            // Fail early instead of getting an unhelpful 403 from Azure.
+            let now = now();
+            if let Some(se) = sas_time(token, "se").filter(|se| *se <= now) {
+                return Err(Error::Expired(format!(
+                    "sas token has expired at {}",
+                    format_rfc3339(se)
+                ))
+                .into());
+            }
+            // Tokens starting a few seconds ahead are accepted in case the
+            // local clock is behind.
+            if let Some(st) = sas_time(token, "st").filter(|st| *st > now + CLOCK_SKEW) {
+                return Err(Error::Expired(format!(
+                    "sas token is not valid until {}",
+                    format_rfc3339(st)
+                ))
+                .into());
+            }
+
             let cred = Credential::SharedAccessSignature(token.clone());
             return Ok(Some(cred));
         }
@@ -72,12 +97,31 @@ impl Loader {
             return Ok(Some(cred));
         }
 
+        if let Some(token) = &self.config.bearer_token {
+            let expires_on = self
+                .config
+                .bearer_token_expires_on
+                .unwrap_or(DateTime::MAX_UTC);
+            // The static token can't be refreshed, fail instead of sending
+            // requests that Azure will reject.
+            if expires_on <= now() {
+                return Err(Error::Expired(format!(
+                    "bearer token has expired at {}",
+                    format_rfc3339(expires_on)
+                ))
+                .into());
+            }
+            let cred = Credential::BearerToken(token.clone(), expires_on);
+            return Ok(Some(cred));
+        }
+
         Ok(None)
     }
 
-    async fn load_via_imds(&self) -> Result<Option<Credential>> {
+    async fn load_via_imds(&self, ctx: &Context) -> Result<Option<Credential>> {
         let token =
-            imds_credential::get_access_token("https://storage.azure.com/", &self.config).await?;
+            imds_credential::get_access_token(ctx, "https://storage.azure.com/", &self.config)
+                .await?;
         let expires_on = if token.expires_on.is_empty() {
             now() + chrono::TimeDelta::try_minutes(10).expect("in bounds")
         } else {
@@ -88,9 +132,9 @@ impl Loader {
         Ok(cred)
     }
 
-    async fn load_via_workload_identity(&self) -> Result<Option<Credential>> {
+    async fn load_via_workload_identity(&self, ctx: &Context) -> Result<Option<Credential>> {
         let workload_identity_token =
-            workload_identity_credential::get_workload_identity_token(&self.config).await?;
+            workload_identity_credential::get_workload_identity_token(ctx, &self.config).await?;
         match workload_identity_token {
             Some(token) => {
                 let expires_on_duration = match token.expires_on {
@@ -106,9 +150,173 @@ impl Loader {
         }
     }
 
-    async fn load_via_client_secret(&self) -> Result<Option<Credential>> {
-        super::client_secret_credential::get_client_secret_token(&self.config)
+    async fn load_via_client_secret(&self, ctx: &Context) -> Result<Option<Credential>> {
+        super::client_secret_credential::get_client_secret_token(ctx, &self.config)
             .await
             .map(|token| token.map(Into::into))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqsign_core::MockContext;
+
+    #[tokio::test]
+    async fn test_load_via_config_bearer_token() -> Result<()> {
+        let expires_on = now() + chrono::TimeDelta::try_hours(1).expect("in bounds");
+        let loader = Loader::new(Config {
+            bearer_token: Some("token".to_string()),
+            bearer_token_expires_on: Some(expires_on),
+            ..Default::default()
+        });
+
+        match loader
+            .load(&MockContext::new().context())
+            .await?
+            .expect("credential must be loaded")
+        {
+            Credential::BearerToken(token, v) => {
+                assert_eq!(token, "token");
+                assert_eq!(v, expires_on);
+            }
+            cred => panic!("unexpected credential: {cred:?}"),
+        }
+
+        // Tokens without expiry never expire.
+        let loader = Loader::new(Config {
+            bearer_token: Some("token".to_string()),
+            ..Default::default()
+        });
+        let cred = loader
+            .load(&MockContext::new().context())
+            .await?
+            .expect("credential must be loaded");
+        assert!(cred.is_valid());
+
+        // Expired tokens can't be refreshed and must fail.
+        let loader = Loader::new(Config {
+            bearer_token: Some("token".to_string()),
+            bearer_token_expires_on: Some(now() - chrono::TimeDelta::try_hours(1).unwrap()),
+            ..Default::default()
+        });
+        let err = loader
+            .load(&MockContext::new().context())
+            .await
+            .expect_err("expired bearer token must fail");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Expired(_))
+        ));
+        assert!(err.to_string().contains("bearer token has expired at"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_via_config_expired_sas_token() -> Result<()> {
+        let loader = Loader::new(Config {
+            sas_token: Some(
+                "sv=2021-01-01&ss=b&srt=c&sp=rl&se=2022-01-01T11:00:14Z&sig=c2ln".to_string(),
+            ),
+            ..Default::default()
+        });
+        let err = loader
+            .load(&MockContext::new().context())
+            .await
+            .expect_err("expired sas token must fail");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Expired(_))
+        ));
+        assert!(err
+            .to_string()
+            .contains("sas token has expired at 2022-01-01T11:00:14Z"));
+
+        let st = (now() + chrono::TimeDelta::try_hours(1).unwrap()).format("%Y-%m-%dT%H:%M:%SZ");
+        let loader = Loader::new(Config {
+            sas_token: Some(format!("sv=2021-01-01&st={st}&sig=c2ln")),
+            ..Default::default()
+        });
+        let err = loader
+            .load(&MockContext::new().context())
+            .await
+            .expect_err("future sas token must fail");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Expired(_))
+        ));
+        assert!(err.to_string().contains("sas token is not valid until"));
+
+        let st = (now() + chrono::TimeDelta::try_seconds(5).unwrap()).format("%Y-%m-%dT%H:%M:%SZ");
+        let loader = Loader::new(Config {
+            sas_token: Some(format!("sv=2021-01-01&st={st}&sig=c2ln")),
+            ..Default::default()
+        });
+        let cred = loader
+            .load(&MockContext::new().context())
+            .await?
+            .expect("sas token starting within the clock skew must be loaded");
+        assert!(cred.is_valid());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_via_client_secret_with_context() -> Result<()> {
+        let mock = MockContext::new().with_response(
+            http::Method::POST,
+            "https://login.microsoftonline.com/tenant_id/oauth2/v2.0/token",
+            http::StatusCode::OK,
+            r#"{"token_type":"Bearer","expires_in":3600,"access_token":"token"}"#,
+        );
+        let ctx = mock.context().with_user_agent("my-app/1.0");
+        let loader = Loader::new(Config {
+            tenant_id: Some("tenant_id".to_string()),
+            client_id: Some("client_id".to_string()),
+            client_secret: Some("client_secret".to_string()),
+            ..Default::default()
+        });
+
+        match loader.load(&ctx).await?.expect("credential must be loaded") {
+            Credential::BearerToken(token, _) => assert_eq!(token, "token"),
+            cred => panic!("unexpected credential: {cred:?}"),
+        }
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(
+            requests[0].headers()[http::header::USER_AGENT],
+            "my-app/1.0"
+        );
+        assert!(String::from_utf8_lossy(requests[0].body()).contains("client_secret=client_secret"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_load_via_imds_with_context() -> Result<()> {
+        let mock = MockContext::new().with_response(
+            http::Method::GET,
+            "http://169.254.169.254/metadata/identity/oauth2/token?api-version=2019-08-01&resource=https%3A%2F%2Fstorage.azure.com%2F",
+            http::StatusCode::OK,
+            r#"{"access_token":"token","expires_on":"","token_type":"Bearer","resource":"https://storage.azure.com/"}"#,
+        );
+        let ctx = mock.context();
+
+        match Loader::new(Config::default())
+            .load(&ctx)
+            .await?
+            .expect("credential must be loaded")
+        {
+            Credential::BearerToken(token, _) => assert_eq!(token, "token"),
+            cred => panic!("unexpected credential: {cred:?}"),
+        }
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].headers()["metadata"], "true");
+        // The default User-Agent of reqsign applies as well.
+        assert!(requests[0].headers()[http::header::USER_AGENT]
+            .to_str()?
+            .starts_with("reqsign/"));
+        Ok(())
+    }
+}
@@ -1,9 +1,10 @@
-use std::{fs, str};
+use std::str;
 
+use bytes::Bytes;
 use http::HeaderValue;
 use http::Method;
 use http::Request;
-use reqwest::Client;
+use reqsign_core::Context;
 use reqwest::Url;
 use serde::Deserialize;
 
@@ -14,20 +15,21 @@ const STORAGE_TOKEN_SCOPE: &str = "https://storage.azure.com/.default";
 /// Gets an access token for the specified resource and configuration.
 ///
 /// See <https://learn.microsoft.com/en-us/azure/app-service/overview-managed-identity?tabs=portal,http#using-the-rest-protocol>
-pub async fn get_workload_identity_token(config: &Config) -> anyhow::Result<Option<LoginResponse>> {
-    let (token_file, tenant_id, client_id, authority_host) = match (
+pub async fn get_workload_identity_token(
+    ctx: &Context,
+    config: &Config,
+) -> anyhow::Result<Option<LoginResponse>> {
+    let (token_file, tenant_id, client_id) = match (
         &config.federated_token_file,
         &config.tenant_id,
         &config.client_id,
-        &config.authority_host,
     ) {
-        (Some(token_file), Some(tenant_id), Some(client_id), Some(authority_host)) => {
-            (token_file, tenant_id, client_id, authority_host)
-        }
+        (Some(token_file), Some(tenant_id), Some(client_id)) => (token_file, tenant_id, client_id),
         _ => return Ok(None),
     };
+    let authority_host = config.authority_host();
 
-    let token = fs::read_to_string(token_file)?;
+    let token = ctx.file_read_as_string(token_file).await?;
     let url = Url::parse(authority_host)?.join(&format!("/{tenant_id}/oauth2/v2.0/token"))?;
     let scopes: &[&str] = &[STORAGE_TOKEN_SCOPE];
     let encoded_body: String = form_urlencoded::Serializer::new(String::new())
@@ -44,7 +46,7 @@ pub async fn get_workload_identity_token(config: &Config) -> anyhow::Result<Opti
     let mut req = Request::builder()
         .method(Method::POST)
         .uri(url.to_string())
-        .body(encoded_body)?;
+        .body(Bytes::from(encoded_body))?;
     req.headers_mut().insert(
         http::header::CONTENT_TYPE.as_str(),
         HeaderValue::from_static("application/x-www-form-urlencoded"),
@@ -53,9 +55,9 @@ pub async fn get_workload_identity_token(config: &Config) -> anyhow::Result<Opti
     req.headers_mut()
         .insert(API_VERSION, HeaderValue::from_static("2019-06-01"));
 
-    let res = Client::new().execute(req.try_into()?).await?;
+    let res = ctx.http_send_as_string(req).await?;
     let rsp_status = res.status();
-    let rsp_body = res.text().await?;
+    let rsp_body = res.into_body();
 
     if !rsp_status.is_success() {
         return Err(anyhow::anyhow!(
@@ -9,11 +9,20 @@ use log::debug;
 use log::warn;
 use percent_encoding::utf8_percent_encode;
 use percent_encoding::NON_ALPHANUMERIC;
+use reqsign_azure_storage::presign_blob;
 use reqsign_azure_storage::Config;
 use reqsign_azure_storage::Loader;
 use reqsign_azure_storage::Signer;
+use reqsign_core::time::now;
+use reqsign_core::Context;
+use reqsign_file_read_tokio::TokioFileRead;
+use reqsign_http_send_reqwest::ReqwestHttpSend;
 use reqwest::Client;
 
+fn context() -> Context {
+    Context::new(TokioFileRead, ReqwestHttpSend::default())
+}
+
 fn init_signer() -> Option<(Loader, Signer)> {
     let _ = env_logger::builder().is_test(true).try_init();
 
@@ -61,7 +70,7 @@ async fn test_head_blob() -> Result<()> {
     let req = builder.body("")?;
 
     let cred = loader
-        .load()
+        .load(&context())
         .await
         .expect("load credential must success")
         .unwrap();
@@ -110,7 +119,7 @@ async fn test_head_object_with_encoded_characters() -> Result<()> {
     ))?;
 
     let cred = loader
-        .load()
+        .load(&context())
         .await
         .expect("load credential must success")
         .unwrap();
@@ -163,7 +172,7 @@ async fn test_list_container_blobs() -> Result<()> {
         let req = builder.body("")?;
 
         let cred = loader
-            .load()
+            .load(&context())
             .await
             .expect("load credential must success")
             .unwrap();
@@ -210,7 +219,7 @@ async fn test_can_head_blob_with_sas() -> Result<()> {
     let req = builder.body("")?;
 
     let cred = loader
-        .load()
+        .load(&context())
         .await
         .expect("load credential must success")
         .unwrap();
@@ -236,6 +245,54 @@ async fn test_can_head_blob_with_sas() -> Result<()> {
     Ok(())
 }
 
+#[tokio::test]
+async fn test_can_head_blob_with_presigned_url() -> Result<()> {
+    if init_signer().is_none() {
+        warn!("REQSIGN_AZURE_STORAGE_ON_TEST is not set, skipped");
+        return Ok(());
+    }
+
+    let url =
+        &env::var("REQSIGN_AZURE_STORAGE_URL").expect("env REQSIGN_AZURE_STORAGE_URL must set");
+    // The url points to the container, like `https://{account}.blob.core.windows.net/{container}`.
+    let (endpoint, container) = url
+        .trim_end_matches('/')
+        .rsplit_once('/')
+        .expect("url must contain the container");
+    let config = Config {
+        account_name: Some(
+            env::var("REQSIGN_AZURE_STORAGE_ACCOUNT_NAME")
+                .expect("env REQSIGN_AZURE_STORAGE_ACCOUNT_NAME must set"),
+        ),
+        account_key: Some(
+            env::var("REQSIGN_AZURE_STORAGE_ACCOUNT_KEY")
+                .expect("env REQSIGN_AZURE_STORAGE_ACCOUNT_KEY must set"),
+        ),
+        blob_endpoint: Some(endpoint.to_string()),
+        ..Default::default()
+    };
+
+    let url = presign_blob(
+        &config,
+        container,
+        "not_exist_dir/not exist file",
+        "r",
+        now() + Duration::from_secs(60),
+    )?;
+    debug!("presigned url: {url}");
+
+    let resp = Client::new()
+        .head(url)
+        .send()
+        .await
+        .expect("request must success");
+
+    debug!("got response: {:?}", resp);
+    // Azure rejects a wrong string to sign with 403 before looking up the blob.
+    assert_eq!(StatusCode::NOT_FOUND, resp.status());
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_can_list_container_blobs() -> Result<()> {
     // API https://learn.microsoft.com/en-us/rest/api/storageservices/list-blobs?tabs=azure-ad
@@ -264,7 +321,7 @@ async fn test_can_list_container_blobs() -> Result<()> {
         let req = builder.body("")?;
 
         let cred = loader
-            .load()
+            .load(&context())
             .await
             .expect("load credential must success")
             .unwrap();
@@ -307,7 +364,7 @@ async fn test_head_blob_with_ldms() -> Result<()> {
     };
     let loader = Loader::new(config);
     let cred = loader
-        .load()
+        .load(&context())
         .await
         .expect("load credential must success")
         .unwrap();
@@ -359,7 +416,7 @@ async fn test_can_list_container_blobs_with_ldms() -> Result<()> {
     };
     let loader = Loader::new(config);
     let cred = loader
-        .load()
+        .load(&context())
         .await
         .expect("load credential must success")
         .unwrap();
@@ -433,7 +490,7 @@ async fn test_head_blob_with_client_secret() -> Result<()> {
     let loader = Loader::new(config);
 
     let cred = loader
-        .load()
+        .load(&context())
         .await
         .expect("load credential must success")
         .unwrap();
@@ -499,7 +556,7 @@ async fn test_can_list_container_blobs_client_secret() -> Result<()> {
     let loader = Loader::new(config);
 
     let cred = loader
-        .load()
+        .load(&context())
         .await
         .expect("load credential must success")
         .unwrap();
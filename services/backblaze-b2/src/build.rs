@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use http::header::AUTHORIZATION;
+use http::request::Parts;
+use http::{HeaderValue, Uri};
+use reqsign_core::{Build, Context};
+
+use crate::Authorization;
+
+/// Builder that signs requests for B2 native API.
+///
+/// The authorization token will be inserted as `Authorization` header.
+/// Requests without scheme and authority like `/b2api/v3/b2_list_buckets`
+/// will be sent to the `api_url` of [`Authorization`], and `/file/...`
+/// downloads to the `download_url`.
+#[derive(Debug, Default)]
+pub struct Builder {}
+
+impl Builder {
+    /// Create a new builder for B2 native API.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl Build for Builder {
+    type Key = Authorization;
+
+    async fn build(
+        &self,
+        _: &Context,
+        req: &mut Parts,
+        key: Option<&Self::Key>,
+        expires_in: Option<Duration>,
+    ) -> Result<()> {
+        if expires_in.is_some() {
+            bail!("presign is not supported by B2 native API");
+        }
+        let Some(auth) = key else {
+            return Ok(());
+        };
+
+        if req.uri.authority().is_none() {
+            let base = if req.uri.path().starts_with("/file/") {
+                &auth.download_url
+            } else {
+                &auth.api_url
+            };
+            let paq = req.uri.path_and_query().map_or("/", |v| v.as_str());
+            req.uri = format!("{}{paq}", base.trim_end_matches('/')).parse::<Uri>()?;
+        }
+
+        let mut value = HeaderValue::try_from(&auth.authorization_token)?;
+        value.set_sensitive(true);
+        req.headers.insert(AUTHORIZATION, value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqsign_core::MockContext;
+
+    #[tokio::test]
+    async fn test_build() -> Result<()> {
+        let ctx = MockContext::new().context();
+        let auth = Authorization {
+            account_id: "account_id".to_string(),
+            authorization_token: "authorization_token".to_string(),
+            api_url: "https://api001.backblazeb2.com".to_string(),
+            download_url: "https://f001.backblazeb2.com".to_string(),
+            expires_in: None,
+        };
+
+        let cases = [
+            (
+                "/b2api/v3/b2_list_buckets?accountId=account_id",
+                "https://api001.backblazeb2.com/b2api/v3/b2_list_buckets?accountId=account_id",
+            ),
+            (
+                "/file/bucket/hello.txt",
+                "https://f001.backblazeb2.com/file/bucket/hello.txt",
+            ),
+            (
+                "https://example.com/file/bucket/hello.txt",
+                "https://example.com/file/bucket/hello.txt",
+            ),
+        ];
+        for (uri, expected) in cases {
+            let (mut parts, _) = http::Request::get(uri).body(())?.into_parts();
+            Builder::new()
+                .build(&ctx, &mut parts, Some(&auth), None)
+                .await?;
+            assert_eq!(parts.uri, expected);
+            assert_eq!(parts.headers[AUTHORIZATION], "authorization_token");
+        }
+
+        let (mut parts, _) = http::Request::get("/file/bucket/hello.txt")
+            .body(())?
+            .into_parts();
+        assert!(Builder::new()
+            .build(&ctx, &mut parts, Some(&auth), Some(Duration::from_secs(60)))
+            .await
+            .is_err());
+        Ok(())
+    }
+}
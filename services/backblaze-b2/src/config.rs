@@ -0,0 +1,64 @@
+use std::fmt;
+
+use reqsign_core::utils::Redact;
+use reqsign_core::Context;
+
+use crate::constants::*;
+
+/// Config for backblaze b2 native API.
+#[derive(Clone)]
+pub struct Config {
+    /// `application_key_id` will be loaded from
+    ///
+    /// - this field if it's `is_some`
+    /// - env value: [`B2_APPLICATION_KEY_ID`]
+    pub application_key_id: Option<String>,
+    /// `application_key` will be loaded from
+    ///
+    /// - this field if it's `is_some`
+    /// - env value: [`B2_APPLICATION_KEY`]
+    pub application_key: Option<String>,
+    /// `endpoint` used to call `b2_authorize_account`.
+    ///
+    /// Default to `https://api.backblazeb2.com`.
+    pub endpoint: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            application_key_id: None,
+            application_key: None,
+            endpoint: B2_AUTHORIZE_ENDPOINT.to_string(),
+        }
+    }
+}
+
+impl fmt::Debug for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Config")
+            .field(
+                "application_key_id",
+                &Redact::from(&self.application_key_id),
+            )
+            .field("application_key", &Redact::from(&self.application_key))
+            .field("endpoint", &self.endpoint)
+            .finish()
+    }
+}
+
+impl Config {
+    /// Load config from env.
+    pub fn from_env(mut self, ctx: &Context) -> Self {
+        let envs = ctx.env_vars();
+
+        if let Some(v) = envs.get(B2_APPLICATION_KEY_ID) {
+            self.application_key_id.get_or_insert(v.clone());
+        }
+        if let Some(v) = envs.get(B2_APPLICATION_KEY) {
+            self.application_key.get_or_insert(v.clone());
+        }
+
+        self
+    }
+}
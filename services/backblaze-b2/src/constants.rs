@@ -0,0 +1,6 @@
+// Env values used in backblaze b2.
+pub const B2_APPLICATION_KEY_ID: &str = "B2_APPLICATION_KEY_ID";
+pub const B2_APPLICATION_KEY: &str = "B2_APPLICATION_KEY";
+
+/// Endpoint of `b2_authorize_account`.
+pub const B2_AUTHORIZE_ENDPOINT: &str = "https://api.backblazeb2.com";
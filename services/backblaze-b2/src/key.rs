@@ -0,0 +1,54 @@
+use std::fmt::{Debug, Formatter};
+
+use reqsign_core::time::{now, DateTime};
+use reqsign_core::utils::Redact;
+use reqsign_core::Key;
+
+/// Authorization returned by `b2_authorize_account`.
+///
+/// B2 native API calls must be sent to `api_url` and downloads to
+/// `download_url`, so they are kept alongside the token.
+#[derive(Clone)]
+pub struct Authorization {
+    /// The account this key belongs to.
+    pub account_id: String,
+    /// Token used in the `Authorization` header of API calls.
+    pub authorization_token: String,
+    /// Base URL for all API calls except downloads.
+    pub api_url: String,
+    /// Base URL for downloading files.
+    pub download_url: String,
+    /// Expiration time for this token, tokens are valid for up to 24 hours.
+    pub expires_in: Option<DateTime>,
+}
+
+impl Debug for Authorization {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Authorization")
+            .field("account_id", &self.account_id)
+            .field(
+                "authorization_token",
+                &Redact::from(&self.authorization_token),
+            )
+            .field("api_url", &self.api_url)
+            .field("download_url", &self.download_url)
+            .field("expires_in", &self.expires_in)
+            .finish()
+    }
+}
+
+impl Key for Authorization {
+    fn is_valid(&self) -> bool {
+        self.is_valid_at(now())
+    }
+
+    fn is_valid_at(&self, now: DateTime) -> bool {
+        if self.authorization_token.is_empty() {
+            return false;
+        }
+        // Take 120s as buffer to avoid edge cases.
+        self.expires_in
+            .map(|v| v > now + chrono::TimeDelta::try_minutes(2).expect("in bounds"))
+            .unwrap_or(true)
+    }
+}
@@ -0,0 +1,12 @@
+//! Backblaze B2 native API signer
+
+mod constants;
+
+mod config;
+pub use config::Config;
+mod key;
+pub use key::Authorization;
+mod build;
+pub use build::Builder;
+mod load;
+pub use load::AuthorizeAccountLoader;
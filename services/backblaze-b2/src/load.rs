@@ -0,0 +1,204 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use bytes::Bytes;
+use http::header::AUTHORIZATION;
+use http::{Method, StatusCode};
+use reqsign_core::{Context, Error, Load};
+use serde::Deserialize;
+
+use crate::{Authorization, Config};
+
+/// AuthorizeAccountLoader will load [`Authorization`] via
+/// [`b2_authorize_account`](https://www.backblaze.com/apidocs/b2-authorize-account).
+///
+/// The returned token is valid for 24 hours, wrap the loader in a
+/// [`reqsign_core::Signer`] to reuse it until then.
+#[derive(Debug)]
+pub struct AuthorizeAccountLoader {
+    config: Arc<Config>,
+}
+
+impl AuthorizeAccountLoader {
+    /// Create a new `AuthorizeAccountLoader` instance.
+    pub fn new(config: Arc<Config>) -> Self {
+        Self { config }
+    }
+}
+
+#[async_trait]
+impl Load for AuthorizeAccountLoader {
+    type Key = Authorization;
+
+    async fn load(&self, ctx: &Context) -> Result<Option<Self::Key>> {
+        let (Some(key_id), Some(key)) = (
+            &self.config.application_key_id,
+            &self.config.application_key,
+        ) else {
+            return Ok(None);
+        };
+
+        let url = format!(
+            "{}/b2api/v3/b2_authorize_account",
+            self.config.endpoint.trim_end_matches('/')
+        );
+        let mut basic = http::HeaderValue::try_from(format!(
+            "Basic {}",
+            STANDARD.encode(format!("{key_id}:{key}"))
+        ))?;
+        basic.set_sensitive(true);
+        let req = http::Request::builder()
+            .method(Method::GET)
+            .uri(url)
+            .header(AUTHORIZATION, basic)
+            .body(Bytes::new())?;
+
+        let resp = ctx.http_send_as_string(req).await?;
+        match resp.status() {
+            StatusCode::OK => {}
+            StatusCode::UNAUTHORIZED => {
+                return Err(
+                    Error::Config(format!("application key is invalid: {}", resp.body())).into(),
+                )
+            }
+            _ => {
+                return Err(Error::Http(format!(
+                    "request to b2_authorize_account failed: {}",
+                    resp.body()
+                ))
+                .into())
+            }
+        }
+
+        let resp: AuthorizeAccountResponse = serde_json::from_str(resp.body())
+            .map_err(|e| Error::Parse(format!("invalid b2_authorize_account response: {e}")))?;
+
+        let storage_api = resp.api_info.storage_api;
+        Ok(Some(Authorization {
+            account_id: resp.account_id,
+            authorization_token: resp.authorization_token,
+            api_url: storage_api.api_url,
+            download_url: storage_api.download_url,
+            expires_in: Some(ctx.now() + chrono::TimeDelta::try_hours(24).expect("in bounds")),
+        }))
+    }
+}
+
+#[derive(Default, Debug, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct AuthorizeAccountResponse {
+    account_id: String,
+    authorization_token: String,
+    api_info: ApiInfo,
+}
+
+#[derive(Default, Debug, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct ApiInfo {
+    storage_api: StorageApi,
+}
+
+#[derive(Default, Debug, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct StorageApi {
+    api_url: String,
+    download_url: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqsign_core::time::now;
+    use reqsign_core::{FixedClock, MockContext};
+
+    const AUTHORIZE_ACCOUNT_RESPONSE: &str = r#"{
+  "accountId": "account_id",
+  "apiInfo": {
+    "storageApi": {
+      "absoluteMinimumPartSize": 5000000,
+      "apiUrl": "https://api001.backblazeb2.com",
+      "downloadUrl": "https://f001.backblazeb2.com",
+      "recommendedPartSize": 100000000,
+      "s3ApiUrl": "https://s3.us-west-001.backblazeb2.com"
+    }
+  },
+  "applicationKeyExpirationTimestamp": null,
+  "authorizationToken": "authorization_token"
+}"#;
+
+    fn config() -> Arc<Config> {
+        Arc::new(Config {
+            application_key_id: Some("key_id".to_string()),
+            application_key: Some("key".to_string()),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_authorize_account_loader() -> Result<()> {
+        let now = now();
+        let mock = MockContext::new().with_response(
+            Method::GET,
+            "https://api.backblazeb2.com/b2api/v3/b2_authorize_account",
+            StatusCode::OK,
+            AUTHORIZE_ACCOUNT_RESPONSE,
+        );
+        let ctx = mock.context().with_clock(FixedClock(now));
+
+        let auth = AuthorizeAccountLoader::new(config())
+            .load(&ctx)
+            .await?
+            .expect("authorization must be loaded");
+        assert_eq!(auth.account_id, "account_id");
+        assert_eq!(auth.authorization_token, "authorization_token");
+        assert_eq!(auth.api_url, "https://api001.backblazeb2.com");
+        assert_eq!(auth.download_url, "https://f001.backblazeb2.com");
+        assert_eq!(
+            auth.expires_in,
+            Some(now + chrono::TimeDelta::try_hours(24).unwrap())
+        );
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 1);
+        // base64("key_id:key")
+        assert_eq!(
+            requests[0].headers()[AUTHORIZATION],
+            "Basic a2V5X2lkOmtleQ=="
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_authorize_account_loader_bad_key() {
+        let mock = MockContext::new().with_response(
+            Method::GET,
+            "https://api.backblazeb2.com/b2api/v3/b2_authorize_account",
+            StatusCode::UNAUTHORIZED,
+            r#"{"code":"unauthorized","message":"","status":401}"#,
+        );
+        let ctx = mock.context();
+
+        let err = AuthorizeAccountLoader::new(config())
+            .load(&ctx)
+            .await
+            .expect_err("bad key must fail");
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Config(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_authorize_account_loader_without_key() -> Result<()> {
+        let mock = MockContext::new();
+        let ctx = mock.context();
+
+        let loader = AuthorizeAccountLoader::new(Arc::new(Config::default()));
+        assert!(loader.load(&ctx).await?.is_none());
+        assert!(mock.requests().is_empty());
+        Ok(())
+    }
+}
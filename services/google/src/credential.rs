@@ -1,3 +1,4 @@
+pub mod authorized_user;
 pub mod external_account;
 pub mod impersonated_service_account;
 pub mod service_account;
@@ -11,20 +12,31 @@ use anyhow::anyhow;
 use anyhow::Result;
 use log::debug;
 
+pub use self::authorized_user::AuthorizedUser;
 pub use self::external_account::ExternalAccount;
 use self::impersonated_service_account::ImpersonatedServiceAccount;
 pub use self::service_account::ServiceAccount;
 use super::constants::GOOGLE_APPLICATION_CREDENTIALS;
 use reqsign_core::hash::base64_decode;
 
-#[derive(Clone, serde::Deserialize)]
-#[cfg_attr(test, derive(Debug))]
+/// The `type` of a credential file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize)]
 #[serde(rename_all = "snake_case")]
-#[allow(clippy::enum_variant_names)]
 pub enum CredentialType {
+    /// `impersonated_service_account`
     ImpersonatedServiceAccount,
+    /// `external_account`
     ExternalAccount,
+    /// `service_account`
     ServiceAccount,
+    /// `authorized_user`
+    AuthorizedUser,
+}
+
+#[derive(serde::Deserialize)]
+struct CredentialFile {
+    #[serde(rename = "type")]
+    ty: CredentialType,
 }
 
 /// A Google API credential file.
@@ -34,30 +46,52 @@ pub struct Credential {
     pub(crate) service_account: Option<ServiceAccount>,
     pub(crate) impersonated_service_account: Option<ImpersonatedServiceAccount>,
     pub(crate) external_account: Option<ExternalAccount>,
+    pub(crate) authorized_user: Option<AuthorizedUser>,
 }
 
 impl Credential {
     /// Deserialize credential file
+    ///
+    /// The credential is dispatched by the `type` field of the file, which
+    /// could be `service_account`, `authorized_user`, `external_account` or
+    /// `impersonated_service_account`.
     pub fn from_slice(v: &[u8]) -> Result<Credential> {
-        let service_account = serde_json::from_slice(v).ok();
-        let impersonated_service_account = serde_json::from_slice(v).ok();
-        let external_account = serde_json::from_slice(v).ok();
-
-        let cred = Credential {
-            service_account,
-            impersonated_service_account,
-            external_account,
-        };
-
-        if cred.service_account.is_none()
-            && cred.impersonated_service_account.is_none()
-            && cred.external_account.is_none()
-        {
-            return Err(anyhow!("Couldn't deserialize credential file"));
+        let file: CredentialFile = serde_json::from_slice(v)
+            .map_err(|err| anyhow!("Couldn't deserialize credential file type: {err}"))?;
+
+        let mut cred = Credential::default();
+        match file.ty {
+            CredentialType::ServiceAccount => {
+                cred.service_account = Some(serde_json::from_slice(v)?);
+            }
+            CredentialType::AuthorizedUser => {
+                cred.authorized_user = Some(serde_json::from_slice(v)?);
+            }
+            CredentialType::ExternalAccount => {
+                cred.external_account = Some(serde_json::from_slice(v)?);
+            }
+            CredentialType::ImpersonatedServiceAccount => {
+                cred.impersonated_service_account = Some(serde_json::from_slice(v)?);
+            }
         }
 
         Ok(cred)
     }
+
+    /// Get the type of this credential.
+    pub fn credential_type(&self) -> Option<CredentialType> {
+        if self.service_account.is_some() {
+            Some(CredentialType::ServiceAccount)
+        } else if self.authorized_user.is_some() {
+            Some(CredentialType::AuthorizedUser)
+        } else if self.external_account.is_some() {
+            Some(CredentialType::ExternalAccount)
+        } else if self.impersonated_service_account.is_some() {
+            Some(CredentialType::ImpersonatedServiceAccount)
+        } else {
+            None
+        }
+    }
 }
 
 /// CredentialLoader will load credential from different methods.
@@ -361,6 +395,63 @@ V08rl535r74rMilnQ37X1/zaKBYyxpfhnd2XXgoCgTM=
         );
     }
 
+    #[test]
+    fn loader_returns_authorized_user() {
+        temp_env::with_vars(
+            vec![(
+                GOOGLE_APPLICATION_CREDENTIALS,
+                Some(format!(
+                    "{}/testdata/test_authorized_user.json",
+                    env::current_dir()
+                        .expect("current_dir must exist")
+                        .to_string_lossy()
+                )),
+            )],
+            || {
+                let cred_loader = CredentialLoader::default();
+
+                let cred = cred_loader.load().expect("credential must exist").unwrap();
+                assert_eq!(cred.credential_type(), Some(CredentialType::AuthorizedUser));
+
+                let cred = cred
+                    .authorized_user
+                    .expect("couldn't deserialize authorized user");
+                assert_eq!("placeholder_client_id", &cred.client_id);
+                assert_eq!("placeholder_client_secret", &cred.client_secret);
+                assert_eq!("placeholder_refresh_token", &cred.refresh_token);
+            },
+        );
+    }
+
+    #[test]
+    fn credential_from_slice_dispatches_by_type() {
+        let cases = vec![
+            (
+                r#"{"type": "service_account", "private_key": "key", "client_email": "email"}"#,
+                Some(CredentialType::ServiceAccount),
+            ),
+            (
+                r#"{"type": "authorized_user", "client_id": "id", "client_secret": "secret", "refresh_token": "token"}"#,
+                Some(CredentialType::AuthorizedUser),
+            ),
+            // Fields of authorized user but typed as service account.
+            (
+                r#"{"type": "service_account", "client_id": "id", "client_secret": "secret", "refresh_token": "token"}"#,
+                None,
+            ),
+            (r#"{"type": "unknown"}"#, None),
+            (r#"{"client_id": "id"}"#, None),
+        ];
+
+        for (content, expected) in cases {
+            let cred = Credential::from_slice(content.as_bytes());
+            match expected {
+                Some(ty) => assert_eq!(cred.unwrap().credential_type(), Some(ty), "{content}"),
+                None => assert!(cred.is_err(), "{content}"),
+            }
+        }
+    }
+
     #[test]
     fn loader_returns_external_account_from_github_oidc() {
         let path = if let Ok(path) = env::var("REQSIGN_GOOGLE_CREDENTIAL_PATH") {
@@ -0,0 +1,28 @@
+//! An authorized user.
+
+use std::fmt::{Debug, Formatter};
+
+use reqsign_core::utils::Redact;
+
+/// Credential created by `gcloud auth application-default login`, which
+/// stores the OAuth2 client and the refresh token of a user.
+#[derive(Clone, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub struct AuthorizedUser {
+    /// The OAuth2 client id.
+    pub client_id: String,
+    /// The OAuth2 client secret.
+    pub client_secret: String,
+    /// The refresh token of the user.
+    pub refresh_token: String,
+}
+
+impl Debug for AuthorizedUser {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthorizedUser")
+            .field("client_id", &self.client_id)
+            .field("client_secret", &Redact::from(&self.client_secret))
+            .field("refresh_token", &Redact::from(&self.refresh_token))
+            .finish()
+    }
+}
@@ -1,8 +1,11 @@
 //! A service account.
 
+use std::fmt::{Debug, Formatter};
+
+use reqsign_core::utils::Redact;
+
 /// Credential is the file which stores service account's client_id and private key.
 #[derive(Clone, serde::Deserialize)]
-#[cfg_attr(test, derive(Debug))]
 #[serde(rename_all = "snake_case")]
 pub struct ServiceAccount {
     /// Private key of credential
@@ -10,3 +13,12 @@ pub struct ServiceAccount {
     /// The client email of credential
     pub client_email: String,
 }
+
+impl Debug for ServiceAccount {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServiceAccount")
+            .field("private_key", &Redact::from(&self.private_key))
+            .field("client_email", &self.client_email)
+            .finish()
+    }
+}
@@ -6,11 +6,18 @@ mod credential;
 pub(crate) use credential::external_account;
 pub use credential::Credential;
 pub use credential::CredentialLoader;
+pub use credential::CredentialType;
 
 mod token;
+pub use token::IdToken;
+pub use token::IdTokenLoader;
+pub use token::ImpersonatedTokenLoader;
 pub use token::Token;
 pub use token::TokenLoad;
 pub use token::TokenLoader;
 
 mod signer;
 pub use signer::Signer;
+
+#[cfg(test)]
+mod testing;
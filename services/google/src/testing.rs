@@ -0,0 +1,48 @@
+use anyhow::Result;
+use http::StatusCode;
+use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpListener;
+
+/// Serve one http request with `status` and `body`, and return the raw request.
+pub(crate) async fn serve_once(
+    listener: TcpListener,
+    status: StatusCode,
+    body: String,
+) -> Result<String> {
+    let (mut stream, _) = listener.accept().await?;
+
+    let mut buf = Vec::new();
+    loop {
+        let mut chunk = [0; 4096];
+        let n = stream.read(&mut chunk).await?;
+        buf.extend_from_slice(&chunk[..n]);
+
+        let req = String::from_utf8_lossy(&buf);
+        if let Some((head, content)) = req.split_once("\r\n\r\n") {
+            let length = head
+                .lines()
+                .find_map(|v| {
+                    v.to_lowercase()
+                        .strip_prefix("content-length: ")
+                        .map(|v| v.to_string())
+                })
+                .and_then(|v| v.parse::<usize>().ok())
+                .unwrap_or_default();
+            if content.len() >= length {
+                break;
+            }
+        }
+        if n == 0 {
+            break;
+        }
+    }
+
+    let resp = format!(
+        "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(resp.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(String::from_utf8_lossy(&buf).to_string())
+}
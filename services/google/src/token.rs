@@ -1,5 +1,11 @@
+mod authorized_user;
 mod external_account;
+mod id_token;
+pub use id_token::IdToken;
+pub use id_token::IdTokenLoader;
 mod impersonated_service_account;
+mod impersonation;
+pub use impersonation::ImpersonatedTokenLoader;
 mod service_account;
 
 use std::fmt::Debug;
@@ -14,6 +20,7 @@ use serde::Deserialize;
 use serde::Serialize;
 
 use super::credential::Credential;
+use super::credential::CredentialLoader;
 use reqsign_core::time::now;
 use reqsign_core::time::DateTime;
 
@@ -111,7 +118,6 @@ pub trait TokenLoad: 'static + Send + Sync + Debug {
 }
 
 /// TokenLoader will load token from different methods.
-#[cfg_attr(test, derive(Debug))]
 pub struct TokenLoader {
     scope: String,
     client: Client,
@@ -124,6 +130,17 @@ pub struct TokenLoader {
     token: Arc<Mutex<Option<(Token, DateTime)>>>,
 }
 
+impl Debug for TokenLoader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TokenLoader")
+            .field("scope", &self.scope)
+            .field("disable_vm_metadata", &self.disable_vm_metadata)
+            .field("service_account", &self.service_account)
+            .field("customized_token_loader", &self.customized_token_loader)
+            .finish_non_exhaustive()
+    }
+}
+
 impl TokenLoader {
     /// Create a new token loader.
     ///
@@ -150,6 +167,25 @@ impl TokenLoader {
         }
     }
 
+    /// Create a new token loader with [Application Default Credentials](https://google.aip.dev/auth/4110).
+    ///
+    /// Credentials will be searched in order:
+    ///
+    /// - the file at env `GOOGLE_APPLICATION_CREDENTIALS`
+    /// - the gcloud well-known file: `~/.config/gcloud/application_default_credentials.json`
+    /// - the GCE metadata server
+    ///
+    /// `service_account`, `authorized_user`, `external_account` and
+    /// `impersonated_service_account` credential files are supported.
+    pub fn from_application_default(scope: &str, client: Client) -> Result<Self> {
+        let loader = Self::new(scope, client);
+
+        match CredentialLoader::default().load()? {
+            Some(cred) => Ok(loader.with_credentials(cred)),
+            None => Ok(loader),
+        }
+    }
+
     /// Set the credential for token loader.
     pub fn with_credentials(mut self, credentials: Credential) -> Self {
         self.credential = Some(credentials);
@@ -213,6 +249,10 @@ impl TokenLoader {
             return Ok(Some(token));
         }
 
+        if let Some(token) = self.load_via_authorized_user().await? {
+            return Ok(Some(token));
+        }
+
         if let Some(token) = self.load_via_impersonated_service_account().await? {
             return Ok(Some(token));
         }
@@ -0,0 +1,59 @@
+use anyhow::bail;
+use anyhow::Result;
+use http::header::CONTENT_TYPE;
+use log::error;
+
+use crate::credential::AuthorizedUser;
+
+use super::Token;
+use super::TokenLoader;
+
+impl TokenLoader {
+    /// Exchange token via the refresh token of an authorized user.
+    ///
+    /// Reference: [Refreshing an access token](https://developers.google.com/identity/protocols/oauth2/web-server#offline)
+    pub(super) async fn load_via_authorized_user(&self) -> Result<Option<Token>> {
+        let Some(cred) = self
+            .credential
+            .as_ref()
+            .and_then(|cred| cred.authorized_user.as_ref())
+        else {
+            return Ok(None);
+        };
+
+        self.refresh_authorized_user(cred).await.map(Some)
+    }
+
+    pub(super) async fn refresh_authorized_user(&self, cred: &AuthorizedUser) -> Result<Token> {
+        let req = serde_json::json!({
+            "grant_type": "refresh_token",
+            "refresh_token": &cred.refresh_token,
+            "client_id": &cred.client_id,
+            "client_secret": &cred.client_secret,
+        });
+
+        let req = serde_json::to_vec(&req)?;
+
+        let resp = self
+            .client
+            .post("https://oauth2.googleapis.com/token")
+            .header(CONTENT_TYPE, "application/json")
+            .body(req)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            error!(
+                "refresh token for authorized user got unexpected response: {:?}",
+                resp
+            );
+            bail!(
+                "refresh token for authorized user failed: {}",
+                resp.text().await?
+            );
+        }
+
+        let token = serde_json::from_slice(&resp.bytes().await?)?;
+        Ok(token)
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Debug;
+use std::fmt::Formatter;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use anyhow::bail;
+use anyhow::Result;
+use http::header;
+use jsonwebtoken::Algorithm;
+use jsonwebtoken::DecodingKey;
+use jsonwebtoken::EncodingKey;
+use jsonwebtoken::Header;
+use jsonwebtoken::Validation;
+use log::error;
+use percent_encoding::utf8_percent_encode;
+use reqsign_core::time::now;
+use reqsign_core::time::DateTime;
+use reqwest::Client;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::constants::GOOG_QUERY_ENCODE_SET;
+use crate::credential::Credential;
+
+/// IdToken is a Google-signed OpenID Connect token for a specific audience.
+///
+/// It's used to call IAP-protected endpoints or authenticated Cloud Run
+/// services, send it as `authorization: Bearer <token>`.
+#[derive(Clone)]
+pub struct IdToken {
+    token: String,
+    expires_at: DateTime,
+}
+
+impl IdToken {
+    /// Create a new id token, the expiry is read from its `exp` claim.
+    ///
+    /// The signature is not verified, the token is expected to come from
+    /// Google directly.
+    pub fn from_jwt(token: &str) -> Result<Self> {
+        #[derive(Deserialize)]
+        struct Exp {
+            exp: i64,
+        }
+
+        let mut validation = Validation::new(Algorithm::RS256);
+        validation.insecure_disable_signature_validation();
+        validation.validate_exp = false;
+        validation.validate_aud = false;
+        validation.required_spec_claims = HashSet::new();
+        let data = jsonwebtoken::decode::<Exp>(token, &DecodingKey::from_secret(&[]), &validation)?;
+
+        let Some(expires_at) = DateTime::from_timestamp(data.claims.exp, 0) else {
+            bail!("invalid exp of id token: {}", data.claims.exp);
+        };
+        Ok(Self {
+            token: token.to_string(),
+            expires_at,
+        })
+    }
+
+    /// Get the id token.
+    pub fn token(&self) -> &str {
+        &self.token
+    }
+
+    /// Get the expiration time of the id token.
+    pub fn expires_at(&self) -> DateTime {
+        self.expires_at
+    }
+}
+
+/// Make sure `token` is redacted for IdToken
+impl Debug for IdToken {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IdToken")
+            .field("token", &"<redacted>")
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+/// Claims is used to request id token via service account.
+///
+/// ```json
+/// {
+///   "iss": "761326798069-r5mljlln1rd4lrbhg75efgigp36m78j5@developer.gserviceaccount.com",
+///   "aud": "https://oauth2.googleapis.com/token",
+///   "target_audience": "https://example.run.app",
+///   "exp": 1328554385,
+///   "iat": 1328550785
+/// }
+/// ```
+#[derive(Debug, Serialize)]
+struct IdTokenClaims {
+    iss: String,
+    aud: String,
+    target_audience: String,
+    exp: u64,
+    iat: u64,
+}
+
+#[derive(Deserialize)]
+struct IdTokenResponse {
+    id_token: String,
+}
+
+/// IdTokenLoader loads id tokens for the audience given on every load.
+///
+/// Tokens are loaded in order from:
+///
+/// - the service account credential, via the JWT assertion with `target_audience`
+/// - the GCE metadata server, via the `identity` endpoint
+///
+/// Tokens are cached per audience and refreshed 2 minutes before they expire.
+///
+/// Reference: [Get an ID token](https://cloud.google.com/docs/authentication/get-id-token)
+pub struct IdTokenLoader {
+    client: Client,
+
+    credential: Option<Credential>,
+    disable_vm_metadata: bool,
+    service_account: Option<String>,
+    token_endpoint: String,
+    metadata_endpoint: String,
+
+    tokens: Arc<Mutex<HashMap<String, IdToken>>>,
+}
+
+impl Debug for IdTokenLoader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IdTokenLoader")
+            .field("disable_vm_metadata", &self.disable_vm_metadata)
+            .field("service_account", &self.service_account)
+            .field("token_endpoint", &self.token_endpoint)
+            .field("metadata_endpoint", &self.metadata_endpoint)
+            .finish_non_exhaustive()
+    }
+}
+
+impl IdTokenLoader {
+    /// Create a new id token loader.
+    pub fn new(client: Client) -> Self {
+        Self {
+            client,
+
+            credential: None,
+            disable_vm_metadata: false,
+            service_account: None,
+            token_endpoint: "https://oauth2.googleapis.com/token".to_string(),
+            metadata_endpoint: "http://metadata.google.internal".to_string(),
+
+            tokens: Arc::default(),
+        }
+    }
+
+    /// Set the credential for id token loader.
+    ///
+    /// Only `service_account` credentials can mint id tokens.
+    pub fn with_credentials(mut self, credentials: Credential) -> Self {
+        self.credential = Some(credentials);
+        self
+    }
+
+    /// Disable vm metadata.
+    pub fn with_disable_vm_metadata(mut self, disable_vm_metadata: bool) -> Self {
+        self.disable_vm_metadata = disable_vm_metadata;
+        self
+    }
+
+    /// Set the service account of the vm metadata, default to `default`.
+    pub fn with_service_account(mut self, service_account: &str) -> Self {
+        self.service_account = Some(service_account.to_string());
+        self
+    }
+
+    /// Set the endpoint of Google OAuth2 token service.
+    pub fn with_token_endpoint(mut self, endpoint: &str) -> Self {
+        self.token_endpoint = endpoint.to_string();
+        self
+    }
+
+    /// Set the endpoint of the GCE metadata server.
+    pub fn with_metadata_endpoint(mut self, endpoint: &str) -> Self {
+        self.metadata_endpoint = endpoint.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// Load id token for the audience, like the url of a Cloud Run service
+    /// or the OAuth client id of IAP.
+    pub async fn load(&self, audience: &str) -> Result<Option<IdToken>> {
+        match self.tokens.lock().expect("lock poisoned").get(audience) {
+            Some(token)
+                if now()
+                    < token.expires_at
+                        - chrono::TimeDelta::try_seconds(2 * 60).expect("in bounds") =>
+            {
+                return Ok(Some(token.clone()))
+            }
+            _ => (),
+        }
+
+        let token = if let Some(token) = self.load_via_service_account(audience).await? {
+            token
+        } else if let Some(token) = self.load_via_vm_metadata(audience).await? {
+            token
+        } else {
+            return Ok(None);
+        };
+
+        self.tokens
+            .lock()
+            .expect("lock poisoned")
+            .insert(audience.to_string(), token.clone());
+        Ok(Some(token))
+    }
+
+    /// Exchange id token via Google OAuth2 Service.
+    ///
+    /// Reference: [Using OAuth 2.0 for Server to Server Applications](https://developers.google.com/identity/protocols/oauth2/service-account#authorizingrequests)
+    async fn load_via_service_account(&self, audience: &str) -> Result<Option<IdToken>> {
+        let Some(cred) = self
+            .credential
+            .as_ref()
+            .and_then(|cred| cred.service_account.as_ref())
+        else {
+            return Ok(None);
+        };
+
+        let current = now().timestamp() as u64;
+        let claims = IdTokenClaims {
+            iss: cred.client_email.clone(),
+            aud: self.token_endpoint.clone(),
+            target_audience: audience.to_string(),
+            exp: current + 3600,
+            iat: current,
+        };
+        let jwt = jsonwebtoken::encode(
+            &Header::new(Algorithm::RS256),
+            &claims,
+            &EncodingKey::from_rsa_pem(cred.private_key.as_bytes())?,
+        )?;
+
+        let resp = self
+            .client
+            .post(&self.token_endpoint)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", &jwt),
+            ])
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            error!("exchange id token got unexpected response: {:?}", resp);
+            bail!("exchange id token failed: {}", resp.text().await?);
+        }
+
+        let resp: IdTokenResponse = serde_json::from_slice(&resp.bytes().await?)?;
+        IdToken::from_jwt(&resp.id_token).map(Some)
+    }
+
+    /// Fetch id token via vm metadata.
+    async fn load_via_vm_metadata(&self, audience: &str) -> Result<Option<IdToken>> {
+        if self.disable_vm_metadata {
+            return Ok(None);
+        }
+
+        // Use `default` if service account not set by user.
+        let service_account = self.service_account.as_deref().unwrap_or("default");
+
+        let url = format!(
+            "{}/computeMetadata/v1/instance/service-accounts/{service_account}/identity?audience={}&format=full",
+            self.metadata_endpoint,
+            utf8_percent_encode(audience, &GOOG_QUERY_ENCODE_SET)
+        );
+
+        let resp = self
+            .client
+            .get(&url)
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            error!("fetch id token got unexpected response: {:?}", resp);
+            bail!("fetch id token failed: {}", resp.text().await?);
+        }
+
+        IdToken::from_jwt(resp.text().await?.trim()).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::StatusCode;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::testing::serve_once;
+
+    /// Build a JWT that expires in `secs` from now.
+    fn jwt(secs: i64) -> Result<String> {
+        let exp = now().timestamp() + secs;
+        Ok(jsonwebtoken::encode(
+            &Header::new(Algorithm::HS256),
+            &serde_json::json!({"aud": "https://example.run.app", "exp": exp}),
+            &EncodingKey::from_secret(b"secret"),
+        )?)
+    }
+
+    #[test]
+    fn test_id_token_from_jwt() -> Result<()> {
+        let token = IdToken::from_jwt(&jwt(3600)?)?;
+        let ttl = (token.expires_at() - now()).num_seconds();
+        assert!((3590..=3600).contains(&ttl));
+
+        assert!(IdToken::from_jwt("not a jwt").is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_id_token_loader_via_service_account() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}/token", listener.local_addr()?);
+        let id_token = jwt(3600)?;
+        let server = tokio::spawn(serve_once(
+            listener,
+            StatusCode::OK,
+            format!(r#"{{"id_token": "{id_token}"}}"#),
+        ));
+
+        let cred =
+            Credential::from_slice(include_bytes!("../../testdata/testbucket_credential.json"))?;
+        let loader = IdTokenLoader::new(Client::new())
+            .with_credentials(cred)
+            .with_disable_vm_metadata(true)
+            .with_token_endpoint(&endpoint);
+
+        let token = loader
+            .load("https://example.run.app")
+            .await?
+            .expect("id token must be loaded");
+        assert_eq!(token.token(), id_token);
+
+        let req = server.await??;
+        assert!(req.starts_with("POST /token HTTP/1.1"));
+        assert!(req.contains("grant_type=urn%3Aietf%3Aparams%3Aoauth%3Agrant-type%3Ajwt-bearer"));
+
+        // The id token is cached per audience, the server is gone now.
+        let token = loader
+            .load("https://example.run.app")
+            .await?
+            .expect("id token must be loaded");
+        assert_eq!(token.token(), id_token);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_id_token_loader_via_vm_metadata() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        let id_token = jwt(3600)?;
+        let server = tokio::spawn(serve_once(listener, StatusCode::OK, id_token.clone()));
+
+        let loader = IdTokenLoader::new(Client::new()).with_metadata_endpoint(&endpoint);
+        let token = loader
+            .load("https://example.run.app")
+            .await?
+            .expect("id token must be loaded");
+        assert_eq!(token.token(), id_token);
+
+        let req = server.await??;
+        assert!(req.starts_with(
+            "GET /computeMetadata/v1/instance/service-accounts/default/identity?audience=https%3A%2F%2Fexample.run.app&format=full HTTP/1.1"
+        ));
+        assert!(req.to_lowercase().contains("metadata-flavor: google"));
+        Ok(())
+    }
+}
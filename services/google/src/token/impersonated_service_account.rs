@@ -14,13 +14,13 @@ use super::TokenLoader;
 #[derive(Clone, Deserialize, Default)]
 #[cfg_attr(test, derive(Debug))]
 #[serde(default, rename_all = "camelCase")]
-struct ImpersonatedToken {
-    access_token: String,
-    expire_time: String,
+pub(super) struct ImpersonatedToken {
+    pub(super) access_token: String,
+    pub(super) expire_time: String,
 }
 
 /// The maximum impersonated token lifetime allowed, 1 hour.
-const MAX_LIFETIME: Duration = Duration::from_secs(3600);
+pub(super) const MAX_LIFETIME: Duration = Duration::from_secs(3600);
 
 impl TokenLoader {
     pub(super) async fn load_via_impersonated_service_account(&self) -> Result<Option<Token>> {
@@ -32,44 +32,14 @@ impl TokenLoader {
             return Ok(None);
         };
 
-        let bearer_auth_token = self.generate_bearer_auth_token(cred).await?;
+        let bearer_auth_token = self
+            .refresh_authorized_user(&cred.source_credentials)
+            .await?;
         self.generate_access_token(cred, bearer_auth_token)
             .await
             .map(Some)
     }
 
-    async fn generate_bearer_auth_token(&self, cred: &ImpersonatedServiceAccount) -> Result<Token> {
-        let req = serde_json::json!({
-            "grant_type": "refresh_token",
-            "refresh_token": &cred.source_credentials.refresh_token,
-            "client_id": &cred.source_credentials.client_id,
-            "client_secret": &cred.source_credentials.client_secret,
-        });
-
-        let req = serde_json::to_vec(&req)?;
-
-        let resp = self
-            .client
-            .post("https://oauth2.googleapis.com/token")
-            .header(CONTENT_TYPE, "application/json")
-            .body(req)
-            .send()
-            .await?;
-
-        if !resp.status().is_success() {
-            error!("bearer token loader for impersonated service account got unexpected response: {:?}", resp);
-            bail!(
-                "bearer token loader for impersonated service account failed: {}",
-                resp.text().await?
-            );
-        }
-
-        let token: Option<Token> = serde_json::from_slice(&resp.bytes().await?)?;
-        let token = token.expect("couldn't parse bearer token response");
-
-        Ok(token)
-    }
-
     async fn generate_access_token(
         &self,
         cred: &ImpersonatedServiceAccount,
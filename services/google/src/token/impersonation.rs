@@ -0,0 +1,236 @@
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::bail;
+use anyhow::Result;
+use async_trait::async_trait;
+use http::header::CONTENT_TYPE;
+use log::error;
+use reqsign_core::time::now;
+use reqsign_core::time::parse_rfc3339;
+use reqsign_core::time::DateTime;
+use reqwest::Client;
+
+use super::impersonated_service_account::ImpersonatedToken;
+use super::impersonated_service_account::MAX_LIFETIME;
+use super::Token;
+use super::TokenLoad;
+use super::TokenLoader;
+
+/// ImpersonatedTokenLoader loads a short-lived token of the target service
+/// account by impersonating it with the token of the source loader.
+///
+/// The impersonated token is cached independently of the source token and
+/// refreshed 2 minutes before it expires.
+///
+/// Reference: [Create a short-lived access token](https://cloud.google.com/iam/docs/create-short-lived-credentials-direct#sa-credentials-oauth)
+#[derive(Debug)]
+pub struct ImpersonatedTokenLoader {
+    source: Box<dyn TokenLoad>,
+    target_principal: String,
+    scopes: Vec<String>,
+    delegates: Vec<String>,
+    lifetime: Duration,
+    endpoint: String,
+
+    token: Arc<Mutex<Option<(Token, DateTime)>>>,
+}
+
+impl ImpersonatedTokenLoader {
+    /// Create a new impersonated token loader.
+    ///
+    /// `target_principal` is the email of the service account to impersonate.
+    pub fn new(source: impl TokenLoad, target_principal: &str, scopes: &[&str]) -> Self {
+        Self {
+            source: Box::new(source),
+            target_principal: target_principal.to_string(),
+            scopes: scopes.iter().map(|v| v.to_string()).collect(),
+            delegates: Vec::new(),
+            lifetime: MAX_LIFETIME,
+            endpoint: "https://iamcredentials.googleapis.com".to_string(),
+
+            token: Arc::default(),
+        }
+    }
+
+    /// Set the delegation chain for multi-hop impersonation.
+    ///
+    /// Every service account in the chain must grant the previous one the
+    /// `roles/iam.serviceAccountTokenCreator` role, the last one is granted
+    /// on the target principal.
+    pub fn with_delegates(mut self, delegates: &[&str]) -> Self {
+        self.delegates = delegates.iter().map(|v| v.to_string()).collect();
+        self
+    }
+
+    /// Set the lifetime of the impersonated token, default to 1 hour.
+    pub fn with_lifetime(mut self, lifetime: Duration) -> Self {
+        self.lifetime = lifetime;
+        self
+    }
+
+    /// Set the endpoint of IAM credentials service.
+    pub fn with_endpoint(mut self, endpoint: &str) -> Self {
+        self.endpoint = endpoint.trim_end_matches('/').to_string();
+        self
+    }
+
+    async fn generate_access_token(&self, client: Client) -> Result<Option<Token>> {
+        let Some(source_token) = self.source.load(client.clone()).await? else {
+            return Ok(None);
+        };
+
+        let req = serde_json::json!({
+            "lifetime": format!("{}s", self.lifetime.as_secs()),
+            "scope": &self.scopes,
+            "delegates": self
+                .delegates
+                .iter()
+                .map(|v| format!("projects/-/serviceAccounts/{v}"))
+                .collect::<Vec<_>>(),
+        });
+
+        let resp = client
+            .post(format!(
+                "{}/v1/projects/-/serviceAccounts/{}:generateAccessToken",
+                self.endpoint, self.target_principal
+            ))
+            .header(CONTENT_TYPE, "application/json")
+            .bearer_auth(source_token.access_token())
+            .body(serde_json::to_vec(&req)?)
+            .send()
+            .await?;
+
+        if !resp.status().is_success() {
+            error!(
+                "impersonate service account got unexpected response: {:?}",
+                resp
+            );
+            bail!("impersonate service account failed: {}", resp.text().await?);
+        }
+
+        let token: ImpersonatedToken = serde_json::from_slice(&resp.bytes().await?)?;
+        let expires_in = (parse_rfc3339(&token.expire_time)? - now())
+            .num_seconds()
+            .max(0) as usize;
+
+        Ok(Some(Token::new(
+            &token.access_token,
+            expires_in,
+            &self.scopes.join(" "),
+        )))
+    }
+}
+
+#[async_trait]
+impl TokenLoad for ImpersonatedTokenLoader {
+    async fn load(&self, client: Client) -> Result<Option<Token>> {
+        match self.token.lock().expect("lock poisoned").clone() {
+            Some((token, expire_in))
+                if now()
+                    < expire_in - chrono::TimeDelta::try_seconds(2 * 60).expect("in bounds") =>
+            {
+                return Ok(Some(token))
+            }
+            _ => (),
+        }
+
+        let Some(token) = self.generate_access_token(client).await? else {
+            return Ok(None);
+        };
+
+        let expire_in =
+            now() + chrono::TimeDelta::try_seconds(token.expires_in() as i64).expect("in bounds");
+        *self.token.lock().expect("lock poisoned") = Some((token.clone(), expire_in));
+
+        Ok(Some(token))
+    }
+}
+
+/// Allow [`TokenLoader`] to be used as the source of other loaders like
+/// [`ImpersonatedTokenLoader`], its own client will be used.
+#[async_trait]
+impl TokenLoad for TokenLoader {
+    async fn load(&self, _: Client) -> Result<Option<Token>> {
+        TokenLoader::load(self).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use http::StatusCode;
+    use reqsign_core::time::format_rfc3339;
+    use tokio::net::TcpListener;
+
+    use super::*;
+    use crate::testing::serve_once;
+
+    #[derive(Debug, Default)]
+    struct StaticTokenLoader {
+        calls: Arc<AtomicUsize>,
+    }
+
+    #[async_trait]
+    impl TokenLoad for StaticTokenLoader {
+        async fn load(&self, _: Client) -> Result<Option<Token>> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            Ok(Some(Token::new("source_token", 3600, "cloud-platform")))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_impersonated_token_loader() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let endpoint = format!("http://{}", listener.local_addr()?);
+        let expire_time = now() + chrono::TimeDelta::try_seconds(1800).expect("in bounds");
+        let server = tokio::spawn(serve_once(
+            listener,
+            StatusCode::OK,
+            format!(
+                r#"{{"accessToken": "impersonated_token", "expireTime": "{}"}}"#,
+                format_rfc3339(expire_time)
+            ),
+        ));
+
+        let source = StaticTokenLoader::default();
+        let source_calls = source.calls.clone();
+        let loader = ImpersonatedTokenLoader::new(
+            source,
+            "target@example.iam.gserviceaccount.com",
+            &["https://www.googleapis.com/auth/cloud-platform"],
+        )
+        .with_delegates(&["delegate@example.iam.gserviceaccount.com"])
+        .with_endpoint(&endpoint);
+
+        let token = loader
+            .load(Client::new())
+            .await?
+            .expect("token must be loaded");
+        assert_eq!(token.access_token(), "impersonated_token");
+        assert!((1790..=1800).contains(&token.expires_in()));
+
+        let req = server.await??;
+        assert!(req.starts_with("POST /v1/projects/-/serviceAccounts/target@example.iam.gserviceaccount.com:generateAccessToken HTTP/1.1"));
+        assert!(req
+            .to_lowercase()
+            .contains("authorization: bearer source_token"));
+        assert!(req.contains(
+            r#""delegates":["projects/-/serviceAccounts/delegate@example.iam.gserviceaccount.com"]"#
+        ));
+        assert!(req.contains(r#""lifetime":"3600s""#));
+
+        // The impersonated token is cached, the server is gone now.
+        let token = loader
+            .load(Client::new())
+            .await?
+            .expect("token must be loaded");
+        assert_eq!(token.access_token(), "impersonated_token");
+        assert_eq!(source_calls.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+}
@@ -1,6 +1,3 @@
-// Headers used in huawei cloud services.
-pub const CONTENT_MD5: &str = "Content-MD5"; // different from azure
-
 // Env values used in huawei cloud services.
 pub const HUAWEI_CLOUD_ACCESS_KEY_ID: &str = "HUAWEI_CLOUD_ACCESS_KEY_ID";
 pub const HUAWEI_CLOUD_SECRET_ACCESS_KEY: &str = "HUAWEI_CLOUD_SECRET_ACCESS_KEY";
@@ -1,13 +1,15 @@
+use std::fmt::Debug;
+use std::fmt::Formatter;
 use std::sync::Arc;
 use std::sync::Mutex;
 
 use anyhow::Result;
+use reqsign_core::utils::Redact;
 
 use super::config::Config;
 
 /// Credential for obs.
 #[derive(Clone)]
-#[cfg_attr(test, derive(Debug))]
 pub struct Credential {
     /// Access key id for obs
     pub access_key_id: String,
@@ -17,6 +19,16 @@ pub struct Credential {
     pub security_token: Option<String>,
 }
 
+impl Debug for Credential {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credential")
+            .field("access_key_id", &Redact::from(&self.access_key_id))
+            .field("secret_access_key", &Redact::from(&self.secret_access_key))
+            .field("security_token", &Redact::from(&self.security_token))
+            .finish()
+    }
+}
+
 /// CredentialLoader will load credential from different methods.
 #[derive(Default)]
 #[cfg_attr(test, derive(Debug))]
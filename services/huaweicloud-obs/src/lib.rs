@@ -2,6 +2,7 @@
 
 mod signer;
 pub use signer::Signer;
+pub use signer::SigningAlgorithm;
 
 mod config;
 pub use config::Config;
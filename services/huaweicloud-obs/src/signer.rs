@@ -1,33 +1,38 @@
 //! Huawei Cloud Object Storage Service (OBS) signer
 use std::collections::HashSet;
 use std::fmt::Debug;
-use std::fmt::Write;
 use std::time::Duration;
 
 use anyhow::Result;
 use http::header::AUTHORIZATION;
-use http::header::CONTENT_TYPE;
 use http::header::DATE;
 use http::HeaderValue;
 use log::debug;
 use once_cell::sync::Lazy;
 use percent_encoding::utf8_percent_encode;
 
-use super::constants::*;
 use super::credential::Credential;
-use reqsign_core::hash::base64_hmac_sha1;
+use reqsign_core::canonical;
+use reqsign_core::canonical::CanonicalRules;
 use reqsign_core::time::format_http_date;
 use reqsign_core::time::now;
 use reqsign_core::time::DateTime;
 use reqsign_core::SigningMethod;
 use reqsign_core::SigningRequest;
 
+/// HMAC algorithm used to calculate the OBS signature.
+///
+/// - [`SigningAlgorithm::HmacSha1`] is the legacy algorithm used by OBS.
+/// - [`SigningAlgorithm::HmacSha256`] is the newer algorithm used by OBS.
+pub use reqsign_core::canonical::HmacAlgorithm as SigningAlgorithm;
+
 /// Signer that implement Huawei Cloud Object Storage Service Authorization.
 ///
 /// - [User Signature Authentication](https://support.huaweicloud.com/intl/en-us/api-obs/obs_04_0009.html)
 #[derive(Debug)]
 pub struct Signer {
     bucket: String,
+    algorithm: SigningAlgorithm,
 
     time: Option<DateTime>,
 }
@@ -37,10 +42,19 @@ impl Signer {
     pub fn new(bucket: &str) -> Self {
         Self {
             bucket: bucket.to_string(),
+            algorithm: SigningAlgorithm::default(),
             time: None,
         }
     }
 
+    /// Specify the HMAC algorithm used to calculate the signature.
+    ///
+    /// Default to [`SigningAlgorithm::HmacSha1`].
+    pub fn with_algorithm(mut self, algorithm: SigningAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
     /// Specify the signing time.
     ///
     /// # Note
@@ -63,8 +77,9 @@ impl Signer {
         let mut ctx = SigningRequest::build(parts)?;
 
         let string_to_sign = string_to_sign(&mut ctx, cred, now, method, &self.bucket)?;
-        let signature =
-            base64_hmac_sha1(cred.secret_access_key.as_bytes(), string_to_sign.as_bytes());
+        let signature = self
+            .algorithm
+            .sign(cred.secret_access_key.as_bytes(), string_to_sign.as_bytes());
 
         match method {
             SigningMethod::Header => {
@@ -147,6 +162,23 @@ impl Signer {
     }
 }
 
+/// Canonicalization rules for Huawei Cloud OBS.
+///
+/// ## Reference
+///
+/// - [Authentication of Signature in a Header](https://support.huaweicloud.com/intl/en-us/api-obs/obs_04_0010.html)
+struct ObsRules;
+
+impl CanonicalRules for ObsRules {
+    fn header_prefix(&self) -> &str {
+        "x-obs-"
+    }
+
+    fn is_sub_resource(&self, key: &str) -> bool {
+        is_sub_resource(key)
+    }
+}
+
 /// Construct string to sign
 ///
 /// ## Format
@@ -170,92 +202,31 @@ fn string_to_sign(
     method: SigningMethod,
     bucket: &str,
 ) -> Result<String> {
-    let mut s = String::new();
-    s.write_str(ctx.method.as_str())?;
-    s.write_str("\n")?;
-    s.write_str(ctx.header_get_or_default(&CONTENT_MD5.parse()?)?)?;
-    s.write_str("\n")?;
-    s.write_str(ctx.header_get_or_default(&CONTENT_TYPE)?)?;
-    s.write_str("\n")?;
-    match method {
-        SigningMethod::Header => {
-            writeln!(&mut s, "{}", format_http_date(now))?;
-        }
-        SigningMethod::Query(expires) => {
-            writeln!(
-                &mut s,
-                "{}",
-                (now + chrono::TimeDelta::from_std(expires).unwrap()).timestamp()
-            )?;
+    // Insert security token
+    if let Some(token) = &cred.security_token {
+        match method {
+            SigningMethod::Header => {
+                ctx.headers.insert("x-obs-security-token", token.parse()?);
+            }
+            SigningMethod::Query(_) => {
+                ctx.query
+                    .push(("security-token".to_string(), token.to_string()));
+            }
         }
     }
 
-    {
-        let headers = canonicalize_header(ctx, method, cred)?;
-        if !headers.is_empty() {
-            writeln!(&mut s, "{headers}",)?;
-        }
-    }
-    write!(
-        &mut s,
-        "{}",
-        canonicalize_resource(ctx, bucket, method, cred)
-    )?;
+    let date = match method {
+        SigningMethod::Header => format_http_date(now),
+        SigningMethod::Query(expires) => (now + chrono::TimeDelta::from_std(expires).unwrap())
+            .timestamp()
+            .to_string(),
+    };
 
+    let s = canonical::string_to_sign(ctx, &ObsRules, &date, bucket)?;
     debug!("string to sign: {}", &s);
     Ok(s)
 }
 
-/// ## Reference
-///
-/// - [Authentication of Signature in a Header](https://support.huaweicloud.com/intl/en-us/api-obs/obs_04_0010.html)
-fn canonicalize_header(
-    ctx: &mut SigningRequest,
-    method: SigningMethod,
-    cred: &Credential,
-) -> Result<String> {
-    if method == SigningMethod::Header {
-        // Insert security token
-        if let Some(token) = &cred.security_token {
-            ctx.headers.insert("x-obs-security-token", token.parse()?);
-        }
-    }
-
-    Ok(SigningRequest::header_to_string(
-        ctx.header_to_vec_with_prefix("x-obs-"),
-        ":",
-        "\n",
-    ))
-}
-
-/// ## Reference
-///
-/// - [Authentication of Signature in a Header](https://support.huaweicloud.com/intl/en-us/api-obs/obs_04_0010.html)
-fn canonicalize_resource(
-    ctx: &mut SigningRequest,
-    bucket: &str,
-    method: SigningMethod,
-    cred: &Credential,
-) -> String {
-    if let SigningMethod::Query(_) = method {
-        // Insert security token
-        if let Some(token) = &cred.security_token {
-            ctx.query
-                .push(("security-token".to_string(), token.to_string()));
-        };
-    }
-
-    let params = ctx.query_to_vec_with_filter(is_sub_resource);
-
-    let params_str = SigningRequest::query_to_string(params, "=", "&");
-
-    if params_str.is_empty() {
-        format!("/{bucket}{}", ctx.path)
-    } else {
-        format!("/{bucket}{}?{params_str}", ctx.path)
-    }
-}
-
 fn is_sub_resource(param: &str) -> bool {
     SUBRESOURCES.contains(param)
 }
@@ -459,4 +430,53 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_sign_round_trip() -> Result<()> {
+        let cred = Credential {
+            access_key_id: "access_key".to_string(),
+            secret_access_key: "123456".to_string(),
+            security_token: Some("security_token".to_string()),
+        };
+        let time = chrono::DateTime::parse_from_rfc2822("Mon, 15 Aug 2022 16:50:12 GMT")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        for algorithm in [SigningAlgorithm::HmacSha1, SigningAlgorithm::HmacSha256] {
+            let req = http::Request::put(
+                "http://bucket.obs.cn-north-4.myhuaweicloud.com/object.txt?acl&abc=def",
+            )
+            .header("Content-Type", "text/plain")
+            .header("x-obs-meta-key", "value")
+            .body(())?;
+            let (mut parts, _) = req.into_parts();
+
+            Signer::new("bucket")
+                .with_algorithm(algorithm)
+                .with_time(time)
+                .sign(&mut parts, &cred)?;
+
+            // Parse back the generated header components.
+            let auth = parts.headers[AUTHORIZATION].to_str()?;
+            let (prefix, rest) = auth.split_once(' ').unwrap();
+            let (ak, signature) = rest.split_once(':').unwrap();
+            assert_eq!(prefix, "OBS");
+            assert_eq!(ak, "access_key");
+            assert_eq!(parts.headers[DATE], "Mon, 15 Aug 2022 16:50:12 GMT");
+            assert_eq!(parts.headers["x-obs-security-token"], "security_token");
+
+            let string_to_sign = "PUT\n\ntext/plain\nMon, 15 Aug 2022 16:50:12 GMT\nx-obs-meta-key:value\nx-obs-security-token:security_token\n/bucket/object.txt?acl";
+            assert_eq!(
+                signature,
+                algorithm.sign(b"123456", string_to_sign.as_bytes())
+            );
+        }
+
+        assert_ne!(
+            SigningAlgorithm::HmacSha1.sign(b"123456", b"content"),
+            SigningAlgorithm::HmacSha256.sign(b"123456", b"content"),
+        );
+
+        Ok(())
+    }
 }
@@ -1,2 +1,5 @@
 // Env values used in oracle cloud infrastructure services.
 pub const ORACLE_CONFIG_PATH: &str = "~/.oci/config";
+
+// Headers used in oracle cloud infrastructure services.
+pub const X_CONTENT_SHA256: &str = "x-content-sha256";
@@ -3,34 +3,64 @@
 use anyhow::{Error, Result};
 use base64::{engine::general_purpose, Engine as _};
 use http::{
-    header::{AUTHORIZATION, DATE},
-    HeaderValue,
+    header::{AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, DATE},
+    HeaderValue, Method,
 };
 use log::debug;
+use rsa::pkcs1::DecodeRsaPrivateKey;
 use rsa::pkcs1v15::SigningKey;
 use rsa::sha2::Sha256;
 use rsa::signature::{SignatureEncoding, Signer};
 use rsa::{pkcs8::DecodePrivateKey, RsaPrivateKey};
 use std::fmt::Write;
 
+use super::constants::X_CONTENT_SHA256;
 use super::credential::Credential;
+use reqsign_core::hash::base64_sha256;
 use reqsign_core::time;
 use reqsign_core::time::DateTime;
 use reqsign_core::SigningRequest;
 
 /// Signer for Oracle Cloud Infrastructure using API Key.
+///
+/// - [Request Signatures](https://docs.oracle.com/en-us/iaas/Content/API/Concepts/signingrequests.htm)
 #[derive(Default)]
-pub struct APIKeySigner {}
+pub struct APIKeySigner {
+    time: Option<DateTime>,
+}
 
 impl APIKeySigner {
+    /// Specify the signing time.
+    ///
+    /// # Note
+    ///
+    /// We should always take current time to sign requests.
+    /// Only use this function for testing.
+    #[cfg(test)]
+    pub fn with_time(mut self, time: DateTime) -> Self {
+        self.time = Some(time);
+        self
+    }
+
     /// Building a signing context.
     fn build(&self, parts: &mut http::request::Parts, cred: &Credential) -> Result<SigningRequest> {
-        let now = time::now();
+        let now = self.time.unwrap_or_else(time::now);
+        // The request target must use the query as sent, take it before
+        // the signing context decodes it.
+        let target = parts
+            .uri
+            .path_and_query()
+            .map_or("/", |v| v.as_str())
+            .to_string();
         let mut ctx = SigningRequest::build(parts)?;
 
-        let string_to_sign = string_to_sign(&mut ctx, now)?;
+        ctx.headers
+            .insert(DATE, HeaderValue::from_str(&time::format_http_date(now))?);
+        let headers = signed_headers(&mut ctx)?;
+
+        let string_to_sign = string_to_sign(&ctx, &target, headers)?;
         let private_key = if let Some(path) = &cred.key_file {
-            RsaPrivateKey::read_pkcs8_pem_file(path)?
+            read_private_key(path)?
         } else {
             return Err(Error::msg("no private key"));
         };
@@ -38,12 +68,10 @@ impl APIKeySigner {
         let signature = signing_key.try_sign(string_to_sign.as_bytes())?;
         let encoded_signature = general_purpose::STANDARD.encode(signature.to_bytes());
 
-        ctx.headers
-            .insert(DATE, HeaderValue::from_str(&time::format_http_date(now))?);
         if let Some(fp) = &cred.fingerprint {
             let mut auth_value = String::new();
             write!(auth_value, "Signature version=\"1\",")?;
-            write!(auth_value, "headers=\"date (request-target) host\",")?;
+            write!(auth_value, "headers=\"{}\",", headers.join(" "))?;
             write!(
                 auth_value,
                 "keyId=\"{}/{}/{}\",",
@@ -51,8 +79,9 @@ impl APIKeySigner {
             )?;
             write!(auth_value, "algorithm=\"rsa-sha256\",")?;
             write!(auth_value, "signature=\"{}\"", encoded_signature)?;
-            ctx.headers
-                .insert(AUTHORIZATION, HeaderValue::from_str(&auth_value)?);
+            let mut auth_value = HeaderValue::from_str(&auth_value)?;
+            auth_value.set_sensitive(true);
+            ctx.headers.insert(AUTHORIZATION, auth_value);
         } else {
             return Err(Error::msg("no fingerprint"));
         }
@@ -61,9 +90,75 @@ impl APIKeySigner {
     }
 
     /// Signing request with header.
+    ///
+    /// Requests with body like `POST`, `PUT` and `PATCH` must carry
+    /// `x-content-sha256` and `content-length`, use
+    /// [`APIKeySigner::sign_with_body`] to fill them from the body.
     pub fn sign(&self, parts: &mut http::request::Parts, cred: &Credential) -> Result<()> {
+        // OCI only adds headers, keep the uri as is instead of rebuilding it
+        // from the decoded query in signing context.
+        let uri = parts.uri.clone();
         let ctx = self.build(parts, cred)?;
-        ctx.apply(parts)
+        parts.headers = ctx.headers;
+        parts.uri = uri;
+        Ok(())
+    }
+
+    /// Signing request with header, `x-content-sha256` and `content-length`
+    /// will be calculated from `body` for requests with body.
+    pub fn sign_with_body(
+        &self,
+        parts: &mut http::request::Parts,
+        body: &[u8],
+        cred: &Credential,
+    ) -> Result<()> {
+        if has_body(&parts.method) {
+            parts.headers.insert(
+                X_CONTENT_SHA256,
+                HeaderValue::from_str(&base64_sha256(body))?,
+            );
+            parts.headers.insert(CONTENT_LENGTH, body.len().into());
+        }
+        self.sign(parts, cred)
+    }
+}
+
+/// OCI signs extra headers for requests with body.
+fn has_body(method: &Method) -> bool {
+    matches!(*method, Method::POST | Method::PUT | Method::PATCH)
+}
+
+/// Get the headers to sign in order, and fill the defaults if needed.
+fn signed_headers(ctx: &mut SigningRequest) -> Result<&'static [&'static str]> {
+    if !has_body(&ctx.method) {
+        return Ok(&["date", "(request-target)", "host"]);
+    }
+
+    if !ctx.headers.contains_key(X_CONTENT_SHA256) || !ctx.headers.contains_key(CONTENT_LENGTH) {
+        return Err(Error::msg(
+            "x-content-sha256 and content-length are required for requests with body",
+        ));
+    }
+    if !ctx.headers.contains_key(CONTENT_TYPE) {
+        ctx.headers
+            .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    }
+    Ok(&[
+        "date",
+        "(request-target)",
+        "host",
+        "content-length",
+        "content-type",
+        "x-content-sha256",
+    ])
+}
+
+/// Load the private key in either PKCS#8 or PKCS#1 PEM.
+fn read_private_key(path: &str) -> Result<RsaPrivateKey> {
+    let pem = std::fs::read_to_string(path)?;
+    match RsaPrivateKey::from_pkcs8_pem(&pem) {
+        Ok(key) => Ok(key),
+        Err(_) => Ok(RsaPrivateKey::from_pkcs1_pem(&pem)?),
     }
 }
 
@@ -76,20 +171,149 @@ impl APIKeySigner {
 /// + "(request-target): {verb} {uri}" + "\n"
 /// + "host: {Host}"
 /// ```
-fn string_to_sign(ctx: &mut SigningRequest, now: DateTime) -> Result<String> {
-    let string_to_sign = {
-        let mut f = String::new();
-        writeln!(f, "date: {}", time::format_http_date(now))?;
-        writeln!(
-            f,
-            "(request-target): {} {}",
-            ctx.method.as_str().to_lowercase(),
-            ctx.path
-        )?;
-        write!(f, "host: {}", ctx.authority)?;
-        f
-    };
-
-    debug!("string to sign: {}", &string_to_sign);
-    Ok(string_to_sign)
+///
+/// Followed by `content-length`, `content-type` and `x-content-sha256`
+/// for requests with body.
+fn string_to_sign(ctx: &SigningRequest, target: &str, headers: &[&str]) -> Result<String> {
+    let mut f = String::new();
+    for (i, header) in headers.iter().enumerate() {
+        if i > 0 {
+            writeln!(f)?;
+        }
+        match *header {
+            "(request-target)" => write!(
+                f,
+                "(request-target): {} {}",
+                ctx.method.as_str().to_lowercase(),
+                target
+            )?,
+            "host" => write!(f, "host: {}", ctx.authority)?,
+            name => write!(f, "{}: {}", name, ctx.headers[name].to_str()?)?,
+        }
+    }
+
+    debug!("string to sign: {}", &f);
+    Ok(f)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rsa::pkcs1v15::{Signature, VerifyingKey};
+    use rsa::signature::Verifier;
+
+    fn test_time() -> DateTime {
+        chrono::DateTime::parse_from_rfc3339("2014-01-05T21:31:40Z")
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+    }
+
+    fn test_credential(key_file: &str) -> Credential {
+        Credential {
+            tenancy:
+                "ocid1.tenancy.oc1..aaaaaaaaba3pv6wkcr4jqae5f15p2b2m2yt2j6rx32uzr4h25vqstifsfdsq"
+                    .to_string(),
+            user: "ocid1.user.oc1..aaaaaaaat5nvwcna5j6aqzjcaty5eqbb6qt2jvpkanghtgdaqedqw3rynjq"
+                .to_string(),
+            key_file: Some(format!(
+                "{}/testdata/{key_file}",
+                env!("CARGO_MANIFEST_DIR")
+            )),
+            fingerprint: Some("20:3b:97:13:55:1c:5b:0d:d3:37:d8:50:4e:c5:3a:34".to_string()),
+            expires_in: None,
+        }
+    }
+
+    /// Verify the signature in authorization header against `string_to_sign`.
+    fn verify(parts: &http::request::Parts, cred: &Credential, string_to_sign: &str) {
+        let auth = parts.headers[AUTHORIZATION].to_str().unwrap();
+        let signature = auth
+            .split("signature=\"")
+            .nth(1)
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap();
+        let signature = Signature::try_from(
+            general_purpose::STANDARD
+                .decode(signature)
+                .unwrap()
+                .as_slice(),
+        )
+        .unwrap();
+
+        let key = read_private_key(cred.key_file.as_deref().unwrap()).unwrap();
+        VerifyingKey::<Sha256>::new(key.to_public_key())
+            .verify(string_to_sign.as_bytes(), &signature)
+            .expect("signature must be valid");
+    }
+
+    /// GET request from the OCI request signatures documentation.
+    #[test]
+    fn test_sign_get() -> Result<()> {
+        let cred = test_credential("test_key.pem");
+        let req = http::Request::get("https://iaas.us-phoenix-1.oraclecloud.com/20160918/instances?availabilityDomain=Pjwf%3A%20PHX-AD-1&compartmentId=ocid1.compartment.oc1..aaaaaaaam3we6vgnherjq5q2idnccdflvjsnog7mlr6rtdb25gilchfeyjxa&displayName=TeamXInstances&volumeId=ocid1.volume.oc1.phx.abyhqljrgvttnlx73nmrwfaux7kcvzfs3s66izvxf2h4lgvyndsdsnoiwr5q").body(())?;
+        let (mut parts, _) = req.into_parts();
+
+        APIKeySigner::default()
+            .with_time(test_time())
+            .sign(&mut parts, &cred)?;
+
+        assert_eq!(parts.headers[DATE], "Sun, 05 Jan 2014 21:31:40 GMT");
+        let auth = parts.headers[AUTHORIZATION].to_str()?;
+        assert!(auth.starts_with(
+            "Signature version=\"1\",headers=\"date (request-target) host\",keyId=\"ocid1.tenancy.oc1..aaaaaaaaba3pv6wkcr4jqae5f15p2b2m2yt2j6rx32uzr4h25vqstifsfdsq/ocid1.user.oc1..aaaaaaaat5nvwcna5j6aqzjcaty5eqbb6qt2jvpkanghtgdaqedqw3rynjq/20:3b:97:13:55:1c:5b:0d:d3:37:d8:50:4e:c5:3a:34\",algorithm=\"rsa-sha256\",signature=\""
+        ));
+        verify(
+            &parts,
+            &cred,
+            "date: Sun, 05 Jan 2014 21:31:40 GMT\n\
+             (request-target): get /20160918/instances?availabilityDomain=Pjwf%3A%20PHX-AD-1&compartmentId=ocid1.compartment.oc1..aaaaaaaam3we6vgnherjq5q2idnccdflvjsnog7mlr6rtdb25gilchfeyjxa&displayName=TeamXInstances&volumeId=ocid1.volume.oc1.phx.abyhqljrgvttnlx73nmrwfaux7kcvzfs3s66izvxf2h4lgvyndsdsnoiwr5q\n\
+             host: iaas.us-phoenix-1.oraclecloud.com",
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign_with_body() -> Result<()> {
+        let cred = test_credential("test_key_pkcs1.pem");
+        let body = br#"{"compartmentId":"ocid1.compartment.oc1..aaaaaaaam3we6vgnherjq5q2idnccdflvjsnog7mlr6rtdb25gilchfeyjxa"}"#;
+        let req = http::Request::post(
+            "https://iaas.us-phoenix-1.oraclecloud.com/20160918/volumeAttachments",
+        )
+        .body(())?;
+        let (mut parts, _) = req.into_parts();
+
+        APIKeySigner::default()
+            .with_time(test_time())
+            .sign_with_body(&mut parts, body, &cred)?;
+
+        assert_eq!(parts.headers[CONTENT_LENGTH], body.len().to_string());
+        assert_eq!(parts.headers[CONTENT_TYPE], "application/json");
+        let sha256 = base64_sha256(body);
+        assert_eq!(parts.headers[X_CONTENT_SHA256], sha256.as_str());
+        assert!(parts.headers[AUTHORIZATION].to_str()?.contains(
+            "headers=\"date (request-target) host content-length content-type x-content-sha256\""
+        ));
+        verify(
+            &parts,
+            &cred,
+            &format!(
+                "date: Sun, 05 Jan 2014 21:31:40 GMT\n\
+                 (request-target): post /20160918/volumeAttachments\n\
+                 host: iaas.us-phoenix-1.oraclecloud.com\n\
+                 content-length: {}\n\
+                 content-type: application/json\n\
+                 x-content-sha256: {sha256}",
+                body.len()
+            ),
+        );
+
+        // Requests with body must carry the body headers.
+        let (mut parts, _) = http::Request::put(
+            "https://objectstorage.us-phoenix-1.oraclecloud.com/n/ns/b/bucket/o/key",
+        )
+        .body(())?
+        .into_parts();
+        assert!(APIKeySigner::default().sign(&mut parts, &cred).is_err());
+        Ok(())
+    }
 }
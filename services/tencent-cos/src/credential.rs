@@ -1,3 +1,5 @@
+use std::fmt::Debug;
+use std::fmt::Formatter;
 use std::fs;
 use std::sync::Arc;
 use std::sync::Mutex;
@@ -16,10 +18,10 @@ use super::config::Config;
 use reqsign_core::time::now;
 use reqsign_core::time::parse_rfc3339;
 use reqsign_core::time::DateTime;
+use reqsign_core::utils::Redact;
 
 /// Credential for cos.
 #[derive(Clone)]
-#[cfg_attr(test, derive(Debug))]
 pub struct Credential {
     /// Secret ID
     pub secret_id: String,
@@ -31,6 +33,17 @@ pub struct Credential {
     pub expires_in: Option<DateTime>,
 }
 
+impl Debug for Credential {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Credential")
+            .field("secret_id", &Redact::from(&self.secret_id))
+            .field("secret_key", &Redact::from(&self.secret_key))
+            .field("security_token", &Redact::from(&self.security_token))
+            .field("expires_in", &self.expires_in)
+            .finish()
+    }
+}
+
 /// CredentialLoader will load credential from different methods.
 #[derive(Default)]
 #[cfg_attr(test, derive(Debug))]
@@ -203,3 +203,110 @@ fn build_signature(
 
     format!("q-sign-algorithm=sha1&q-ak={}&q-sign-time={}&q-key-time={}&q-header-list={}&q-url-param-list={}&q-signature={}", cred.secret_id, key_time, key_time, header_list, param_list, signature)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http::header::CONTENT_LENGTH;
+    use http::header::CONTENT_TYPE;
+    use http::header::HOST;
+    use http::Request;
+
+    fn test_time() -> DateTime {
+        chrono::DateTime::parse_from_rfc2822("Thu, 16 May 2019 06:45:51 GMT")
+            .unwrap()
+            .with_timezone(&chrono::Utc)
+    }
+
+    fn test_credential() -> Credential {
+        Credential {
+            secret_id: "AKIDQjz3ltompVjBni5LitkWHFlFpwkn9U5q".to_string(),
+            secret_key: "BQYIM75p8x0iWVFSIgqEKwFprpRSVHlz".to_string(),
+            security_token: None,
+            expires_in: None,
+        }
+    }
+
+    /// Example from [COS documentation](https://cloud.tencent.com/document/product/436/7778)
+    #[test]
+    fn test_build_signature_cos_example() -> Result<()> {
+        let req = Request::put(
+            "https://examplebucket-1250000000.cos.ap-beijing.myqcloud.com/exampleobject(%E8%85%BE%E8%AE%AF%E4%BA%91)",
+        )
+        .header(DATE, "Thu, 16 May 2019 06:45:51 GMT")
+        .header(HOST, "examplebucket-1250000000.cos.ap-beijing.myqcloud.com")
+        .header(CONTENT_TYPE, "text/plain")
+        .header(CONTENT_LENGTH, "13")
+        .header("Content-MD5", "mQ/fVh815F3k6TAUm8m0eg==")
+        .header("x-cos-acl", "private")
+        .header("x-cos-grant-read", "uin=\"100000000011\"")
+        .body(())?;
+        let (mut parts, _) = req.into_parts();
+        let mut ctx = SigningRequest::build(&mut parts)?;
+
+        let signature = build_signature(
+            &mut ctx,
+            &test_credential(),
+            test_time(),
+            Duration::from_secs(7200),
+        );
+
+        assert_eq!(
+            signature,
+            "q-sign-algorithm=sha1&q-ak=AKIDQjz3ltompVjBni5LitkWHFlFpwkn9U5q&q-sign-time=1557989151;1557996351&q-key-time=1557989151;1557996351&q-header-list=content-length;content-md5;content-type;date;host;x-cos-acl;x-cos-grant-read&q-url-param-list=&q-signature=3b8851a11a569213c17ba8fa7dcf2abec6935172"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign_with_security_token() -> Result<()> {
+        let req = Request::get(
+            "https://examplebucket-1250000000.cos.ap-beijing.myqcloud.com/exampleobject",
+        )
+        .body(())?;
+        let (mut parts, _) = req.into_parts();
+
+        let cred = Credential {
+            security_token: Some("security_token".to_string()),
+            ..test_credential()
+        };
+        Signer::new()
+            .with_time(test_time())
+            .sign(&mut parts, &cred)?;
+
+        let authorization = parts.headers[AUTHORIZATION].to_str()?;
+        assert!(authorization.starts_with(
+            "q-sign-algorithm=sha1&q-ak=AKIDQjz3ltompVjBni5LitkWHFlFpwkn9U5q&q-sign-time=1557989151;1557992751&q-key-time=1557989151;1557992751"
+        ));
+        assert_eq!(parts.headers["x-cos-security-token"], "security_token");
+        assert_eq!(parts.headers[DATE], "Thu, 16 May 2019 06:45:51 GMT");
+        Ok(())
+    }
+
+    #[test]
+    fn test_sign_query_with_security_token() -> Result<()> {
+        let req = Request::get(
+            "https://examplebucket-1250000000.cos.ap-beijing.myqcloud.com/exampleobject",
+        )
+        .body(())?;
+        let (mut parts, _) = req.into_parts();
+
+        let cred = Credential {
+            security_token: Some("security_token".to_string()),
+            ..test_credential()
+        };
+        Signer::new().with_time(test_time()).sign_query(
+            &mut parts,
+            Duration::from_secs(3600),
+            &cred,
+        )?;
+
+        let query = parts.uri.query().unwrap();
+        assert!(query.contains("q-sign-algorithm=sha1"));
+        assert!(query.contains("q-ak=AKIDQjz3ltompVjBni5LitkWHFlFpwkn9U5q"));
+        assert!(query.contains("q-signature="));
+        assert!(query.contains("x-cos-security-token=security%5Ftoken"));
+        assert!(parts.headers.get(AUTHORIZATION).is_none());
+        Ok(())
+    }
+}